@@ -0,0 +1,277 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+
+use specs::{Entities, LazyUpdate};
+
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::falling_block::Gravity;
+use crate::fluid::FluidFlow;
+use crate::redstone::{Button, Lamp, Lever, Repeater, Wire};
+use crate::vegetation::{GrassSpread, LeafDecay, SaplingGrowth};
+
+/// A block update planned for a later physics tick
+#[derive(Debug, Copy, Clone)]
+pub struct ScheduledTick {
+    pub tick: u64,
+    // Ticks due at the same time run by increasing priority
+    pub priority: i32,
+    pub position: (i32, i32, i32),
+    // The tick is dropped if the block changed in the meantime
+    pub block: BlockID,
+    // Ticks with the same time and priority run in the order they were scheduled
+    order: u64,
+}
+
+impl ScheduledTick {
+    fn key(&self) -> (u64, i32, u64) {
+        (self.tick, self.priority, self.order)
+    }
+}
+
+impl PartialEq for ScheduledTick {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ScheduledTick {}
+
+impl PartialOrd for ScheduledTick {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScheduledTick {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// The ticks scheduled in a chunk, a block has at most one pending tick
+#[derive(Default)]
+pub struct TickQueue {
+    ticks: BinaryHeap<Reverse<ScheduledTick>>,
+    positions: HashSet<(i32, i32, i32)>,
+    scheduled: u64,
+}
+
+impl TickQueue {
+    /// Returns false if the block already has a pending tick
+    pub fn schedule(&mut self, tick: u64, priority: i32, position: (i32, i32, i32), block: BlockID) -> bool {
+        if !self.positions.insert(position) {
+            return false;
+        }
+        self.ticks.push(Reverse(ScheduledTick { tick, priority, position, block, order: self.scheduled }));
+        self.scheduled += 1;
+        true
+    }
+
+    /// Takes the next tick due at `current_tick` or before
+    pub fn pop_due(&mut self, current_tick: u64) -> Option<ScheduledTick> {
+        match self.ticks.peek() {
+            Some(Reverse(tick)) if tick.tick <= current_tick => {}
+            _ => return None,
+        }
+        let Reverse(tick) = self.ticks.pop().unwrap();
+        self.positions.remove(&tick.position);
+        Some(tick)
+    }
+
+    pub fn clear(&mut self) {
+        self.ticks.clear();
+        self.positions.clear();
+    }
+}
+
+/// Notification that the block at `from` changed, sent to the block at `position`
+/// A block is notified of its own change with `from` equal to `position`
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub struct BlockUpdate {
+    pub position: (i32, i32, i32),
+    pub from: (i32, i32, i32),
+}
+
+/// The block updates waiting to be processed, in the order they were sent
+#[derive(Default)]
+pub struct BlockUpdateQueue {
+    updates: VecDeque<BlockUpdate>,
+    pending: HashSet<BlockUpdate>,
+}
+
+impl BlockUpdateQueue {
+    /// Notifies the block at (x, y, z) and its six neighbours that it changed
+    pub fn notify_around(&mut self, x: i32, y: i32, z: i32) {
+        let positions = [
+            (x, y, z),
+            (x + 1, y, z), (x - 1, y, z),
+            (x, y + 1, z), (x, y - 1, z),
+            (x, y, z + 1), (x, y, z - 1),
+        ];
+        for &position in &positions {
+            self.push(BlockUpdate { position, from: (x, y, z) });
+        }
+    }
+
+    /// The same update is only queued once until it is processed
+    pub fn push(&mut self, update: BlockUpdate) {
+        if self.pending.insert(update) {
+            self.updates.push_back(update);
+        }
+    }
+
+    /// Takes at most `max` updates, those sent while they are processed wait for the next tick
+    /// This way blocks updating each other endlessly don't freeze the game
+    pub fn take(&mut self, max: usize) -> Vec<BlockUpdate> {
+        let n = max.min(self.updates.len());
+        let updates: Vec<_> = self.updates.drain(..n).collect();
+        for update in &updates {
+            self.pending.remove(update);
+        }
+        updates
+    }
+}
+
+/// What the behaviours can change in the world
+pub struct TickContext<'a> {
+    pub chunk_manager: &'a ChunkManager,
+    pub lazy_update: &'a LazyUpdate,
+    pub entities: &'a Entities<'a>,
+}
+
+/// How a kind of block evolves by itself, every method does nothing by default
+pub trait BlockBehaviour: Send + Sync {
+    /// Called when the block at `from`, next to (x, y, z) or the block itself, changed
+    fn neighbour_changed(&self, _context: &TickContext, _x: i32, _y: i32, _z: i32, _from: (i32, i32, i32)) {}
+
+    /// Called when a tick scheduled with `ChunkManager::schedule_tick` for the block at (x, y, z) is due
+    fn scheduled_tick(&self, _context: &TickContext, _x: i32, _y: i32, _z: i32) {}
+
+    /// Called for blocks picked at random in the loaded chunks, a few every tick
+    fn random_tick(&self, _context: &TickContext, _x: i32, _y: i32, _z: i32) {}
+
+    /// Called when the player right clicks the block at (x, y, z), returns false to place a block against it instead
+    fn used(&self, _context: &TickContext, _x: i32, _y: i32, _z: i32) -> bool {
+        false
+    }
+
+    /// State of the block when the player places it
+    /// `facing` is the horizontal direction the player looks at, (1, 0) for +x
+    fn placed_state(&self, _facing: (i32, i32)) -> u8 {
+        0
+    }
+}
+
+/// The behaviour of every block which has one
+pub struct BlockBehaviours {
+    behaviours: HashMap<BlockID, Box<dyn BlockBehaviour>>,
+}
+
+impl BlockBehaviours {
+    pub fn register(&mut self, block: BlockID, behaviour: Box<dyn BlockBehaviour>) {
+        self.behaviours.insert(block, behaviour);
+    }
+
+    pub fn get(&self, block: BlockID) -> Option<&dyn BlockBehaviour> {
+        self.behaviours.get(&block).map(|behaviour| behaviour.as_ref())
+    }
+
+    pub fn placed_state(&self, block: BlockID, facing: (i32, i32)) -> u8 {
+        self.get(block).map_or(0, |behaviour| behaviour.placed_state(facing))
+    }
+}
+
+impl Default for BlockBehaviours {
+    fn default() -> Self {
+        let mut behaviours = BlockBehaviours {
+            behaviours: HashMap::new(),
+        };
+        behaviours.register(BlockID::GrassBlock, Box::new(GrassSpread));
+        behaviours.register(BlockID::OakLeaves, Box::new(LeafDecay));
+        behaviours.register(BlockID::OakSapling, Box::new(SaplingGrowth));
+        behaviours.register(BlockID::Water, Box::new(FluidFlow));
+        behaviours.register(BlockID::Lava, Box::new(FluidFlow));
+        behaviours.register(BlockID::Sand, Box::new(Gravity));
+        behaviours.register(BlockID::Gravel, Box::new(Gravity));
+        behaviours.register(BlockID::Lever, Box::new(Lever));
+        behaviours.register(BlockID::StoneButton, Box::new(Button));
+        behaviours.register(BlockID::RedstoneWire, Box::new(Wire));
+        behaviours.register(BlockID::Repeater, Box::new(Repeater));
+        behaviours.register(BlockID::RedstoneLamp, Box::new(Lamp));
+        behaviours.register(BlockID::LitRedstoneLamp, Box::new(Lamp));
+        behaviours
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(updates: &[BlockUpdate]) -> Vec<(i32, i32, i32)> {
+        updates.iter().map(|update| update.position).collect()
+    }
+
+    #[test]
+    fn a_block_has_at_most_one_scheduled_tick() {
+        let mut queue = TickQueue::default();
+        assert!(queue.schedule(5, 0, (1, 2, 3), BlockID::Water));
+        assert!(!queue.schedule(2, 0, (1, 2, 3), BlockID::Sand));
+        assert!(queue.schedule(5, 0, (1, 2, 4), BlockID::Water));
+
+        assert_eq!(queue.pop_due(5).map(|tick| (tick.position, tick.block)), Some(((1, 2, 3), BlockID::Water)));
+        // The position can be scheduled again once its tick ran
+        assert!(queue.schedule(6, 0, (1, 2, 3), BlockID::Sand));
+    }
+
+    #[test]
+    fn ticks_are_popped_when_due() {
+        let mut queue = TickQueue::default();
+        queue.schedule(10, 0, (0, 0, 0), BlockID::Water);
+        assert!(queue.pop_due(9).is_none());
+        assert_eq!(queue.pop_due(12).map(|tick| tick.tick), Some(10));
+        assert!(queue.pop_due(12).is_none());
+    }
+
+    #[test]
+    fn ticks_run_by_time_then_priority_then_order() {
+        let mut queue = TickQueue::default();
+        queue.schedule(3, 0, (0, 0, 0), BlockID::Water);
+        queue.schedule(2, 5, (1, 0, 0), BlockID::Water);
+        queue.schedule(3, -1, (2, 0, 0), BlockID::Water);
+        queue.schedule(3, 0, (3, 0, 0), BlockID::Water);
+        queue.schedule(3, -1, (4, 0, 0), BlockID::Water);
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop_due(3)).map(|tick| tick.position.0).collect();
+        assert_eq!(order, vec![1, 2, 4, 0, 3]);
+    }
+
+    #[test]
+    fn take_leaves_the_overflow_for_the_next_call() {
+        let mut queue = BlockUpdateQueue::default();
+        queue.notify_around(0, 0, 0);
+
+        let first = queue.take(4);
+        assert_eq!(positions(&first), vec![(0, 0, 0), (1, 0, 0), (-1, 0, 0), (0, 1, 0)]);
+        let second = queue.take(4);
+        assert_eq!(positions(&second), vec![(0, -1, 0), (0, 0, 1), (0, 0, -1)]);
+        assert!(queue.take(4).is_empty());
+    }
+
+    #[test]
+    fn an_update_is_queued_once_until_processed() {
+        let mut queue = BlockUpdateQueue::default();
+        let update = BlockUpdate { position: (1, 0, 0), from: (0, 0, 0) };
+        queue.push(update);
+        queue.push(update);
+        // The same position notified by another block is a different update
+        queue.push(BlockUpdate { position: (1, 0, 0), from: (2, 0, 0) });
+        assert_eq!(queue.take(10).len(), 2);
+
+        // Sent again while it is processed, it waits for the next call but only once
+        queue.push(update);
+        queue.push(update);
+        assert_eq!(queue.take(10), vec![update]);
+        assert!(queue.take(10).is_empty());
+    }
+}
@@ -0,0 +1,155 @@
+use std::collections::VecDeque;
+use std::time::Instant;
+
+use crate::command::complete;
+use crate::text::{Color, WHITE};
+
+// Name of the player in the messages, there is a single player
+pub const PLAYER_NAME: &str = "Player";
+pub const MAX_INPUT_LENGTH: usize = 256;
+const MAX_MESSAGES: usize = 100;
+const MAX_HISTORY: usize = 100;
+
+pub const ERROR_COLOR: Color = (1.0, 0.33, 0.33, 1.0);
+
+pub struct ChatMessage {
+    pub text: String,
+    pub color: Color,
+    pub received_at: Instant,
+}
+
+/// Candidates for the word being completed, cycled through by pressing tab again
+struct Completion {
+    // The input without the word being completed
+    prefix: String,
+    candidates: Vec<String>,
+    selected: usize,
+}
+
+/// The line being typed in the chat, the lines sent before it and the messages shown above it
+#[derive(Default)]
+pub struct Chat {
+    pub input: String,
+    pub messages: VecDeque<ChatMessage>,
+    history: Vec<String>,
+    // Line of the history shown in the input, None while typing a new line
+    history_index: Option<usize>,
+    completion: Option<Completion>,
+}
+
+impl Chat {
+    /// Starts a new line, "/" when opened to type a command
+    pub fn open(&mut self, input: &str) {
+        self.input = input.to_string();
+        self.history_index = None;
+        self.completion = None;
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        if self.input.chars().count() < MAX_INPUT_LENGTH && !c.is_control() {
+            self.input.push(c);
+            self.completion = None;
+        }
+    }
+
+    pub fn erase_char(&mut self) {
+        self.input.pop();
+        self.completion = None;
+    }
+
+    /// Takes the typed line and adds it to the history, None when it is empty
+    pub fn submit(&mut self) -> Option<String> {
+        let line = std::mem::take(&mut self.input).trim().to_string();
+        self.history_index = None;
+        self.completion = None;
+        if line.is_empty() {
+            return None;
+        }
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        Some(line)
+    }
+
+    /// Shows an older line of the history in the input
+    pub fn history_previous(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => self.history.len() - 1,
+            Some(index) => index.saturating_sub(1),
+        };
+        self.show_history_line(Some(index));
+    }
+
+    /// Shows a newer line of the history in the input, or an empty line after the newest one
+    pub fn history_next(&mut self) {
+        match self.history_index {
+            Some(index) if index + 1 < self.history.len() => self.show_history_line(Some(index + 1)),
+            Some(_) => self.show_history_line(None),
+            None => {}
+        }
+    }
+
+    fn show_history_line(&mut self, index: Option<usize>) {
+        self.history_index = index;
+        self.input = index.map_or(String::new(), |index| self.history[index].clone());
+        self.completion = None;
+    }
+
+    /// Replaces the last word of a command with the next candidate to complete it
+    pub fn complete(&mut self) {
+        if let Some(completion) = &mut self.completion {
+            completion.selected = (completion.selected + 1) % completion.candidates.len();
+        } else {
+            let command = match self.input.strip_prefix('/') {
+                Some(command) => command,
+                None => return,
+            };
+            let candidates = complete(command);
+            if candidates.is_empty() {
+                return;
+            }
+            // Everything up to the start of the last word is kept
+            let word_start = self.input.rfind(char::is_whitespace).map_or(1, |i| i + 1);
+            self.completion = Some(Completion {
+                prefix: self.input[..word_start].to_string(),
+                candidates,
+                selected: 0,
+            });
+        }
+
+        let completion = self.completion.as_ref().unwrap();
+        self.input = format!("{}{}", completion.prefix, completion.candidates[completion.selected]);
+    }
+
+    /// The other candidates of the current completion, to show them above the input
+    pub fn completion_candidates(&self) -> Option<&[String]> {
+        self.completion.as_ref()
+            .filter(|completion| completion.candidates.len() > 1)
+            .map(|completion| completion.candidates.as_slice())
+    }
+
+    pub fn add_message(&mut self, text: &str, color: Color) {
+        for line in text.lines() {
+            self.messages.push_back(ChatMessage {
+                text: line.to_string(),
+                color,
+                received_at: Instant::now(),
+            });
+        }
+        while self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+    }
+
+    pub fn add_info(&mut self, text: &str) {
+        self.add_message(text, WHITE);
+    }
+
+    pub fn add_error(&mut self, text: &str) {
+        self.add_message(text, ERROR_COLOR);
+    }
+}
@@ -2,12 +2,15 @@ use bit_vec::BitVec;
 use rand::{random, Rng};
 use rand::distributions::Standard;
 use rand::prelude::Distribution;
-use std::ptr::null;
 
+use crate::block_tick::TickQueue;
+use crate::chunk_arena::{ArenaAllocation, ChunkDraw, ChunkGeometryArena};
 use crate::chunk_manager::{CHUNK_SIZE, CHUNK_VOLUME};
 use crate::types::TexturePack;
-use crate::shapes::write_unit_cube_to_ptr;
+use crate::fluid::FluidState;
+use crate::shapes::{CROSS_FACES, INDICES_PER_QUAD, PACKED_VERTEX_SIZE, quad_center, VERTICES_PER_QUAD, write_cross_to_ptr, write_unit_cube_to_ptr};
 use parking_lot::RwLock;
+use nalgebra_glm::Vec3;
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum BlockID {
@@ -24,6 +27,19 @@ pub enum BlockID {
     Glass,
     Urss,
     Hitler,
+    CraftingTable,
+    Snow,
+    Water,
+    Lava,
+    OakSapling,
+    Sand,
+    Gravel,
+    Lever,
+    StoneButton,
+    RedstoneWire,
+    Repeater,
+    RedstoneLamp,
+    LitRedstoneLamp,
     Debug,
     Debug2,
 }
@@ -38,7 +54,15 @@ impl BlockID {
         match self {
             &BlockID::Air |
             &BlockID::OakLeaves |
-            &BlockID::Glass => true,
+            &BlockID::Glass |
+            &BlockID::Snow |
+            &BlockID::Water |
+            &BlockID::Lava |
+            &BlockID::OakSapling |
+            &BlockID::Lever |
+            &BlockID::StoneButton |
+            &BlockID::RedstoneWire |
+            &BlockID::Repeater => true,
             _ => false
         }
     }
@@ -50,7 +74,15 @@ impl BlockID {
     pub fn is_transparent_not_air(&self) -> bool {
         match self {
             &BlockID::OakLeaves |
-            &BlockID::Glass => true,
+            &BlockID::Glass |
+            &BlockID::Snow |
+            &BlockID::Water |
+            &BlockID::Lava |
+            &BlockID::OakSapling |
+            &BlockID::Lever |
+            &BlockID::StoneButton |
+            &BlockID::RedstoneWire |
+            &BlockID::Repeater => true,
             _ => false
         }
     }
@@ -58,12 +90,175 @@ impl BlockID {
     pub fn is_transparent_no_leaves(&self) -> bool {
         match self {
             &BlockID::Air |
-            &BlockID::Glass => true,
+            &BlockID::Glass |
+            &BlockID::Snow |
+            &BlockID::Water |
+            &BlockID::Lava |
+            &BlockID::OakSapling |
+            &BlockID::Lever |
+            &BlockID::StoneButton |
+            &BlockID::RedstoneWire |
+            &BlockID::Repeater => true,
             _ => false
         }
     }
+    /// Whether a face of the block is drawn against `neighbour`
+    /// There are no faces between two blocks of the same fluid
+    #[inline]
+    pub fn shows_face_next_to(&self, neighbour: BlockID) -> bool {
+        neighbour.is_transparent() && !(self.is_fluid() && neighbour == *self)
+    }
+    /// Sources and flowing fluids alike, told apart by their block state
+    #[inline]
+    pub fn is_fluid(&self) -> bool {
+        match self {
+            &BlockID::Water |
+            &BlockID::Lava => true,
+            _ => false
+        }
+    }
+    /// Blocks which stop the player and the particles, a layer of snow is walked through
+    #[inline]
+    pub fn has_collision(&self) -> bool {
+        match self {
+            &BlockID::Air |
+            &BlockID::Snow |
+            &BlockID::Water |
+            &BlockID::Lava |
+            &BlockID::OakSapling |
+            &BlockID::Lever |
+            &BlockID::StoneButton |
+            &BlockID::RedstoneWire |
+            &BlockID::Repeater => false,
+            _ => true
+        }
+    }
+    /// Blocks which fall when there is nothing under them
+    #[inline]
+    pub fn has_gravity(&self) -> bool {
+        match self {
+            &BlockID::Sand |
+            &BlockID::Gravel => true,
+            _ => false
+        }
+    }
+    /// Plants and levers are drawn as two crossed quads instead of a cube
+    #[inline]
+    pub fn is_cross_shaped(&self) -> bool {
+        match self {
+            &BlockID::OakSapling |
+            &BlockID::Lever => true,
+            _ => false
+        }
+    }
+    /// Height of the block in eighths of a block, see `fluid_height` for fluids
+    #[inline]
+    pub fn height(&self) -> u32 {
+        match self {
+            &BlockID::Snow |
+            &BlockID::RedstoneWire => 1,
+            &BlockID::StoneButton |
+            &BlockID::Repeater => 2,
+            _ => 8
+        }
+    }
+    /// Maximum number of blocks in a single stack of the inventory
+    #[inline]
+    pub fn max_stack_size(&self) -> u32 {
+        64
+    }
+    /// How long the block resists when broken by hand in survival, None if it can't be broken
+    #[inline]
+    pub fn hardness(&self) -> Option<f32> {
+        match self {
+            &BlockID::Air |
+            &BlockID::OakSapling |
+            &BlockID::RedstoneWire |
+            &BlockID::Repeater => Some(0.0),
+            &BlockID::Bedrock |
+            &BlockID::Water |
+            &BlockID::Lava => None,
+            &BlockID::Snow => Some(0.1),
+            &BlockID::OakLeaves => Some(0.2),
+            &BlockID::Glass |
+            &BlockID::RedstoneLamp |
+            &BlockID::LitRedstoneLamp => Some(0.3),
+            &BlockID::Dirt |
+            &BlockID::Sand |
+            &BlockID::Lever |
+            &BlockID::StoneButton => Some(0.5),
+            &BlockID::GrassBlock |
+            &BlockID::Gravel => Some(0.6),
+            &BlockID::Stone => Some(1.5),
+            &BlockID::Cobblestone |
+            &BlockID::OakLog |
+            &BlockID::OakPlanks => Some(2.0),
+            &BlockID::CraftingTable => Some(2.5),
+            &BlockID::Obsidian => Some(50.0),
+            _ => Some(1.0),
+        }
+    }
+    /// Blocks which are slow to break and drop nothing without a pickaxe
+    #[inline]
+    pub fn requires_pickaxe(&self) -> bool {
+        match self {
+            &BlockID::Stone |
+            &BlockID::Cobblestone |
+            &BlockID::Obsidian => true,
+            _ => false
+        }
+    }
+    /// The block given back when this one is broken or picked, a lit lamp is a lamp
+    #[inline]
+    pub fn item_block(&self) -> BlockID {
+        match self {
+            &BlockID::LitRedstoneLamp => BlockID::RedstoneLamp,
+            _ => *self
+        }
+    }
+    /// The render pass in which the faces of the block are drawn
+    #[inline]
+    pub fn render_layer(&self) -> RenderLayer {
+        match self {
+            &BlockID::OakLeaves |
+            &BlockID::Glass |
+            &BlockID::OakSapling |
+            &BlockID::Lever |
+            &BlockID::RedstoneWire => RenderLayer::Cutout,
+            // Lava has a lowered surface like water, so the faces behind it are kept and it's drawn after them
+            &BlockID::Water |
+            &BlockID::Lava => RenderLayer::Translucent,
+            _ => RenderLayer::Opaque
+        }
+    }
+}
+
+/// Chunk meshes are split in 3 buckets which are drawn in separate passes:
+/// - Opaque: fully opaque textures
+/// - Cutout: textures with fully transparent texels which are discarded (leaves, glass, plants)
+/// - Translucent: textures blended with what is behind them, drawn back to front
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum RenderLayer {
+    Opaque,
+    Cutout,
+    Translucent,
+}
+
+impl RenderLayer {
+    pub const COUNT: usize = 3;
+
+    #[inline]
+    pub fn index(&self) -> usize {
+        match self {
+            RenderLayer::Opaque => 0,
+            RenderLayer::Cutout => 1,
+            RenderLayer::Translucent => 2,
+        }
+    }
 }
 
+pub type PackedQuad = [u32; PACKED_VERTEX_SIZE * VERTICES_PER_QUAD];
+
 impl Distribution<BlockID> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> BlockID {
         match rng.gen_range(1, 4) {
@@ -76,39 +271,6 @@ impl Distribution<BlockID> for Standard {
     }
 }
 
-fn create_vao_vbo() -> (u32, u32) {
-    let mut vao = 0;
-    gl_call!(gl::CreateVertexArrays(1, &mut vao));
-
-    // Position
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
-    gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
-
-    // Texture coords
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
-    gl_call!(gl::VertexArrayAttribFormat(vao, 1, 3 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
-
-    // Normals
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
-    gl_call!(gl::VertexArrayAttribFormat(vao, 2, 3 as i32, gl::FLOAT, gl::FALSE, 6 * std::mem::size_of::<f32>() as u32));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 2, 0));
-
-    // Ambient occlusion
-    gl_call!(gl::EnableVertexArrayAttrib(vao, 3));
-    gl_call!(gl::VertexArrayAttribFormat(vao, 3, 1 as i32, gl::FLOAT, gl::FALSE, 9 * std::mem::size_of::<f32>() as u32));
-    gl_call!(gl::VertexArrayAttribBinding(vao, 3, 0));
-
-    let mut vbo = 0;
-    gl_call!(gl::CreateBuffers(1, &mut vbo));
-    // We intentionally don't initialize the buffer's data store because it's dynamically created
-    // when the chunk is invalidated
-
-    gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, (10 * std::mem::size_of::<f32>()) as i32));
-    (vao, vbo)
-}
-
 pub struct ChunkColumn {
     pub heighest_blocks: RwLock<Box<[u8; 16 * 16]>>,
     pub has_foliage: RwLock<bool>,
@@ -237,14 +399,27 @@ pub struct Chunk {
     pub is_generated: RwLock<bool>,
     pub is_uploaded_to_gpu: RwLock<bool>,
     pub blocks: RwLock<[BlockID; CHUNK_VOLUME as usize]>,
+    // Extra state of every block, like the level of fluids, 0 for most blocks
+    pub block_states: RwLock<[u8; CHUNK_VOLUME as usize]>,
+    pub scheduled_ticks: RwLock<TickQueue>,
     pub number_of_opaque_blocks: RwLock<u32>,
     pub number_of_transparent_blocks: RwLock<u32>,
     pub active_faces: RwLock<BitVec>,
+    // Which faces of the chunk can be seen from which other faces through non-opaque blocks
+    pub visibility: RwLock<ChunkVisibility>,
+    // Set when a block becomes opaque or stops being opaque, the visibility has to be computed again
+    pub is_visibility_outdated: RwLock<bool>,
     pub ao_vertices: RwLock<[[[u8; 4]; 6]; CHUNK_VOLUME as usize]>,
 
-    pub vao: RwLock<u32>,
-    pub vbo: RwLock<u32>,
+    // Faces of the chunk in the geometry arena, none if the chunk has nothing to draw
+    pub allocation: RwLock<Option<ArenaAllocation>>,
     pub vertices_drawn: RwLock<u32>,
+    // Number of faces of each render layer, stored consecutively in the VBO in the order of the layers
+    pub layer_faces: RwLock<[u32; RenderLayer::COUNT]>,
+    // CPU copy of the translucent faces, they are sorted back to front when the camera moves
+    pub translucent_faces: RwLock<Vec<PackedQuad>>,
+    // Block coordinates (relative to the chunk) of the camera the last time the translucent faces were sorted
+    pub translucent_sort_origin: RwLock<Option<(i32, i32, i32)>>,
 }
 
 impl Default for Chunk {
@@ -262,9 +437,16 @@ impl Chunk {
         // self.unload_from_gpu();
         *self.is_generated.write() = false;
         *self.blocks.write() = [BlockID::Air; CHUNK_VOLUME as usize];
+        *self.block_states.write() = [0; CHUNK_VOLUME as usize];
+        self.scheduled_ticks.write().clear();
         *self.number_of_opaque_blocks.write() = 0;
         *self.number_of_transparent_blocks.write() = 0;
         *self.vertices_drawn.write() = 0;
+        *self.visibility.write() = ChunkVisibility::all();
+        *self.is_visibility_outdated.write() = false;
+        *self.layer_faces.write() = [0; RenderLayer::COUNT];
+        self.translucent_faces.write().clear();
+        *self.translucent_sort_origin.write() = None;
     }
 
     /// Creates a chunk where every block is the same
@@ -282,14 +464,20 @@ impl Chunk {
             is_generated: RwLock::new(false),
             is_uploaded_to_gpu: RwLock::new(false),
             blocks: RwLock::new([block; CHUNK_VOLUME as usize]),
+            block_states: RwLock::new([0; CHUNK_VOLUME as usize]),
+            scheduled_ticks: RwLock::new(TickQueue::default()),
             number_of_opaque_blocks: RwLock::new(opaque),
             number_of_transparent_blocks: RwLock::new(transparent),
             active_faces: RwLock::new(BitVec::from_elem(6 * CHUNK_VOLUME as usize, false)),
+            visibility: RwLock::new(ChunkVisibility::all()),
+            is_visibility_outdated: RwLock::new(block.is_opaque()),
             ao_vertices: RwLock::new([[[0; 4]; 6]; CHUNK_VOLUME as usize]),
 
-            vao: RwLock::new(0),
-            vbo: RwLock::new(0),
+            allocation: RwLock::new(None),
             vertices_drawn: RwLock::new(0),
+            layer_faces: RwLock::new([0; RenderLayer::COUNT]),
+            translucent_faces: RwLock::new(Vec::new()),
+            translucent_sort_origin: RwLock::new(None),
         }
     }
 
@@ -310,14 +498,20 @@ impl Chunk {
                 }
                 blocks
             }),
+            block_states: RwLock::new([0; CHUNK_VOLUME as usize]),
+            scheduled_ticks: RwLock::new(TickQueue::default()),
             number_of_opaque_blocks: RwLock::new(16 * 16 * 16),
             number_of_transparent_blocks: RwLock::new(0),
             active_faces: RwLock::new(BitVec::from_elem(6 * CHUNK_VOLUME as usize, false)),
+            visibility: RwLock::new(ChunkVisibility::all()),
+            is_visibility_outdated: RwLock::new(true),
             ao_vertices: RwLock::new([[[0; 4]; 6]; CHUNK_VOLUME as usize]),
 
-            vao: RwLock::new(0),
-            vbo: RwLock::new(0),
+            allocation: RwLock::new(None),
             vertices_drawn: RwLock::new(0),
+            layer_faces: RwLock::new([0; RenderLayer::COUNT]),
+            translucent_faces: RwLock::new(Vec::new()),
+            translucent_sort_origin: RwLock::new(None),
         }
     }
 
@@ -329,6 +523,67 @@ impl Chunk {
         *self.number_of_opaque_blocks.read() + *self.number_of_transparent_blocks.read() == 0
     }
 
+    /// Computes which faces of the chunk are connected by non-opaque blocks
+    /// Every group of connected non-opaque blocks is flood filled and the faces
+    /// of the chunk it touches can all see each other
+    /// https://tomcc.github.io/2014/08/31/visibility-1.html
+    pub fn compute_visibility(&self) {
+        if self.is_empty() {
+            *self.visibility.write() = ChunkVisibility::all();
+            return;
+        }
+        if self.is_fully_opaque() {
+            *self.visibility.write() = ChunkVisibility::none();
+            return;
+        }
+
+        let blocks = self.blocks.read();
+        let mut visibility = ChunkVisibility::none();
+        let mut is_visited = BitVec::from_elem(CHUNK_VOLUME as usize, false);
+        let mut stack = Vec::new();
+
+        for (x, y, z) in BlockIterator::new() {
+            let index = Chunk::chunk_coords_to_array_index(x, y, z);
+            if is_visited[index] || blocks[index].is_opaque() {
+                continue;
+            }
+
+            let mut touched_faces = 0u8;
+            is_visited.set(index, true);
+            stack.push((x as i32, y as i32, z as i32));
+
+            while let Some((x, y, z)) = stack.pop() {
+                touched_faces |= faces_on_chunk_border(x, y, z);
+
+                for (face, &(dx, dy, dz)) in FACE_DIRECTIONS.iter().enumerate() {
+                    let (nx, ny, nz) = (x + dx, y + dy, z + dz);
+                    if nx < 0 || ny < 0 || nz < 0
+                        || nx >= CHUNK_SIZE as i32 || ny >= CHUNK_SIZE as i32 || nz >= CHUNK_SIZE as i32 {
+                        touched_faces |= 1 << face;
+                        continue;
+                    }
+                    let neighbour = Chunk::chunk_coords_to_array_index(nx as u32, ny as u32, nz as u32);
+                    if !is_visited[neighbour] && !blocks[neighbour].is_opaque() {
+                        is_visited.set(neighbour, true);
+                        stack.push((nx, ny, nz));
+                    }
+                }
+            }
+
+            visibility.connect_all(touched_faces);
+        }
+
+        *self.visibility.write() = visibility;
+    }
+
+    /// Computes the visibility again only if the opacity of a block changed since the last time
+    pub fn update_visibility(&self) {
+        let is_outdated = std::mem::replace(&mut *self.is_visibility_outdated.write(), false);
+        if is_outdated {
+            self.compute_visibility();
+        }
+    }
+
     #[inline]
     fn chunk_coords_to_array_index(x: u32, y: u32, z: u32) -> usize {
         (y * (CHUNK_SIZE * CHUNK_SIZE) + z * CHUNK_SIZE + x) as usize
@@ -339,7 +594,19 @@ impl Chunk {
         self.blocks.read()[Chunk::chunk_coords_to_array_index(x, y, z)]
     }
 
-    /// Sets a block at some given coordinates
+    #[inline]
+    pub fn get_block_state(&self, x: u32, y: u32, z: u32) -> u8 {
+        self.block_states.read()[Chunk::chunk_coords_to_array_index(x, y, z)]
+    }
+
+    /// Sets a block and its state at some given coordinates
+    #[inline]
+    pub fn set_block_with_state(&self, block: BlockID, state: u8, x: u32, y: u32, z: u32) {
+        self.set_block(block, x, y, z);
+        self.block_states.write()[Chunk::chunk_coords_to_array_index(x, y, z)] = state;
+    }
+
+    /// Sets a block at some given coordinates, in its default state
     /// The coordinates must be within the chunk size
     #[inline]
     pub fn set_block(&self, block: BlockID, x: u32, y: u32, z: u32) {
@@ -368,48 +635,56 @@ impl Chunk {
             }
         }
 
+        if target.is_opaque() != block.is_opaque() {
+            *self.is_visibility_outdated.write() = true;
+        }
         self.blocks.write()[index] = block;
+        self.block_states.write()[index] = 0;
     }
 
-    pub fn unload_from_gpu(&self) {
+    pub fn unload_from_gpu(&self, arena: &mut ChunkGeometryArena) {
         *self.is_uploaded_to_gpu.write() = false;
-        let vbo = *self.vbo.read();
-        if vbo != 0 {
-            gl_call!(gl::NamedBufferData(vbo,
-                0,
-                null(),
-                gl::DYNAMIC_DRAW));
+        if let Some(allocation) = self.allocation.write().take() {
+            arena.free(allocation);
         }
     }
 
-    pub fn upload_to_gpu(&self, texture_pack: &TexturePack) {
-        if *self.vao.read() == 0 && *self.vbo.read() == 0 {
-            let (vao, vbo) = create_vao_vbo();
-            *self.vao.write() = vao;
-            *self.vbo.write() = vbo;
+    pub fn upload_to_gpu(&self, texture_pack: &TexturePack, arena: &mut ChunkGeometryArena) {
+        let sides_vec = &self.active_faces.read();
+        let ao_vec = &self.ao_vertices.read();
+
+        // Count the faces of each render layer to know where each bucket starts in the arena
+        let mut layer_faces = [0; RenderLayer::COUNT];
+        for (j, (x, y, z)) in BlockIterator::new().enumerate() {
+            let block = self.get_block(x, y, z);
+            if block.is_cross_shaped() {
+                layer_faces[block.render_layer().index()] += CROSS_FACES;
+            } else if block != BlockID::Air {
+                let n_faces = (0..6).filter(|&face| sides_vec[6 * j + face]).count() as u32;
+                layer_faces[block.render_layer().index()] += n_faces;
+            }
         }
+        *self.layer_faces.write() = layer_faces;
+        *self.translucent_sort_origin.write() = None;
 
-        let n_visible_faces = self.active_faces.read().iter().fold(0, |acc, b| acc + b as i32);
+        let n_visible_faces: u32 = layer_faces.iter().sum();
+        let mut allocation = self.allocation.write();
+        if let Some(old_allocation) = allocation.take() {
+            arena.free(old_allocation);
+        }
         if n_visible_faces == 0 {
+            *self.vertices_drawn.write() = 0;
+            self.translucent_faces.write().clear();
             return;
         }
 
-        // Initialize the VBO
-        gl_call!(gl::NamedBufferData(*self.vbo.read(),
-                (6 * 10 * std::mem::size_of::<f32>() * n_visible_faces as usize) as isize,
-                null(),
-                gl::DYNAMIC_DRAW));
+        // Offset (in faces) of the next face of each layer
+        let mut layer_offsets = [0, layer_faces[0], layer_faces[0] + layer_faces[1]];
+        let mut faces = vec![0u32; n_visible_faces as usize * VERTICES_PER_QUAD * PACKED_VERTEX_SIZE];
+        let mut translucent_faces = Vec::with_capacity(layer_faces[RenderLayer::Translucent.index()] as usize);
+        let mut block_faces = [0u32; 6 * PACKED_VERTEX_SIZE * VERTICES_PER_QUAD];
 
-        // Map VBO to virtual memory
-        let vbo_ptr: *mut f32 = gl_call!(gl::MapNamedBuffer(*self.vbo.read(), gl::WRITE_ONLY)) as *mut f32;
-        let mut vbo_offset = 0;
-
-        let mut vertices_drawn = 0;
-        let sides_vec = &self.active_faces.read();
-        let ao_vec = &self.ao_vertices.read();
-        let mut j = 0;
-
-        for (x, y, z) in BlockIterator::new() {
+        for (j, (x, y, z)) in BlockIterator::new().enumerate() {
             let block = self.get_block(x, y, z);
             if block != BlockID::Air {
                 let active_sides = [
@@ -426,16 +701,163 @@ impl Chunk {
                 let uvs = texture_pack.get(&block).unwrap().clone();
                 let uvs = uvs.get_uv_of_every_face();
 
-                let copied_vertices = unsafe { write_unit_cube_to_ptr(vbo_ptr.offset(vbo_offset), x as f32, y as f32, z as f32, uvs, active_sides, ao_block) };
-                // let cube_array = unit_cube_array(x as f32, y as f32, z as f32, uv_bl, uv_tr, active_sides);
-                // gl_call!(gl::NamedBufferSubData(self.vbo, (i * std::mem::size_of::<f32>()) as isize, (cube_array.len() * std::mem::size_of::<f32>()) as isize, cube_array.as_ptr() as *mut c_void));
-                vertices_drawn += copied_vertices;
-                vbo_offset += copied_vertices as isize * 10; // 5 floats per vertex
+                let copied_faces = if block.is_cross_shaped() {
+                    unsafe { write_cross_to_ptr(block_faces.as_mut_ptr(), x, y, z, uvs.0) }
+                } else {
+                    let height = if block.is_fluid() {
+                        self.fluid_height(block, x, y, z)
+                    } else {
+                        block.height()
+                    };
+                    unsafe { write_unit_cube_to_ptr(block_faces.as_mut_ptr(), x, y, z, height, uvs, active_sides, ao_block) }
+                };
+                let copied_size = copied_faces as usize * VERTICES_PER_QUAD * PACKED_VERTEX_SIZE;
+
+                let layer = block.render_layer();
+                if layer == RenderLayer::Translucent {
+                    for face in block_faces[..copied_size].chunks(VERTICES_PER_QUAD * PACKED_VERTEX_SIZE) {
+                        let mut quad: PackedQuad = Default::default();
+                        quad.copy_from_slice(face);
+                        translucent_faces.push(quad);
+                    }
+                }
+
+                let offset = &mut layer_offsets[layer.index()];
+                let start = *offset as usize * VERTICES_PER_QUAD * PACKED_VERTEX_SIZE;
+                faces[start..start + copied_size].copy_from_slice(&block_faces[..copied_size]);
+                *offset += copied_faces;
+            }
+        }
+
+        let new_allocation = arena.allocate(n_visible_faces);
+        arena.write_faces(&new_allocation, 0, &faces);
+        *allocation = Some(new_allocation);
+
+        // The triangles are assembled with the shared index buffer, 6 indices per face
+        *self.vertices_drawn.write() = n_visible_faces * INDICES_PER_QUAD as u32;
+        *self.translucent_faces.write() = translucent_faces;
+    }
+
+    /// Height of the surface of a fluid in eighths of a block, full when the same fluid is above
+    fn fluid_height(&self, fluid: BlockID, x: u32, y: u32, z: u32) -> u32 {
+        if y + 1 < CHUNK_SIZE && self.get_block(x, y + 1, z) == fluid {
+            8
+        } else {
+            FluidState::from_block_state(self.get_block_state(x, y, z)).height()
+        }
+    }
+
+    /// The draw of the faces of one render layer, if the chunk has any
+    /// `origin` is the position of the chunk in the world
+    pub fn layer_draw(&self, layer: RenderLayer, origin: [f32; 3]) -> Option<ChunkDraw> {
+        let allocation = (*self.allocation.read())?;
+        let layer_faces = self.layer_faces.read();
+        let n_faces = layer_faces[layer.index()];
+        if n_faces == 0 {
+            return None;
+        }
+        let first_face: u32 = layer_faces[..layer.index()].iter().sum();
+        Some(ChunkDraw {
+            first_face: allocation.first_face + first_face,
+            n_faces,
+            origin,
+        })
+    }
+
+    /// Sorts the translucent faces from back to front relative to the camera
+    /// and uploads them again if the camera moved to another block since the last sort
+    /// `camera_position` is relative to the origin of the chunk
+    pub fn sort_translucent_faces(&self, camera_position: &Vec3, arena: &ChunkGeometryArena) {
+        let origin = (
+            camera_position.x.floor() as i32,
+            camera_position.y.floor() as i32,
+            camera_position.z.floor() as i32);
+        if *self.translucent_sort_origin.read() == Some(origin) {
+            return;
+        }
+        let allocation = match *self.allocation.read() {
+            Some(allocation) => allocation,
+            None => return,
+        };
+        *self.translucent_sort_origin.write() = Some(origin);
+
+        let mut translucent_faces = self.translucent_faces.write();
+        if translucent_faces.is_empty() {
+            return;
+        }
+        translucent_faces.sort_by(|a, b| {
+            let distance_a = (quad_center(a) - camera_position).norm_squared();
+            let distance_b = (quad_center(b) - camera_position).norm_squared();
+            distance_b.partial_cmp(&distance_a).unwrap_or(std::cmp::Ordering::Equal)
+        });
+
+        let layer_faces = self.layer_faces.read();
+        let first_face: u32 = layer_faces[..RenderLayer::Translucent.index()].iter().sum();
+        let faces = translucent_faces.iter().flatten().copied().collect::<Vec<u32>>();
+        arena.write_faces(&allocation, first_face, &faces);
+    }
+}
+
+// Offset to the neighbouring block/chunk for every face, in the order of the active faces:
+// right, left, top, bottom, front, back
+pub const FACE_DIRECTIONS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+/// Index of the face on the other side of `face` (right <-> left, top <-> bottom, front <-> back)
+#[inline]
+pub fn opposite_face(face: usize) -> usize {
+    face ^ 1
+}
+
+#[inline]
+fn faces_on_chunk_border(x: i32, y: i32, z: i32) -> u8 {
+    let max = CHUNK_SIZE as i32 - 1;
+    let mut faces = 0;
+    if x == max { faces |= 1 << 0; }
+    if x == 0 { faces |= 1 << 1; }
+    if y == max { faces |= 1 << 2; }
+    if y == 0 { faces |= 1 << 3; }
+    if z == max { faces |= 1 << 4; }
+    if z == 0 { faces |= 1 << 5; }
+    faces
+}
+
+/// Symmetric 6x6 matrix telling whether a face of a chunk can be seen from another face
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ChunkVisibility(u64);
+
+impl ChunkVisibility {
+    pub fn none() -> Self {
+        ChunkVisibility(0)
+    }
+
+    pub fn all() -> Self {
+        ChunkVisibility((1 << 36) - 1)
+    }
+
+    #[inline]
+    pub fn can_see_through(&self, from: usize, to: usize) -> bool {
+        self.0 & (1 << (6 * from + to)) != 0
+    }
+
+    /// Connects every pair of faces of the bitmask `faces`
+    pub fn connect_all(&mut self, faces: u8) {
+        for from in 0..6 {
+            if faces & (1 << from) == 0 {
+                continue;
+            }
+            for to in 0..6 {
+                if faces & (1 << to) != 0 {
+                    self.0 |= 1 << (6 * from + to);
+                }
             }
-            j += 1;
         }
-        *self.vertices_drawn.write() = vertices_drawn;
-        gl_call!(gl::UnmapNamedBuffer(*self.vbo.read()));
     }
 }
 
@@ -478,3 +900,95 @@ impl Iterator for BlockIterator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RIGHT: usize = 0;
+    const LEFT: usize = 1;
+
+    fn connected_faces(visibility: &ChunkVisibility) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for from in 0..6 {
+            for to in 0..6 {
+                if visibility.can_see_through(from, to) {
+                    pairs.push((from, to));
+                }
+            }
+        }
+        pairs
+    }
+
+    #[test]
+    fn solid_chunk_connects_no_faces() {
+        let chunk = Chunk::full_of_block(BlockID::Stone);
+        chunk.compute_visibility();
+        assert!(connected_faces(&chunk.visibility.read()).is_empty());
+    }
+
+    #[test]
+    fn empty_chunk_connects_every_face() {
+        let chunk = Chunk::empty();
+        chunk.compute_visibility();
+        assert_eq!(connected_faces(&chunk.visibility.read()).len(), 36);
+        assert_eq!(*chunk.visibility.read(), ChunkVisibility::all());
+    }
+
+    #[test]
+    fn tunnel_connects_only_its_ends() {
+        let chunk = Chunk::full_of_block(BlockID::Stone);
+        for x in 0..CHUNK_SIZE {
+            chunk.set_block(BlockID::Air, x, 8, 8);
+        }
+        chunk.compute_visibility();
+        assert_eq!(connected_faces(&chunk.visibility.read()), vec![(RIGHT, RIGHT), (RIGHT, LEFT), (LEFT, RIGHT), (LEFT, LEFT)]);
+    }
+
+    #[test]
+    fn separate_caves_dont_connect_their_faces() {
+        let chunk = Chunk::full_of_block(BlockID::Stone);
+        // Glass lets the view through like air
+        for y in 0..CHUNK_SIZE {
+            chunk.set_block(BlockID::Glass, 2, y, 2);
+        }
+        for z in 0..CHUNK_SIZE {
+            chunk.set_block(BlockID::Air, 12, 8, z);
+        }
+        chunk.compute_visibility();
+        let visibility = *chunk.visibility.read();
+        assert!(visibility.can_see_through(2, 3));
+        assert!(visibility.can_see_through(4, 5));
+        assert!(!visibility.can_see_through(2, 4));
+        assert!(!visibility.can_see_through(3, 5));
+    }
+
+    #[test]
+    fn connect_all_is_symmetric() {
+        let mut visibility = ChunkVisibility::none();
+        visibility.connect_all(0b100100);
+        assert_eq!(connected_faces(&visibility), vec![(2, 2), (2, 5), (5, 2), (5, 5)]);
+    }
+
+    #[test]
+    fn visibility_is_only_computed_again_when_the_opacity_changes() {
+        let chunk = Chunk::full_of_block(BlockID::Stone);
+        chunk.update_visibility();
+        assert_eq!(*chunk.visibility.read(), ChunkVisibility::none());
+
+        // Replacing an opaque block by another one doesn't change what can be seen
+        chunk.set_block(BlockID::Dirt, 0, 0, 0);
+        assert!(!*chunk.is_visibility_outdated.read());
+
+        for x in 0..CHUNK_SIZE {
+            chunk.set_block(BlockID::Air, x, 8, 8);
+        }
+        assert!(*chunk.is_visibility_outdated.read());
+        chunk.update_visibility();
+        assert!(chunk.visibility.read().can_see_through(RIGHT, LEFT));
+
+        // Neither does a transparent block replacing air
+        chunk.set_block(BlockID::Glass, 4, 8, 8);
+        assert!(!*chunk.is_visibility_outdated.read());
+    }
+}
@@ -0,0 +1,356 @@
+use std::os::raw::c_void;
+use std::ptr::null;
+
+use crate::chunk_manager::CHUNK_VOLUME;
+use crate::shapes::{INDICES_PER_QUAD, PACKED_VERTEX_SIZE, quad_indices, VERTICES_PER_QUAD};
+
+// Size of the vertex buffer when it is first created, it doubles whenever it runs out of space
+const INITIAL_ARENA_CAPACITY: u32 = 1 << 18; // In faces
+const FACE_SIZE: usize = PACKED_VERTEX_SIZE * VERTICES_PER_QUAD * std::mem::size_of::<u32>();
+
+// Binding point of the chunk offsets in voxel.vert
+const CHUNK_OFFSETS_BINDING: u32 = 0;
+
+/// Range of faces owned by a chunk in the vertex buffer of the arena
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct ArenaAllocation {
+    pub first_face: u32,
+    pub n_faces: u32,
+}
+
+/// Layout expected by glMultiDrawElementsIndirect
+/// Elements rather than arrays, so that a face is 4 vertices and the shared index buffer makes its 2 triangles,
+/// glMultiDrawArraysIndirect would need 6 vertices per face and half as much geometry again in the arena
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+struct DrawElementsIndirectCommand {
+    count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    base_instance: u32,
+}
+
+/// A draw of a range of faces translated by the origin of its chunk
+pub struct ChunkDraw {
+    pub first_face: u32,
+    pub n_faces: u32,
+    pub origin: [f32; 3],
+}
+
+/// Keeps track of which faces of a buffer are used, without touching the buffer itself
+#[derive(Debug, Default)]
+pub struct FaceAllocator {
+    capacity: u32,
+    // Unused ranges of the buffer as (first face, number of faces), sorted and never adjacent
+    free_ranges: Vec<(u32, u32)>,
+}
+
+impl FaceAllocator {
+    pub fn new(capacity: u32) -> Self {
+        FaceAllocator {
+            capacity,
+            free_ranges: vec![(0, capacity)],
+        }
+    }
+
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// Reserves `n_faces` faces in the first free range large enough, None if there is none
+    pub fn allocate(&mut self, n_faces: u32) -> Option<ArenaAllocation> {
+        assert!(n_faces > 0);
+        let index = self.free_ranges.iter().position(|&(_, size)| size >= n_faces)?;
+        let (first_face, size) = self.free_ranges[index];
+        if size == n_faces {
+            self.free_ranges.remove(index);
+        } else {
+            self.free_ranges[index] = (first_face + n_faces, size - n_faces);
+        }
+        Some(ArenaAllocation { first_face, n_faces })
+    }
+
+    /// Gives the faces back, merging them with the neighbouring free ranges
+    pub fn free(&mut self, allocation: ArenaAllocation) {
+        let ArenaAllocation { first_face, n_faces } = allocation;
+        debug_assert!(first_face + n_faces <= self.capacity, "{:?} is outside of the buffer", allocation);
+        debug_assert!(self.free_ranges.iter().all(|&(start, size)| first_face + n_faces <= start || start + size <= first_face),
+                      "{:?} overlaps a free range, it was freed twice", allocation);
+        let index = self.free_ranges.iter()
+            .position(|&(start, _)| start > first_face)
+            .unwrap_or(self.free_ranges.len());
+        self.free_ranges.insert(index, (first_face, n_faces));
+
+        // Merge with the next range
+        if index + 1 < self.free_ranges.len() {
+            let (start, size) = self.free_ranges[index];
+            let (next_start, next_size) = self.free_ranges[index + 1];
+            if start + size == next_start {
+                self.free_ranges[index].1 += next_size;
+                self.free_ranges.remove(index + 1);
+            }
+        }
+        // Merge with the previous range
+        if index > 0 {
+            let (previous_start, previous_size) = self.free_ranges[index - 1];
+            let (start, size) = self.free_ranges[index];
+            if previous_start + previous_size == start {
+                self.free_ranges[index - 1].1 += size;
+                self.free_ranges.remove(index);
+            }
+        }
+    }
+
+    /// Doubles the capacity until `n_faces` more faces fit at the end, the allocations stay where they are
+    /// Returns the new capacity
+    pub fn grow(&mut self, n_faces: u32) -> u32 {
+        let old_capacity = self.capacity;
+        let mut new_capacity = old_capacity.max(1) * 2;
+        while new_capacity - old_capacity < n_faces {
+            new_capacity *= 2;
+        }
+        self.capacity = new_capacity;
+        self.free(ArenaAllocation { first_face: old_capacity, n_faces: new_capacity - old_capacity });
+        new_capacity
+    }
+}
+
+/// Stores the geometry of every chunk in one big vertex buffer, so that all of the
+/// chunks of a render layer can be drawn with a single shared VAO and one indirect draw call
+/// The GL objects are only created the first time the arena is used on the render thread
+#[derive(Default)]
+pub struct ChunkGeometryArena {
+    vao: u32,
+    vbo: u32,
+    ebo: u32,
+    indirect_buffer: u32,
+    chunk_offsets_buffer: u32,
+    faces: FaceAllocator,
+}
+
+impl ChunkGeometryArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn initialize(&mut self) {
+        if self.vao != 0 {
+            return;
+        }
+
+        gl_call!(gl::CreateVertexArrays(1, &mut self.vao));
+        // Packed vertex (see shapes::pack_voxel_vertex)
+        gl_call!(gl::EnableVertexArrayAttrib(self.vao, 0));
+        gl_call!(gl::VertexArrayAttribIFormat(self.vao, 0, PACKED_VERTEX_SIZE as i32, gl::UNSIGNED_INT, 0));
+        gl_call!(gl::VertexArrayAttribBinding(self.vao, 0, 0));
+
+        // Every chunk quad uses the same 6 indices (relative to its first vertex),
+        // so a single index buffer large enough for the biggest possible chunk is shared by all of them
+        let indices = quad_indices(6 * CHUNK_VOLUME);
+        gl_call!(gl::CreateBuffers(1, &mut self.ebo));
+        gl_call!(gl::NamedBufferData(self.ebo,
+                (indices.len() * std::mem::size_of::<u32>()) as isize,
+                indices.as_ptr() as *const c_void,
+                gl::STATIC_DRAW));
+        gl_call!(gl::VertexArrayElementBuffer(self.vao, self.ebo));
+
+        gl_call!(gl::CreateBuffers(1, &mut self.indirect_buffer));
+        gl_call!(gl::CreateBuffers(1, &mut self.chunk_offsets_buffer));
+
+        self.vbo = ChunkGeometryArena::create_vertex_buffer(INITIAL_ARENA_CAPACITY);
+        self.faces = FaceAllocator::new(INITIAL_ARENA_CAPACITY);
+        self.bind_vertex_buffer();
+    }
+
+    fn create_vertex_buffer(capacity: u32) -> u32 {
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+        gl_call!(gl::NamedBufferStorage(vbo,
+                (capacity as usize * FACE_SIZE) as isize,
+                null(),
+                gl::DYNAMIC_STORAGE_BIT));
+        vbo
+    }
+
+    fn bind_vertex_buffer(&self) {
+        gl_call!(gl::VertexArrayVertexBuffer(self.vao, 0, self.vbo, 0, (PACKED_VERTEX_SIZE * std::mem::size_of::<u32>()) as i32));
+    }
+
+    /// Reserves `n_faces` faces, first fit
+    /// The vertex buffer is grown if none of the free ranges is large enough
+    pub fn allocate(&mut self, n_faces: u32) -> ArenaAllocation {
+        self.initialize();
+        match self.faces.allocate(n_faces) {
+            Some(allocation) => allocation,
+            None => {
+                self.grow(n_faces);
+                self.faces.allocate(n_faces).unwrap()
+            }
+        }
+    }
+
+    /// Gives the faces back to the arena
+    pub fn free(&mut self, allocation: ArenaAllocation) {
+        self.faces.free(allocation);
+    }
+
+    /// Replaces the vertex buffer with one at least twice as large and copies the geometry over
+    fn grow(&mut self, n_faces: u32) {
+        let old_capacity = self.faces.capacity();
+        let new_capacity = self.faces.grow(n_faces);
+        info!("Growing the chunk geometry arena from {} to {} faces", old_capacity, new_capacity);
+
+        let new_vbo = ChunkGeometryArena::create_vertex_buffer(new_capacity);
+        gl_call!(gl::CopyNamedBufferSubData(self.vbo, new_vbo, 0, 0, (old_capacity as usize * FACE_SIZE) as isize));
+        gl_call!(gl::DeleteBuffers(1, &self.vbo));
+        self.vbo = new_vbo;
+        self.bind_vertex_buffer();
+    }
+
+    /// Copies packed faces into an allocation, starting `offset` faces after its beginning
+    pub fn write_faces(&self, allocation: &ArenaAllocation, offset: u32, faces: &[u32]) {
+        let size = faces.len() / (PACKED_VERTEX_SIZE * VERTICES_PER_QUAD);
+        assert!(offset as usize + size <= allocation.n_faces as usize);
+        if size == 0 {
+            return;
+        }
+        gl_call!(gl::NamedBufferSubData(self.vbo,
+                ((allocation.first_face + offset) as usize * FACE_SIZE) as isize,
+                (faces.len() * std::mem::size_of::<u32>()) as isize,
+                faces.as_ptr() as *const c_void));
+    }
+
+    /// Draws every range of faces with a single glMultiDrawElementsIndirect, in order
+    /// Indexed, so that each face only stores its 4 vertices (see `DrawElementsIndirectCommand`)
+    /// The origin of each chunk is read by voxel.vert from a shader storage buffer indexed with the draw ID
+    pub fn draw(&mut self, draws: &[ChunkDraw]) {
+        if draws.is_empty() {
+            return;
+        }
+        self.initialize();
+
+        let commands = draws.iter()
+            .map(|draw| DrawElementsIndirectCommand {
+                count: draw.n_faces * INDICES_PER_QUAD as u32,
+                instance_count: 1,
+                first_index: 0,
+                base_vertex: (draw.first_face as usize * VERTICES_PER_QUAD) as i32,
+                base_instance: 0,
+            })
+            .collect::<Vec<_>>();
+        // vec4 because of the std430 array stride
+        let chunk_offsets = draws.iter()
+            .map(|draw| [draw.origin[0], draw.origin[1], draw.origin[2], 0.0])
+            .collect::<Vec<_>>();
+
+        // Orphan the buffers of the previous draw instead of waiting for the GPU to be done with them
+        gl_call!(gl::NamedBufferData(self.indirect_buffer,
+                (commands.len() * std::mem::size_of::<DrawElementsIndirectCommand>()) as isize,
+                commands.as_ptr() as *const c_void,
+                gl::STREAM_DRAW));
+        gl_call!(gl::NamedBufferData(self.chunk_offsets_buffer,
+                (chunk_offsets.len() * std::mem::size_of::<[f32; 4]>()) as isize,
+                chunk_offsets.as_ptr() as *const c_void,
+                gl::STREAM_DRAW));
+
+        gl_call!(gl::BindVertexArray(self.vao));
+        gl_call!(gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, CHUNK_OFFSETS_BINDING, self.chunk_offsets_buffer));
+        gl_call!(gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.indirect_buffer));
+        gl_call!(gl::MultiDrawElementsIndirect(
+            gl::TRIANGLES,
+            gl::UNSIGNED_INT,
+            null(),
+            commands.len() as i32,
+            0));
+        gl_call!(gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, 0));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allocation(first_face: u32, n_faces: u32) -> ArenaAllocation {
+        ArenaAllocation { first_face, n_faces }
+    }
+
+    #[test]
+    fn allocates_first_fit() {
+        let mut faces = FaceAllocator::new(100);
+        assert_eq!(faces.allocate(10), Some(allocation(0, 10)));
+        assert_eq!(faces.allocate(20), Some(allocation(10, 20)));
+        assert_eq!(faces.allocate(70), Some(allocation(30, 70)));
+        assert_eq!(faces.allocate(1), None);
+        assert!(faces.free_ranges.is_empty());
+    }
+
+    #[test]
+    fn reuses_freed_ranges() {
+        let mut faces = FaceAllocator::new(100);
+        let first = faces.allocate(10).unwrap();
+        let second = faces.allocate(10).unwrap();
+        faces.free(first);
+        // Too large for the hole, taken after the second allocation
+        assert_eq!(faces.allocate(20), Some(allocation(20, 20)));
+        assert_eq!(faces.allocate(4), Some(allocation(0, 4)));
+        assert_eq!(faces.allocate(6), Some(allocation(4, 6)));
+        faces.free(second);
+        assert_eq!(faces.allocate(10), Some(second));
+    }
+
+    #[test]
+    fn merges_freed_ranges_with_both_neighbours() {
+        let mut faces = FaceAllocator::new(30);
+        let first = faces.allocate(10).unwrap();
+        let second = faces.allocate(10).unwrap();
+        let third = faces.allocate(10).unwrap();
+
+        faces.free(first);
+        faces.free(third);
+        assert_eq!(faces.free_ranges, vec![(0, 10), (20, 10)]);
+        faces.free(second);
+        assert_eq!(faces.free_ranges, vec![(0, 30)]);
+        assert_eq!(faces.allocate(30), Some(allocation(0, 30)));
+    }
+
+    #[test]
+    fn grows_by_doubling_and_keeps_the_allocations() {
+        let mut faces = FaceAllocator::new(16);
+        let kept = faces.allocate(10).unwrap();
+        assert_eq!(faces.allocate(10), None);
+
+        assert_eq!(faces.grow(10), 32);
+        // The free end of the old buffer is merged with the new space
+        assert_eq!(faces.free_ranges, vec![(10, 22)]);
+        assert_eq!(faces.allocate(10), Some(allocation(10, 10)));
+
+        // Doubles as many times as needed
+        assert_eq!(faces.grow(100), 256);
+        assert_eq!(faces.capacity(), 256);
+        assert_eq!(faces.allocate(100), Some(allocation(20, 100)));
+        faces.free(kept);
+        assert_eq!(faces.free_ranges, vec![(0, 10), (120, 136)]);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn double_free_is_caught() {
+        let mut faces = FaceAllocator::new(100);
+        let allocation = faces.allocate(10).unwrap();
+        faces.allocate(10).unwrap();
+        faces.free(allocation);
+        faces.free(allocation);
+    }
+
+    #[test]
+    #[should_panic]
+    #[cfg(debug_assertions)]
+    fn overlapping_free_is_caught() {
+        let mut faces = FaceAllocator::new(100);
+        faces.allocate(20).unwrap();
+        faces.free(allocation(0, 10));
+        faces.free(allocation(5, 10));
+    }
+}
@@ -1,22 +1,33 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use nalgebra::Matrix4;
-use nalgebra_glm::{Mat4, vec3};
+use nalgebra_glm::{Vec3, vec3};
 
+use crate::aabb::AABB;
 use crate::ambient_occlusion::compute_ao_of_block;
-use crate::chunk::{BlockID, Chunk, ChunkColumn};
-use crate::shader_compilation::ShaderProgram;
+use crate::block_tick::BlockUpdateQueue;
+use crate::chunk_arena::ChunkGeometryArena;
+use crate::chunk::{BlockID, Chunk, ChunkColumn, RenderLayer, FACE_DIRECTIONS, opposite_face};
+use crate::constants::RENDER_DISTANCE;
+use crate::frustum::Frustum;
 use std::sync::Arc;
+use std::cmp::Ordering;
 use parking_lot::RwLock;
 use owning_ref::OwningRef;
 
 pub const CHUNK_SIZE: u32 = 16;
 pub const CHUNK_VOLUME: u32 = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+// Chunks stacked in a chunk column, the world is 256 blocks high
+pub const CHUNKS_PER_COLUMN: i32 = 16;
 
 #[derive(Default)]
 pub struct ChunkManager {
     pub loaded_chunk_columns: RwLock<HashMap<(i32, i32), Arc<ChunkColumn>>>,
     pub(crate) block_changelist: RwLock<HashSet<(i32, BlockID, i32, i32, i32)>>,
+    // Physics ticks run by the block tick scheduler so far
+    tick: RwLock<u64>,
+    // Changes of the blocks of the chunks in play, to be sent to their neighbours
+    pub(crate) block_updates: RwLock<BlockUpdateQueue>,
+    pub geometry_arena: RwLock<ChunkGeometryArena>,
 }
 
 impl ChunkManager {
@@ -24,6 +35,9 @@ impl ChunkManager {
         ChunkManager {
             loaded_chunk_columns: RwLock::new(HashMap::new()),
             block_changelist: RwLock::new(HashSet::new()),
+            tick: RwLock::new(0),
+            block_updates: RwLock::new(BlockUpdateQueue::default()),
+            geometry_arena: RwLock::new(ChunkGeometryArena::new()),
         }
     }
 
@@ -34,7 +48,7 @@ impl ChunkManager {
 
     #[inline]
     pub fn get_chunk(&self, x: i32, y: i32, z: i32) -> Option<OwningRef<Arc<ChunkColumn>, Chunk>> {
-        if y < 0 || y >= 16 {
+        if y < 0 || y >= CHUNKS_PER_COLUMN {
             return None;
         }
         self.loaded_chunk_columns.read().get(&(x, z))
@@ -103,34 +117,102 @@ impl ChunkManager {
                 chunk.get_block(block_x, block_y, block_z))
     }
 
-    /// Replaces the block at (x, y, z) with `block`.
-    fn _set_block(&self, priority: i32, block: BlockID, x: i32, y: i32, z: i32) -> bool {
+    pub fn get_block_state(&self, x: i32, y: i32, z: i32) -> Option<u8> {
+        let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z)
+            = ChunkManager::get_chunk_coords(x, y, z);
+
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .map(|chunk|
+                chunk.get_block_state(block_x, block_y, block_z))
+    }
+
+    /// Replaces the block at (x, y, z) with `block` in the given state.
+    /// Once the chunk is in play, the block and its neighbours are sent a block update
+    fn _set_block(&self, priority: i32, block: BlockID, state: u8, x: i32, y: i32, z: i32) -> bool {
         let (chunk_x, chunk_y, chunk_z, block_x, block_y, block_z)
             = ChunkManager::get_chunk_coords(x, y, z);
 
         match self.get_chunk(chunk_x, chunk_y, chunk_z) {
             None => false,
             Some(chunk) => {
-                chunk.set_block(block, block_x, block_y, block_z);
+                chunk.set_block_with_state(block, state, block_x, block_y, block_z);
                 if *chunk.is_uploaded_to_gpu.read() {
                     self.block_changelist.write().insert((priority, block, x, y, z));
+                    self.block_updates.write().notify_around(x, y, z);
                 }
                 true
             }
         }
     }
 
+    /// Keeps the heightmap of a column right when blocks are placed above it or its top block is broken
+    /// The world generation fills the heightmap of the terrain itself, then adds its trees with this
+    pub fn update_heighest_block(&self, block: BlockID, x: i32, y: i32, z: i32) {
+        let (chunk_x, _, chunk_z, block_x, _, block_z) = ChunkManager::get_chunk_coords(x, y, z);
+        let column = match self.get_column(chunk_x, chunk_z) {
+            Some(column) => column,
+            None => return,
+        };
+        let i = (16 * block_z + block_x) as usize;
+        let mut heighest_blocks = column.heighest_blocks.write();
+        let heighest_block = heighest_blocks[i] as i32;
+
+        if !block.is_air() && y > heighest_block {
+            heighest_blocks[i] = y as u8;
+        } else if block.is_air() && y == heighest_block {
+            heighest_blocks[i] = (0..y).rev()
+                .find(|&y| !column.get_chunk(y / 16).get_block(block_x, (y % 16) as u32, block_z).is_air())
+                .unwrap_or(0) as u8;
+        }
+    }
+
+    /// Height of the highest block of the world column at (x, z), None if it isn't loaded
+    pub fn heighest_block_at(&self, x: i32, z: i32) -> Option<i32> {
+        let (chunk_x, _, chunk_z, block_x, _, block_z) = ChunkManager::get_chunk_coords(x, 0, z);
+        self.get_column(chunk_x, chunk_z)
+            .map(|column| column.heighest_blocks.read()[(16 * block_z + block_x) as usize] as i32)
+    }
+
     pub fn set_block(&self, block: BlockID, x: i32, y: i32, z: i32) -> bool {
-        self._set_block(0, block, x, y, z)
+        self._set_block(0, block, 0, x, y, z)
     }
 
+    /// Changes a block while the game is running
     pub fn put_block(&self, block: BlockID, x: i32, y: i32, z: i32) -> bool {
-        self._set_block(1, block, x, y, z)
+        self.put_block_with_state(block, 0, x, y, z)
+    }
+
+    pub fn put_block_with_state(&self, block: BlockID, state: u8, x: i32, y: i32, z: i32) -> bool {
+        let is_set = self._set_block(1, block, state, x, y, z);
+        if is_set {
+            self.update_heighest_block(block, x, y, z);
+        }
+        is_set
+    }
+
+    pub fn current_tick(&self) -> u64 {
+        *self.tick.read()
+    }
+
+    /// Moves on to the next physics tick, returns its number
+    pub(crate) fn advance_tick(&self) -> u64 {
+        let mut tick = self.tick.write();
+        *tick += 1;
+        *tick
+    }
+
+    /// Plans a tick of `block` at (x, y, z) in `delay` physics ticks, lower priorities run first
+    /// Returns false if the chunk isn't loaded or if the block already has a pending tick
+    pub fn schedule_tick(&self, block: BlockID, x: i32, y: i32, z: i32, delay: u64, priority: i32) -> bool {
+        let (chunk_x, chunk_y, chunk_z, _, _, _) = ChunkManager::get_chunk_coords(x, y, z);
+        let tick = self.current_tick() + delay;
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .map_or(false, |chunk| chunk.scheduled_ticks.write().schedule(tick, priority, (x, y, z), block))
     }
 
     pub fn is_solid_block_at(&self, x: i32, y: i32, z: i32) -> bool {
         self.get_block(x, y, z)
-            .filter(|&block| block != BlockID::Air)
+            .filter(|&block| block.has_collision())
             .is_some()
     }
 
@@ -145,6 +227,7 @@ impl ChunkManager {
             }
         };
         let this_chunk = this_column.get_chunk(c_y);
+        this_chunk.update_visibility();
         if this_chunk.is_empty() {
             return;
         }
@@ -171,7 +254,7 @@ impl ChunkManager {
 
             let (c_x_n, c_y_n, c_z_n, b_x, b_y, b_z) = ChunkManager::get_chunk_coords(w_x, w_y, w_z);
 
-            if c_y_n < 0 || c_y_n >= 16 {
+            if c_y_n < 0 || c_y_n >= CHUNKS_PER_COLUMN {
                 return BlockID::Air;
             }
 
@@ -188,12 +271,13 @@ impl ChunkManager {
 
         #[inline]
         fn compute_active_faces(column: &ChunkColumn, neighbourhood: &[Option<Arc<ChunkColumn>>; 9], c_x: i32, c_z: i32, x: i32, y: i32, z: i32) -> [bool; 6] {
-            let right = block_at(&column, &neighbourhood, c_x, c_z, x + 1, y, z).is_transparent();
-            let left = block_at(&column, &neighbourhood, c_x, c_z, x - 1, y, z).is_transparent();
-            let top = block_at(&column, &neighbourhood, c_x, c_z, x, y + 1, z).is_transparent();
-            let bottom = block_at(&column, &neighbourhood, c_x, c_z, x, y - 1, z).is_transparent();
-            let front = block_at(&column, &neighbourhood, c_x, c_z, x, y, z + 1).is_transparent();
-            let back = block_at(&column, &neighbourhood, c_x, c_z, x, y, z - 1).is_transparent();
+            let block = block_at(&column, &neighbourhood, c_x, c_z, x, y, z);
+            let right = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x + 1, y, z));
+            let left = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x - 1, y, z));
+            let top = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x, y + 1, z));
+            let bottom = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x, y - 1, z));
+            let front = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x, y, z + 1));
+            let back = block.shows_face_next_to(block_at(&column, &neighbourhood, c_x, c_z, x, y, z - 1));
             [right, left, top, bottom, front, back]
         };
 
@@ -258,45 +342,310 @@ impl ChunkManager {
 
     // An active face is a block face next to a transparent block that needs to be rendered
     pub fn get_active_faces_of_block(&self, x: i32, y: i32, z: i32) -> [bool; 6] {
-        let right = self.get_block(x + 1, y, z).filter(|&b| !b.is_transparent()).is_none();
-        let left = self.get_block(x - 1, y, z).filter(|&b| !b.is_transparent()).is_none();
-        let top = self.get_block(x, y + 1, z).filter(|&b| !b.is_transparent()).is_none();
-        let bottom = self.get_block(x, y - 1, z).filter(|&b| !b.is_transparent()).is_none();
-        let front = self.get_block(x, y, z + 1).filter(|&b| !b.is_transparent()).is_none();
-        let back = self.get_block(x, y, z - 1).filter(|&b| !b.is_transparent()).is_none();
+        let block = self.get_block(x, y, z).unwrap_or(BlockID::Air);
+        let is_active = |x: i32, y: i32, z: i32| {
+            self.get_block(x, y, z).map_or(true, |neighbour| block.shows_face_next_to(neighbour))
+        };
+        let right = is_active(x + 1, y, z);
+        let left = is_active(x - 1, y, z);
+        let top = is_active(x, y + 1, z);
+        let bottom = is_active(x, y - 1, z);
+        let front = is_active(x, y, z + 1);
+        let back = is_active(x, y, z - 1);
         [right, left, top, bottom, front, back]
     }
 
-    pub fn render_loaded_chunks(&self, program: &mut ShaderProgram) {
-        for ((x, z), chunk_column) in self.loaded_chunk_columns.read().iter() {
-            for (ref y, chunk) in chunk_column.chunks.iter().enumerate() {
+    /// Selects the chunks to draw this frame: uploaded, non-empty, inside the view frustum
+    /// and reachable from the camera through the visibility graph of the chunks
+    pub fn cull_loaded_chunks(&self, frustum: &Frustum, camera_position: &Vec3) -> (Vec<VisibleChunk>, ChunkCullingStats) {
+        let mut stats = ChunkCullingStats::default();
+        let mut visible_chunks = Vec::new();
+        let reachable_chunks = self.find_reachable_chunks(frustum, camera_position);
+        for (&(x, z), chunk_column) in self.loaded_chunk_columns.read().iter() {
+            for (y, chunk) in chunk_column.chunks.iter().enumerate() {
+                let y = y as i32;
                 // Skip rendering the chunk if there is nothing to draw
-                let vao = *chunk.vao.read();
-                if !*chunk.is_uploaded_to_gpu.read() || chunk.is_empty() || vao == 0 {
+                if !*chunk.is_uploaded_to_gpu.read() || chunk.is_empty() || chunk.allocation.read().is_none() {
                     continue;
                 }
 
-                let model_matrix = {
-                    let translate_matrix = Matrix4::new_translation(&vec3(
-                        *x as f32, *y as f32, *z as f32).scale(16.0));
-                    let rotate_matrix = Matrix4::from_euler_angles(
-                        0.0f32,
-                        0.0,
-                        0.0,
-                    );
-                    let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(1.0f32, 1.0f32, 1.0f32));
-                    translate_matrix * rotate_matrix * scale_matrix
-                };
+                stats.considered += 1;
+                if !frustum.intersects_aabb(&ChunkManager::chunk_aabb(x, y, z)) {
+                    stats.culled += 1;
+                    continue;
+                }
+                if let Some(reachable_chunks) = &reachable_chunks {
+                    if !reachable_chunks.contains(&(x, y, z)) {
+                        stats.occluded += 1;
+                        continue;
+                    }
+                }
+                stats.drawn += 1;
+                stats.vertices += *chunk.vertices_drawn.read();
+                visible_chunks.push(VisibleChunk {
+                    coords: (x, y, z),
+                    column: Arc::clone(chunk_column),
+                });
+            }
+        }
+        (visible_chunks, stats)
+    }
+
+    /// Breadth-first search from the chunk containing the camera, only going through the faces
+    /// of a chunk which can be seen from the face the search entered it by
+    /// The search never goes back towards the camera, so it can't wrap around walls
+    /// Returns `None` if the camera is outside of the world vertically
+    /// https://tomcc.github.io/2014/08/31/visibility-1.html
+    fn find_reachable_chunks(&self, frustum: &Frustum, camera_position: &Vec3) -> Option<HashSet<(i32, i32, i32)>> {
+        let camera_chunk = (
+            (camera_position.x / CHUNK_SIZE as f32).floor() as i32,
+            (camera_position.y / CHUNK_SIZE as f32).floor() as i32,
+            (camera_position.z / CHUNK_SIZE as f32).floor() as i32,
+        );
+        if camera_chunk.1 < 0 || camera_chunk.1 >= CHUNKS_PER_COLUMN {
+            return None;
+        }
+
+        let mut reachable_chunks = HashSet::new();
+        let mut queue = VecDeque::new();
+        reachable_chunks.insert(camera_chunk);
+        // (chunk, face the chunk was entered from, directions travelled so far)
+        queue.push_back((camera_chunk, None, 0u8));
+
+        while let Some(((x, y, z), entry_face, directions)) = queue.pop_front() {
+            let column = match self.get_column(x, z) {
+                Some(column) => column,
+                None => continue,
+            };
+            // Chunks which are not generated yet don't block the view
+            let visibility = {
+                let chunk = column.get_chunk(y);
+                if *chunk.is_generated.read() {
+                    Some(*chunk.visibility.read())
+                } else {
+                    None
+                }
+            };
+
+            for (face, &(dx, dy, dz)) in FACE_DIRECTIONS.iter().enumerate() {
+                if directions & (1 << opposite_face(face)) != 0 {
+                    continue;
+                }
+                if let (Some(entry_face), Some(visibility)) = (entry_face, visibility) {
+                    if !visibility.can_see_through(entry_face, face) {
+                        continue;
+                    }
+                }
+
+                let neighbour = (x + dx, y + dy, z + dz);
+                if neighbour.1 < 0 || neighbour.1 >= CHUNKS_PER_COLUMN
+                    || (neighbour.0 - camera_chunk.0).abs() > RENDER_DISTANCE
+                    || (neighbour.2 - camera_chunk.2).abs() > RENDER_DISTANCE
+                    || reachable_chunks.contains(&neighbour) {
+                    continue;
+                }
+                if !frustum.intersects_aabb(&ChunkManager::chunk_aabb(neighbour.0, neighbour.1, neighbour.2)) {
+                    continue;
+                }
+
+                reachable_chunks.insert(neighbour);
+                queue.push_back((neighbour, Some(opposite_face(face)), directions | (1 << face)));
+            }
+        }
+
+        Some(reachable_chunks)
+    }
 
-                gl_call!(gl::BindVertexArray(vao));
-                if vao == 0 {
-                    dbg!(vao);
-                    dbg!(*chunk.is_uploaded_to_gpu.read());
-                    dbg!(chunk.is_empty());
+    /// Bounding box of a chunk in world coordinates
+    pub fn chunk_aabb(x: i32, y: i32, z: i32) -> AABB {
+        let mins = vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32);
+        AABB::new(mins, mins + vec3(1.0, 1.0, 1.0).scale(CHUNK_SIZE as f32))
+    }
+
+    /// Draws one render layer of the visible chunks with a single indirect draw call
+    /// Translucent chunks, and the faces inside of them, are drawn from back to front
+    pub fn render_chunks(&self, visible_chunks: &[VisibleChunk], layer: RenderLayer, camera_position: &Vec3) {
+        let mut chunks_to_draw = visible_chunks.iter()
+            .filter(|visible_chunk| visible_chunk.chunk().layer_faces.read()[layer.index()] != 0)
+            .collect::<Vec<_>>();
+
+        let chunk_origin = |(x, y, z): (i32, i32, i32)| {
+            vec3(x as f32, y as f32, z as f32).scale(CHUNK_SIZE as f32)
+        };
+        let chunk_center = |coords: (i32, i32, i32)| {
+            chunk_origin(coords) + vec3(8.0, 8.0, 8.0)
+        };
+
+        if layer == RenderLayer::Translucent {
+            chunks_to_draw.sort_by(|a, b| {
+                let distance_a = (chunk_center(a.coords) - camera_position).norm_squared();
+                let distance_b = (chunk_center(b.coords) - camera_position).norm_squared();
+                distance_b.partial_cmp(&distance_a).unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let mut arena = self.geometry_arena.write();
+        let mut draws = Vec::with_capacity(chunks_to_draw.len());
+        for visible_chunk in chunks_to_draw {
+            let chunk = visible_chunk.chunk();
+            let origin = chunk_origin(visible_chunk.coords);
+            if layer == RenderLayer::Translucent {
+                chunk.sort_translucent_faces(&(camera_position - origin), &arena);
+            }
+            if let Some(draw) = chunk.layer_draw(layer, [origin.x, origin.y, origin.z]) {
+                draws.push(draw);
+            }
+        }
+        arena.draw(&draws);
+    }
+}
+
+/// A chunk which passed the culling tests and will be drawn this frame
+pub struct VisibleChunk {
+    pub coords: (i32, i32, i32),
+    column: Arc<ChunkColumn>,
+}
+
+impl VisibleChunk {
+    #[inline]
+    pub fn chunk(&self) -> &Chunk {
+        self.column.get_chunk(self.coords.1)
+    }
+}
+
+/// Number of chunks processed by the culling during the last frame
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct ChunkCullingStats {
+    // Chunks with geometry uploaded to the GPU
+    pub considered: u32,
+    // Chunks outside the view frustum
+    pub culled: u32,
+    // Chunks inside the view frustum but hidden behind opaque terrain
+    pub occluded: u32,
+    pub drawn: u32,
+    // Vertices of the drawn chunks, in every render layer
+    pub vertices: u32,
+}
+
+#[cfg(test)]
+pub mod test_util {
+    use std::ops::RangeInclusive;
+
+    use super::*;
+
+    /// Empty chunk columns loaded at every chunk coordinates in `xs` and `zs`
+    pub fn empty_world(xs: RangeInclusive<i32>, zs: RangeInclusive<i32>) -> ChunkManager {
+        let chunk_manager = ChunkManager::new();
+        for x in xs {
+            for z in zs.clone() {
+                chunk_manager.add_chunk_column((x, z), Arc::new(ChunkColumn::new()));
+            }
+        }
+        chunk_manager
+    }
+
+    /// Puts `block` everywhere in the box from `min` to `max` included
+    pub fn fill(chunk_manager: &ChunkManager, block: BlockID, min: (i32, i32, i32), max: (i32, i32, i32)) {
+        for x in min.0..=max.0 {
+            for y in min.1..=max.1 {
+                for z in min.2..=max.2 {
+                    chunk_manager.put_block(block, x, y, z);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::{look_at, perspective};
+
+    use crate::chunk::BlockIterator;
+    use crate::chunk_arena::ArenaAllocation;
+
+    use super::test_util::empty_world;
+    use super::*;
+
+    /// Puts a block in the chunk and marks it as uploaded with `vertices` to draw
+    fn upload_chunk(chunk_manager: &ChunkManager, (x, y, z): (i32, i32, i32), vertices: u32) {
+        chunk_manager.set_block(BlockID::Stone, x * CHUNK_SIZE as i32, y * CHUNK_SIZE as i32, z * CHUNK_SIZE as i32);
+        let column = chunk_manager.get_column(x, z).unwrap();
+        let chunk = column.get_chunk(y);
+        *chunk.is_uploaded_to_gpu.write() = true;
+        *chunk.allocation.write() = Some(ArenaAllocation { first_face: 0, n_faces: vertices / 4 });
+        *chunk.vertices_drawn.write() = vertices;
+    }
+
+    #[test]
+    fn culling_counts_the_chunks() {
+        let chunk_manager = empty_world(-1..=1, -3..=3);
+        // In front of the camera, behind it and far below it
+        upload_chunk(&chunk_manager, (0, 2, -2), 400);
+        upload_chunk(&chunk_manager, (0, 2, 2), 100);
+        upload_chunk(&chunk_manager, (1, 0, -3), 40);
+        // Not uploaded yet
+        chunk_manager.set_block(BlockID::Stone, 0, 32, -48);
+
+        let camera_position = vec3(8.0, 40.0, 8.0);
+        let view = look_at(&camera_position, &(camera_position + vec3(0.0, 0.0, -1.0)), &Vec3::y());
+        let projection = perspective(1.0, std::f32::consts::FRAC_PI_4, 0.1, 100.0);
+        let frustum = Frustum::from_view_projection(&view, &projection);
+
+        let (visible_chunks, stats) = chunk_manager.cull_loaded_chunks(&frustum, &camera_position);
+        assert_eq!(visible_chunks.iter().map(|chunk| chunk.coords).collect::<Vec<_>>(), vec![(0, 2, -2)]);
+        assert_eq!(stats, ChunkCullingStats {
+            considered: 3,
+            culled: 2,
+            occluded: 0,
+            drawn: 1,
+            vertices: 400,
+        });
+    }
+
+    #[test]
+    fn camera_on_the_surface_doesnt_reach_a_sealed_cave() {
+        let chunk_manager = ChunkManager::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let column = Arc::new(ChunkColumn::new());
+                for c_y in 0..3 {
+                    let chunk = column.get_chunk(c_y);
+                    for (b_x, b_y, b_z) in BlockIterator::new() {
+                        chunk.set_block(BlockID::Stone, b_x, b_y, b_z);
+                    }
+                    *chunk.is_generated.write() = true;
+                }
+                // The empty chunk above the ground
+                *column.get_chunk(3).is_generated.write() = true;
+                chunk_manager.add_chunk_column((x, z), column);
+            }
+        }
+        // A cave in the middle of the chunk under the surface chunk
+        for b_x in 4..12 {
+            for b_y in 20..28 {
+                for b_z in 4..12 {
+                    chunk_manager.set_block(BlockID::Air, b_x, b_y, b_z);
+                }
+            }
+        }
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let column = chunk_manager.get_column(x, z).unwrap();
+                for c_y in 0..4 {
+                    column.get_chunk(c_y).update_visibility();
                 }
-                program.set_uniform_matrix4fv("model", model_matrix.as_ptr());
-                gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, *chunk.vertices_drawn.read() as i32));
             }
         }
+        upload_chunk(&chunk_manager, (0, 2, 0), 40);
+        upload_chunk(&chunk_manager, (0, 1, 0), 40);
+
+        let camera_position = vec3(8.0, 60.0, 8.0);
+        let view = look_at(&camera_position, &vec3(8.0, 0.0, 8.0), &vec3(0.0, 0.0, -1.0));
+        let projection = perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0);
+        let frustum = Frustum::from_view_projection(&view, &projection);
+
+        let (visible_chunks, stats) = chunk_manager.cull_loaded_chunks(&frustum, &camera_position);
+        assert_eq!(visible_chunks.iter().map(|chunk| chunk.coords).collect::<Vec<_>>(), vec![(0, 2, 0)]);
+        assert_eq!((stats.considered, stats.culled, stats.occluded, stats.drawn), (2, 0, 1, 1));
     }
-}
\ No newline at end of file
+}
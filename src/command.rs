@@ -0,0 +1,459 @@
+use nalgebra_glm::{Vec3, vec3};
+
+use crate::chunk::BlockID;
+use crate::inventory::item::Item;
+use crate::player::GameMode;
+use crate::weather::WeatherKind;
+
+// Biggest number of blocks /fill can change at once
+pub const MAX_FILL_VOLUME: i64 = 32768;
+pub const MAX_GIVE_AMOUNT: i64 = 6400;
+// Longest duration of /weather, in seconds
+pub const MAX_WEATHER_DURATION: i64 = 1_000_000;
+
+pub const COMMAND_NAMES: [&str; 10] = ["fill", "fly", "gamemode", "give", "help", "seed", "setblock", "time", "tp", "weather"];
+// Shown by /help
+pub const HELP: &str = "/tp [target] <x> <y> <z>\n\
+/setblock <x> <y> <z> <block>\n\
+/fill <x1> <y1> <z1> <x2> <y2> <z2> <block>\n\
+/give <target> <item> [amount]\n\
+/gamemode <creative|survival> [target]\n\
+/seed\n\
+/time <set|add|query> <value>\n\
+/fly [on|off]\n\
+/weather <clear|rain|snow|thunder> [duration]";
+
+const SELECTORS: [&str; 3] = ["@a", "@p", "@s"];
+const GAME_MODES: [&str; 2] = ["creative", "survival"];
+const WEATHERS: [&str; 4] = ["clear", "rain", "snow", "thunder"];
+// Blocks which have no item
+const NON_ITEM_BLOCKS: [(&str, BlockID); 4] = [
+    ("air", BlockID::Air),
+    ("lava", BlockID::Lava),
+    ("water", BlockID::Water),
+    ("lit_redstone_lamp", BlockID::LitRedstoneLamp),
+];
+// Named times of /time set, in ticks since the start of the day
+const TIMES_OF_DAY: [(&str, u64); 4] = [("day", 1000), ("noon", 6000), ("night", 13000), ("midnight", 18000)];
+
+/// There is a single player, every selector targets it
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Selector {
+    // @s
+    Executor,
+    // @p
+    NearestPlayer,
+    // @a
+    AllPlayers,
+}
+
+/// A coordinate written as a number, or relative to the position of the player with '~'
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Coordinate {
+    Absolute(f32),
+    Relative(f32),
+}
+
+impl Coordinate {
+    pub fn resolve(&self, origin: f32) -> f32 {
+        match *self {
+            Coordinate::Absolute(value) => value,
+            Coordinate::Relative(offset) => origin + offset,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Coordinates(pub [Coordinate; 3]);
+
+impl Coordinates {
+    pub fn resolve(&self, origin: &Vec3) -> Vec3 {
+        let [x, y, z] = self.0;
+        vec3(x.resolve(origin.x), y.resolve(origin.y), z.resolve(origin.z))
+    }
+
+    /// Position of the block containing the resolved coordinates
+    pub fn resolve_block(&self, origin: &Vec3) -> (i32, i32, i32) {
+        let position = self.resolve(origin);
+        (position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TimeQuery {
+    DayTime,
+    GameTime,
+    Day,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    Teleport { target: Selector, destination: Coordinates },
+    SetBlock { position: Coordinates, block: BlockID },
+    Fill { from: Coordinates, to: Coordinates, block: BlockID },
+    Give { target: Selector, item: Item, amount: u32 },
+    GameMode { game_mode: GameMode, target: Selector },
+    Seed,
+    SetTime(u64),
+    AddTime(u64),
+    QueryTime(TimeQuery),
+    // Toggles flying when there is no state
+    Fly(Option<bool>),
+    // Changes the weather for a number of seconds, or for a random time
+    Weather { kind: WeatherKind, duration: Option<u32> },
+    Help,
+}
+
+/// Reads the words of a command one argument at a time
+/// When completing, the last word is the one being typed: instead of parsing it,
+/// the suggestions for the argument expected there are collected and parsing stops
+pub struct Arguments<'a> {
+    words: Vec<&'a str>,
+    next: usize,
+    completing: bool,
+    suggestions: Vec<String>,
+}
+
+// Returned when parsing stops at the word being completed, never shown to the player
+const COMPLETION_REACHED: &str = "";
+
+impl<'a> Arguments<'a> {
+    pub fn new(line: &'a str) -> Self {
+        Arguments {
+            words: line.split_whitespace().collect(),
+            next: 0,
+            completing: false,
+            suggestions: vec![],
+        }
+    }
+
+    /// A space at the end of the line starts a new, empty word
+    pub fn for_completion(line: &'a str) -> Self {
+        let mut arguments = Arguments::new(line);
+        if line.is_empty() || line.ends_with(char::is_whitespace) {
+            arguments.words.push("");
+        }
+        arguments.completing = true;
+        arguments
+    }
+
+    fn is_at_completed_word(&self) -> bool {
+        self.completing && self.next + 1 == self.words.len()
+    }
+
+    /// Whether there are no more arguments, to know when to stop at optional arguments
+    pub fn is_empty(&self) -> bool {
+        self.next >= self.words.len()
+    }
+
+    fn peek(&self) -> Option<&'a str> {
+        self.words.get(self.next).copied()
+    }
+
+    /// Takes the next word, `expected` describes the argument in the error
+    fn next_word<I, S>(&mut self, expected: &str, suggestions: I) -> Result<&'a str, String>
+        where I: IntoIterator<Item=S>,
+              S: Into<String> {
+        if self.is_at_completed_word() {
+            let prefix = self.words[self.next];
+            self.suggestions = suggestions.into_iter()
+                .map(|suggestion| suggestion.into())
+                .filter(|suggestion: &String| suggestion.starts_with(prefix))
+                .collect();
+            return Err(COMPLETION_REACHED.to_string());
+        }
+        let word = self.peek().ok_or_else(|| format!("missing {}", expected))?;
+        self.next += 1;
+        Ok(word)
+    }
+
+    pub fn integer(&mut self, name: &str, min: i64, max: i64) -> Result<i64, String> {
+        let word = self.next_word(name, Vec::<String>::new())?;
+        let value = word.parse::<i64>()
+            .map_err(|_| format!("expected an integer for {}, found \"{}\"", name, word))?;
+        if value < min || value > max {
+            return Err(format!("{} must be between {} and {}, found {}", name, min, max, value));
+        }
+        Ok(value)
+    }
+
+    /// One of the given words
+    pub fn literal(&mut self, name: &str, choices: &[&'static str]) -> Result<&'static str, String> {
+        let word = self.next_word(name, choices.iter().copied())?;
+        choices.iter()
+            .find(|&&choice| choice == word)
+            .copied()
+            .ok_or_else(|| format!("expected {} for {}, found \"{}\"", choices.join(" | "), name, word))
+    }
+
+    fn coordinate(&mut self, name: &str) -> Result<Coordinate, String> {
+        let word = self.next_word(name, vec!["~"])?;
+        let number = |number: &str| number.parse::<f32>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| format!("expected a number or ~ for {}, found \"{}\"", name, word));
+        match word.strip_prefix('~') {
+            Some("") => Ok(Coordinate::Relative(0.0)),
+            Some(offset) => number(offset).map(Coordinate::Relative),
+            None => number(word).map(Coordinate::Absolute),
+        }
+    }
+
+    pub fn coordinates(&mut self) -> Result<Coordinates, String> {
+        Ok(Coordinates([self.coordinate("x")?, self.coordinate("y")?, self.coordinate("z")?]))
+    }
+
+    pub fn block(&mut self) -> Result<BlockID, String> {
+        let names = Item::NAMES.iter()
+            .filter(|(_, item)| item.block().is_some())
+            .map(|&(name, _)| name);
+        let word = self.next_word("block", NON_ITEM_BLOCKS.iter().map(|&(name, _)| name).chain(names))?;
+        match NON_ITEM_BLOCKS.iter().find(|&&(name, _)| name == word) {
+            Some(&(_, block)) => Ok(block),
+            None => Item::from_name(word)
+                .and_then(|item| item.block())
+                .ok_or_else(|| format!("unknown block \"{}\"", word)),
+        }
+    }
+
+    pub fn item(&mut self) -> Result<Item, String> {
+        let word = self.next_word("item", Item::NAMES.iter().map(|&(name, _)| name))?;
+        Item::from_name(word).ok_or_else(|| format!("unknown item \"{}\"", word))
+    }
+
+    pub fn selector(&mut self) -> Result<Selector, String> {
+        match self.literal("target", &SELECTORS)? {
+            "@s" => Ok(Selector::Executor),
+            "@p" => Ok(Selector::NearestPlayer),
+            _ => Ok(Selector::AllPlayers),
+        }
+    }
+
+    /// The player running the command when there is no selector
+    pub fn optional_selector(&mut self) -> Result<Selector, String> {
+        if self.is_at_completed_word() {
+            // The word can also be the first coordinate
+            self.next_word("target", SELECTORS.iter().chain(&["~"]).copied())?;
+        }
+        match self.peek() {
+            Some(word) if word.starts_with('@') => self.selector(),
+            _ => Ok(Selector::Executor),
+        }
+    }
+
+    /// A number of ticks, or the name of a time of the day
+    pub fn time_of_day(&mut self) -> Result<u64, String> {
+        let word = self.next_word("time", TIMES_OF_DAY.iter().map(|&(name, _)| name))?;
+        match TIMES_OF_DAY.iter().find(|(name, _)| *name == word) {
+            Some(&(_, time)) => Ok(time),
+            None => word.parse::<u32>()
+                .map(|time| time as u64)
+                .map_err(|_| format!("expected a number of ticks, day, noon, night or midnight for time, found \"{}\"", word)),
+        }
+    }
+
+    pub fn game_mode(&mut self) -> Result<GameMode, String> {
+        match self.literal("game mode", &GAME_MODES)? {
+            "creative" => Ok(GameMode::Creative),
+            _ => Ok(GameMode::Survival),
+        }
+    }
+
+    pub fn weather(&mut self) -> Result<WeatherKind, String> {
+        match self.literal("weather", &WEATHERS)? {
+            "clear" => Ok(WeatherKind::Clear),
+            "rain" => Ok(WeatherKind::Rain),
+            "snow" => Ok(WeatherKind::Snow),
+            _ => Ok(WeatherKind::Thunder),
+        }
+    }
+
+    /// Fails when there are words left after the last argument
+    pub fn end(&self) -> Result<(), String> {
+        match self.peek() {
+            Some(word) if !self.is_at_completed_word() => Err(format!("unexpected argument \"{}\"", word)),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse_time(arguments: &mut Arguments) -> Result<Command, String> {
+    match arguments.literal("action", &["add", "query", "set"])? {
+        "add" => Ok(Command::AddTime(arguments.integer("time", 0, i32::MAX as i64)? as u64)),
+        "query" => match arguments.literal("query", &["day", "daytime", "gametime"])? {
+            "day" => Ok(Command::QueryTime(TimeQuery::Day)),
+            "daytime" => Ok(Command::QueryTime(TimeQuery::DayTime)),
+            _ => Ok(Command::QueryTime(TimeQuery::GameTime)),
+        },
+        _ => Ok(Command::SetTime(arguments.time_of_day()?)),
+    }
+}
+
+fn parse_arguments(arguments: &mut Arguments) -> Result<Command, String> {
+    let command = match arguments.literal("command", &COMMAND_NAMES)? {
+        "tp" => {
+            let target = arguments.optional_selector()?;
+            Command::Teleport { target, destination: arguments.coordinates()? }
+        }
+        "setblock" => Command::SetBlock {
+            position: arguments.coordinates()?,
+            block: arguments.block()?,
+        },
+        "fill" => Command::Fill {
+            from: arguments.coordinates()?,
+            to: arguments.coordinates()?,
+            block: arguments.block()?,
+        },
+        "give" => {
+            let target = arguments.selector()?;
+            let item = arguments.item()?;
+            let amount = if arguments.is_empty() {
+                1
+            } else {
+                arguments.integer("amount", 1, MAX_GIVE_AMOUNT)? as u32
+            };
+            Command::Give { target, item, amount }
+        }
+        "gamemode" => {
+            let game_mode = arguments.game_mode()?;
+            let target = if arguments.is_empty() {
+                Selector::Executor
+            } else {
+                arguments.selector()?
+            };
+            Command::GameMode { game_mode, target }
+        }
+        "seed" => Command::Seed,
+        "time" => parse_time(arguments)?,
+        "fly" => {
+            if arguments.is_empty() {
+                Command::Fly(None)
+            } else {
+                Command::Fly(Some(arguments.literal("state", &["off", "on"])? == "on"))
+            }
+        }
+        "weather" => {
+            let kind = arguments.weather()?;
+            let duration = if arguments.is_empty() {
+                None
+            } else {
+                Some(arguments.integer("duration", 1, MAX_WEATHER_DURATION)? as u32)
+            };
+            Command::Weather { kind, duration }
+        }
+        _ => Command::Help,
+    };
+    arguments.end()?;
+    Ok(command)
+}
+
+/// Parses a command, without the leading '/'
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut arguments = Arguments::new(line);
+    if arguments.is_empty() {
+        return Err("missing command".to_string());
+    }
+    parse_arguments(&mut arguments)
+}
+
+/// Every word which can replace the last word of an incomplete command, without the leading '/'
+pub fn complete(line: &str) -> Vec<String> {
+    let mut arguments = Arguments::for_completion(line);
+    // Errors before the word being completed mean there is nothing to suggest
+    let _ = parse_arguments(&mut arguments);
+    arguments.suggestions.sort();
+    arguments.suggestions.dedup();
+    arguments.suggestions
+}
+
+/// Number of blocks between two corners, both included
+pub fn fill_volume((x1, y1, z1): (i32, i32, i32), (x2, y2, z2): (i32, i32, i32)) -> i64 {
+    let side = |a: i32, b: i32| (a as i64 - b as i64).abs() + 1;
+    side(x1, x2) * side(y1, y2) * side(z1, z2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn teleport_to(target: Selector, x: Coordinate, y: Coordinate, z: Coordinate) -> Command {
+        Command::Teleport { target, destination: Coordinates([x, y, z]) }
+    }
+
+    #[test]
+    fn parses_absolute_and_relative_coordinates() {
+        use Coordinate::{Absolute, Relative};
+        assert_eq!(parse_command("tp 1 2.5 -3"), Ok(teleport_to(Selector::Executor, Absolute(1.0), Absolute(2.5), Absolute(-3.0))));
+        assert_eq!(parse_command("tp ~ ~-5 ~0.5"), Ok(teleport_to(Selector::Executor, Relative(0.0), Relative(-5.0), Relative(0.5))));
+
+        let destination = Coordinates([Relative(0.0), Relative(-5.0), Absolute(3.0)]);
+        assert_eq!(destination.resolve(&vec3(10.5, 64.0, 7.0)), vec3(10.5, 59.0, 3.0));
+        assert_eq!(destination.resolve_block(&vec3(-0.5, 64.0, 7.0)), (-1, 59, 3));
+    }
+
+    #[test]
+    fn rejects_coordinates_which_arent_numbers() {
+        for line in &["tp 1 2 nan", "tp inf 2 3", "tp ~-inf 2 3", "tp 1 ~~ 3", "tp 1 2 three", "tp 1 2"] {
+            assert!(parse_command(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn teleports_with_or_without_a_selector() {
+        use Coordinate::Absolute;
+        let expected = teleport_to(Selector::Executor, Absolute(1.0), Absolute(2.0), Absolute(3.0));
+        assert_eq!(parse_command("tp @s 1 2 3"), Ok(expected));
+        assert_eq!(parse_command("tp 1 2 3"), Ok(expected));
+        assert_eq!(parse_command("tp @a 1 2 3"), Ok(teleport_to(Selector::AllPlayers, Absolute(1.0), Absolute(2.0), Absolute(3.0))));
+        assert!(parse_command("tp @x 1 2 3").is_err());
+    }
+
+    #[test]
+    fn limits_the_amount_of_give() {
+        let give = |amount| Ok(Command::Give { target: Selector::NearestPlayer, item: Item::Stick, amount });
+        assert_eq!(parse_command("give @p stick"), give(1));
+        assert_eq!(parse_command(&format!("give @p stick {}", MAX_GIVE_AMOUNT)), give(MAX_GIVE_AMOUNT as u32));
+        assert!(parse_command(&format!("give @p stick {}", MAX_GIVE_AMOUNT + 1)).is_err());
+        assert!(parse_command("give @p stick 0").is_err());
+        assert!(parse_command("give @p stick 99999999999999999999").is_err());
+    }
+
+    #[test]
+    fn rejects_trailing_arguments() {
+        for line in &["seed 1", "tp 1 2 3 4", "fly on off", "setblock 1 2 3 stone stone", "give @s stick 1 2", "help me"] {
+            assert_eq!(parse_command(line).map_err(|err| err.starts_with("unexpected argument")), Err(true), "{}", line);
+        }
+    }
+
+    #[test]
+    fn completes_after_a_trailing_space() {
+        assert_eq!(complete(""), COMMAND_NAMES.to_vec());
+        assert_eq!(complete("we"), vec!["weather"]);
+        assert_eq!(complete("weather "), vec!["clear", "rain", "snow", "thunder"]);
+        assert_eq!(complete("gamemode c"), vec!["creative"]);
+        // Nothing is expected after the last argument
+        assert!(complete("seed ").is_empty());
+        // The earlier arguments have to be valid
+        assert!(complete("weather hail ").is_empty());
+    }
+
+    #[test]
+    fn completes_an_optional_selector_or_a_coordinate() {
+        assert_eq!(complete("tp "), vec!["@a", "@p", "@s", "~"]);
+        assert_eq!(complete("tp @"), vec!["@a", "@p", "@s"]);
+        assert_eq!(complete("tp @s "), vec!["~"]);
+        assert_eq!(complete("tp 1 "), vec!["~"]);
+    }
+
+    #[test]
+    fn completes_block_names() {
+        assert_eq!(complete("setblock 1 2 3 st"), vec!["stone", "stone_button"]);
+        assert_eq!(complete("setblock ~ ~ ~ lit"), vec!["lit_redstone_lamp"]);
+        let blocks = complete("fill 0 0 0 1 1 1 ");
+        assert!(blocks.contains(&"air".to_string()));
+        assert!(blocks.contains(&"redstone".to_string()));
+        // Items which aren't blocks
+        assert!(!blocks.contains(&"stick".to_string()));
+        assert_eq!(complete("give @s st"), vec!["stick", "stone", "stone_button", "stone_pickaxe"]);
+    }
+}
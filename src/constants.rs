@@ -27,6 +27,11 @@ lazy_static! {
     };
 }
 
+// The lowlands are flooded up to this height
+pub const SEA_LEVEL: i32 = 96;
+// The ground deeper than this below the sea level is covered with gravel
+pub const SEA_FLOOR_DEPTH: i32 = 6;
+
 // Rendering
 pub const RENDER_DISTANCE: i32 = 10;
 pub const ENABLE_FOG: bool = true;
@@ -47,9 +52,21 @@ pub const PHYSICS_TICKRATE: f32 = 60.0;
 pub const GRAVITY: f32 = -28.0;
 pub const MAX_VERTICAL_VELOCITY: f32 = 90.0;
 
+// Block ticks, blocks picked at random in every chunk and scheduled block updates run at every physics tick
+pub const RANDOM_TICKS_PER_SECTION: u32 = 1;
+pub const SCHEDULED_TICKS_PER_TICK: usize = 512;
+// Block updates sent to the neighbours of changed blocks, the others wait for the next tick
+pub const BLOCK_UPDATES_PER_TICK: usize = 1024;
+// Ticks caught up at most in a frame after a lag spike
+pub const MAX_BLOCK_TICKS_PER_FRAME: u32 = 10;
+// Physics ticks before sand and gravel start falling
+pub const FALLING_BLOCK_DELAY: u64 = 2;
+
 // Texture pack
-pub const ITEM_ARRAY_TEXTURE_LAYERS: u32 = 50;
+pub const ITEM_ARRAY_TEXTURE_LAYERS: u32 = 64;
 pub const BLOCK_TEXTURE_SIZE: u32 = 16;
+// Number of textures of the cracks drawn on a block being broken
+pub const DESTROY_STAGES: u32 = 10;
 
 // Some values are taken from the minecraft gamepedia
 // Player
@@ -58,14 +75,24 @@ pub const PLAYER_HEIGHT: f32 = 1.8;
 pub const PLAYER_EYES_HEIGHT: f32 = 1.62;
 pub const REACH_DISTANCE: f32 = 7.0;
 pub const JUMP_HEIGHT: f32 = 1.3;
+pub const PLAYER_STEP_HEIGHT: f32 = 0.6;
 pub const HORIZONTAL_ACCELERATION: f32 = 30.0;
 pub const WALKING_SPEED: f32 = 4.317;
 pub const SPRINTING_SPEED: f32 = 6.0;
 pub const SNEAKING_SPEED: f32 = 2.0;
 pub const FLYING_SPEED: f32 = 10.92;
 pub const FLYING_SPRINTING_SPEED: f32 = 50.0;
+pub const FLYING_VERTICAL_SPEED: f32 = 8.0;
 pub const ON_GROUND_FRICTION: f32 = 12.0;
 pub const IN_AIR_FRICTION: f32 = 2.0;
+// Swimming, the fluids slow the player down and make it sink slowly
+pub const FLUID_GRAVITY_SCALE: f32 = 0.25;
+pub const SWIMMING_UP_SPEED: f32 = 4.0;
+pub const MAX_SINKING_SPEED: f32 = 2.0;
+pub const IN_WATER_FRICTION: f32 = 4.0;
+pub const IN_LAVA_FRICTION: f32 = 8.0;
+pub const IN_WATER_SPEED_FACTOR: f32 = 0.5;
+pub const IN_LAVA_SPEED_FACTOR: f32 = 0.3;
 
 // https://wikimedia.org/api/rest_v1/media/math/render/svg/12be1b7cde89a51c88ef0307f7070cb2368a2079
 // Calculation of the initial velocity in order to reach the jump height
@@ -76,3 +103,58 @@ lazy_static! {
 }
 pub const PLAYER_HALF_WIDTH: f32 = PLAYER_WIDTH / 2.0;
 pub const PLAYER_HALF_HEIGHT: f32 = PLAYER_HEIGHT / 2.0;
+
+// Health and hunger, in half hearts and half drumsticks
+pub const MAX_HEALTH: u32 = 20;
+pub const MAX_HUNGER: u32 = 20;
+// The hunger needed to regenerate health
+pub const REGENERATION_HUNGER: u32 = 18;
+// Blocks which can be fallen without taking damage
+pub const SAFE_FALL_DISTANCE: f32 = 3.0;
+// Every 4 points of exhaustion use up one point of saturation, or of hunger when there is no saturation left
+pub const EXHAUSTION_PER_HUNGER_POINT: f32 = 4.0;
+pub const SPRINTING_EXHAUSTION: f32 = 0.1; // Per block
+pub const JUMPING_EXHAUSTION: f32 = 0.05;
+pub const SPRINT_JUMPING_EXHAUSTION: f32 = 0.2;
+pub const DAMAGE_EXHAUSTION: f32 = 0.1;
+pub const REGENERATION_EXHAUSTION: f32 = 6.0;
+lazy_static! {
+    // Time between two points of health regenerated or lost to starvation
+    pub static ref HEALTH_TICK_INTERVAL: Duration = Duration::from_secs(4);
+}
+lazy_static! {
+    // Real time taken by a day of the world, 20 minutes like in Minecraft
+    pub static ref DAY_LENGTH: Duration = Duration::from_secs(20 * 60);
+}
+// Horizontal position where the player appears, on top of the highest block
+pub const WORLD_SPAWN: (i32, i32) = (8, 8);
+
+// Dropped items
+pub const DROPPED_ITEM_SIZE: f32 = 0.25;
+pub const DROPPED_ITEM_HALF_SIZE: f32 = DROPPED_ITEM_SIZE / 2.0;
+// Distance from the AABB of the player at which items are picked up
+pub const DROPPED_ITEM_PICKUP_RANGE: f32 = 1.0;
+pub const DROPPED_ITEM_MERGE_RADIUS: f32 = 0.5;
+pub const DROPPED_ITEM_THROW_SPEED: f32 = 6.0;
+// Items lose 2% of their speed per physics tick in the air
+pub const DROPPED_ITEM_DRAG: f32 = 1.2;
+lazy_static! {
+    pub static ref DROPPED_ITEM_LIFETIME: Duration = Duration::from_secs(300);
+    pub static ref BROKEN_BLOCK_PICKUP_DELAY: Duration = Duration::from_millis(500);
+    pub static ref THROWN_ITEM_PICKUP_DELAY: Duration = Duration::from_secs(2);
+}
+
+// Mobs
+pub const MAX_MOBS: usize = 10;
+// Mobs appear on the grass between these distances from the player, and vanish further than the last one
+pub const MOB_SPAWN_MIN_DISTANCE: f32 = 24.0;
+pub const MOB_SPAWN_MAX_DISTANCE: f32 = 48.0;
+pub const MOB_DESPAWN_DISTANCE: f32 = 96.0;
+// Mobs wander to a spot at most this far away
+pub const MOB_WANDER_RADIUS: i32 = 8;
+pub const MOB_STEP_HEIGHT: f32 = 0.6;
+lazy_static! {
+    pub static ref MOB_SPAWN_INTERVAL: Duration = Duration::from_secs(2);
+    // A mob which didn't reach the next block of its path in this time gives up on it
+    pub static ref MOB_STUCK_TIMEOUT: Duration = Duration::from_secs(3);
+}
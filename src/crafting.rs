@@ -0,0 +1,396 @@
+use crate::inventory::item::{Item, ItemStack};
+
+pub const RECIPES_PATH: &str = "data/recipes.txt";
+
+/// A way to turn the items of a crafting grid into a stack of items
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recipe {
+    /// The ingredients have to follow the pattern (row by row, from the top), anywhere in the grid
+    /// The pattern can also be mirrored horizontally
+    Shaped {
+        width: usize,
+        height: usize,
+        pattern: Vec<Option<Item>>,
+        result: ItemStack,
+    },
+    /// The ingredients can be put in any slot of the grid
+    Shapeless {
+        ingredients: Vec<Item>,
+        result: ItemStack,
+    },
+}
+
+/// Smallest rectangle holding every item of a grid as (min x, min y, max x, max y)
+fn occupied_bounds(grid: &[Option<Item>], width: usize) -> Option<(usize, usize, usize, usize)> {
+    grid.iter()
+        .enumerate()
+        .filter(|(_, slot)| slot.is_some())
+        .map(|(index, _)| (index % width, index / width))
+        .fold(None, |bounds, (x, y)| match bounds {
+            None => Some((x, y, x, y)),
+            Some((min_x, min_y, max_x, max_y)) => Some((min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))),
+        })
+}
+
+impl Recipe {
+    /// The empty rows and columns around the pattern are removed, they don't matter when matching
+    pub fn shaped(rows: &[Vec<Option<Item>>], result: ItemStack) -> Result<Recipe, String> {
+        let width = rows.first().map_or(0, |row| row.len());
+        if rows.iter().any(|row| row.len() != width) {
+            return Err("the rows of the pattern don't have the same length".to_string());
+        }
+        let cells = rows.concat();
+        let (min_x, min_y, max_x, max_y) = occupied_bounds(&cells, width)
+            .ok_or_else(|| "the pattern is empty".to_string())?;
+
+        let pattern = (min_y..=max_y)
+            .flat_map(|y| rows[y][min_x..=max_x].to_vec())
+            .collect();
+        Ok(Recipe::Shaped {
+            width: max_x - min_x + 1,
+            height: max_y - min_y + 1,
+            pattern,
+            result,
+        })
+    }
+
+    pub fn result(&self) -> ItemStack {
+        match self {
+            Recipe::Shaped { result, .. } |
+            Recipe::Shapeless { result, .. } => *result,
+        }
+    }
+
+    /// Whether the items of a square grid of `grid_size` slots per side (row by row, from the top) follow the recipe
+    pub fn matches(&self, grid: &[Option<Item>], grid_size: usize) -> bool {
+        match self {
+            Recipe::Shaped { width, height, pattern, .. } => {
+                let (min_x, min_y, max_x, max_y) = match occupied_bounds(grid, grid_size) {
+                    Some(bounds) => bounds,
+                    None => return false,
+                };
+                if max_x - min_x + 1 != *width || max_y - min_y + 1 != *height {
+                    return false;
+                }
+
+                let matches_pattern = |mirrored: bool| {
+                    (0..*height).all(|y| (0..*width).all(|x| {
+                        let pattern_x = if mirrored { width - 1 - x } else { x };
+                        pattern[y * width + pattern_x] == grid[(min_y + y) * grid_size + min_x + x]
+                    }))
+                };
+                matches_pattern(false) || matches_pattern(true)
+            }
+            Recipe::Shapeless { ingredients, .. } => {
+                let mut remaining = ingredients.clone();
+                for item in grid.iter().filter_map(|slot| *slot) {
+                    match remaining.iter().position(|&ingredient| ingredient == item) {
+                        Some(index) => {
+                            remaining.swap_remove(index);
+                        }
+                        None => return false,
+                    }
+                }
+                remaining.is_empty()
+            }
+        }
+    }
+}
+
+fn parse_item(name: &str) -> Result<Item, String> {
+    Item::from_name(name).ok_or_else(|| format!("unknown item \"{}\"", name))
+}
+
+/// Parses a line of a recipe file:
+/// `shapeless <result> <amount> : <ingredient> <ingredient>...`
+/// `shaped <result> <amount> : <row>/<row>... <key>=<ingredient>...`
+/// Every character of the rows of a shaped recipe is a key, '.' is an empty slot
+pub fn parse_recipe(line: &str) -> Result<Recipe, String> {
+    let mut parts = line.splitn(2, ':');
+    let head = parts.next().unwrap().split_whitespace().collect::<Vec<_>>();
+    let ingredients = parts.next()
+        .ok_or_else(|| "missing ':' before the ingredients".to_string())?
+        .split_whitespace()
+        .collect::<Vec<_>>();
+
+    let (kind, result) = match head.as_slice() {
+        &[kind, result, amount] => {
+            let amount = amount.parse::<u32>()
+                .map_err(|_| format!("invalid amount \"{}\"", amount))?;
+            (kind, ItemStack::new(amount, parse_item(result)?))
+        }
+        _ => return Err("expected \"<shaped|shapeless> <result> <amount>\" before ':'".to_string()),
+    };
+
+    match kind {
+        "shapeless" => {
+            if ingredients.is_empty() {
+                return Err("a shapeless recipe needs at least one ingredient".to_string());
+            }
+            let ingredients = ingredients.iter()
+                .map(|&name| parse_item(name))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(Recipe::Shapeless { ingredients, result })
+        }
+        "shaped" => {
+            let (rows, keys) = ingredients.split_first()
+                .ok_or_else(|| "missing pattern".to_string())?;
+            let keys = keys.iter()
+                .map(|key| {
+                    let mut key_parts = key.splitn(2, '=');
+                    match (key_parts.next(), key_parts.next()) {
+                        (Some(key), Some(name)) if key.chars().count() == 1 => {
+                            Ok((key.chars().next().unwrap(), parse_item(name)?))
+                        }
+                        _ => Err(format!("invalid key \"{}\", expected <character>=<ingredient>", key)),
+                    }
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let rows = rows.split('/')
+                .map(|row| row.chars()
+                    .map(|c| match c {
+                        '.' => Ok(None),
+                        c => keys.iter()
+                            .find(|(key, _)| *key == c)
+                            .map(|&(_, item)| Some(item))
+                            .ok_or_else(|| format!("undefined key '{}'", c)),
+                    })
+                    .collect::<Result<Vec<_>, _>>())
+                .collect::<Result<Vec<_>, _>>()?;
+            Recipe::shaped(&rows, result)
+        }
+        kind => Err(format!("unknown recipe kind \"{}\"", kind)),
+    }
+}
+
+/// Every known recipe, the first one matching a grid wins
+#[derive(Default)]
+pub struct RecipeRegistry {
+    recipes: Vec<Recipe>,
+}
+
+impl RecipeRegistry {
+    pub fn new(recipes: Vec<Recipe>) -> Self {
+        RecipeRegistry { recipes }
+    }
+
+    /// Loads a recipe file, one recipe per line
+    /// Empty lines and lines starting with '#' are ignored
+    pub fn load(path: &str) -> Self {
+        let contents = match std::fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => panic!("Filename: {}, error: {}", path, err.to_string())
+        };
+        let recipes = contents.lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| match parse_recipe(line) {
+                Ok(recipe) => recipe,
+                Err(err) => panic!("Filename: {}, line {}: {}", path, i + 1, err)
+            })
+            .collect::<Vec<_>>();
+        info!("Loaded {} recipes", recipes.len());
+        RecipeRegistry::new(recipes)
+    }
+
+    pub fn find_result(&self, grid: &[Option<Item>], grid_size: usize) -> Option<ItemStack> {
+        self.recipes.iter()
+            .find(|recipe| recipe.matches(grid, grid_size))
+            .map(|recipe| recipe.result())
+    }
+}
+
+/// Slots in which the ingredients are put, 2x2 in the inventory screen and 3x3 for a crafting table
+pub struct CraftingGrid {
+    pub size: usize,
+    pub slots: Vec<Option<ItemStack>>,
+    // What the ingredients currently in the grid would make
+    pub result: Option<ItemStack>,
+}
+
+impl CraftingGrid {
+    pub fn new(size: usize) -> Self {
+        CraftingGrid {
+            size,
+            slots: vec![None; size * size],
+            result: None,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(|slot| slot.is_none())
+    }
+
+    pub fn update_result(&mut self, recipes: &RecipeRegistry) {
+        let items = self.slots.iter()
+            .map(|slot| slot.map(|stack| stack.item))
+            .collect::<Vec<_>>();
+        self.result = recipes.find_result(&items, self.size);
+    }
+
+    /// Uses one item of every slot, once the result has been taken
+    pub fn consume_ingredients(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if let Some(stack) = slot {
+                stack.amount -= 1;
+                if stack.amount == 0 {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    /// Empties the grid and gives it a new size
+    /// Returns the stacks which were in it
+    pub fn resize(&mut self, size: usize) -> Vec<ItemStack> {
+        let stacks = self.slots.drain(..).filter_map(|slot| slot).collect();
+        *self = CraftingGrid::new(size);
+        stacks
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::BlockID;
+
+    use super::*;
+
+    const PLANKS: Item = Item::Block(BlockID::OakPlanks);
+    const LOG: Item = Item::Block(BlockID::OakLog);
+    const COBBLESTONE: Item = Item::Block(BlockID::Cobblestone);
+
+    /// A grid of `grid_size` slots per side with the rows of `rows` starting at (x, y)
+    fn grid_with(grid_size: usize, rows: &[Vec<Option<Item>>], x: usize, y: usize) -> Vec<Option<Item>> {
+        let mut grid = vec![None; grid_size * grid_size];
+        for (row_y, row) in rows.iter().enumerate() {
+            for (row_x, &slot) in row.iter().enumerate() {
+                grid[(y + row_y) * grid_size + x + row_x] = slot;
+            }
+        }
+        grid
+    }
+
+    fn recipe(line: &str) -> Recipe {
+        parse_recipe(line).unwrap()
+    }
+
+    #[test]
+    fn shaped_recipe_matches_at_every_offset() {
+        let stick = recipe("shaped stick 4 : P/P P=oak_planks");
+        let crafting_table = recipe("shaped crafting_table 1 : PP/PP P=oak_planks");
+        let cases = [
+            (&stick, vec![vec![Some(PLANKS)], vec![Some(PLANKS)]]),
+            (&crafting_table, vec![vec![Some(PLANKS); 2]; 2]),
+        ];
+        for (recipe, rows) in cases.iter() {
+            for &grid_size in &[2, 3] {
+                for y in 0..=grid_size - rows.len() {
+                    for x in 0..=grid_size - rows[0].len() {
+                        let grid = grid_with(grid_size, rows, x, y);
+                        assert!(recipe.matches(&grid, grid_size), "{:?} at ({}, {}) in {}x{}", recipe, x, y, grid_size, grid_size);
+                    }
+                }
+            }
+        }
+
+        let pickaxe = recipe("shaped stone_pickaxe 1 : CCC/.S./.S. C=cobblestone S=stick");
+        let rows = vec![
+            vec![Some(COBBLESTONE); 3],
+            vec![None, Some(Item::Stick), None],
+            vec![None, Some(Item::Stick), None],
+        ];
+        assert!(pickaxe.matches(&grid_with(3, &rows, 0, 0), 3));
+        assert!(!pickaxe.matches(&grid_with(3, &rows[..2], 0, 0), 3));
+    }
+
+    #[test]
+    fn shaped_recipe_matches_mirrored_but_not_upside_down() {
+        let recipe = recipe("shaped stick 1 : PL/.L P=oak_planks L=oak_log");
+        let pattern = vec![vec![Some(PLANKS), Some(LOG)], vec![None, Some(LOG)]];
+        let mirrored = vec![vec![Some(LOG), Some(PLANKS)], vec![Some(LOG), None]];
+        let upside_down = vec![vec![None, Some(LOG)], vec![Some(PLANKS), Some(LOG)]];
+        let rotated = vec![vec![Some(LOG), Some(LOG)], vec![Some(PLANKS), None]];
+        for &grid_size in &[2, 3] {
+            assert!(recipe.matches(&grid_with(grid_size, &pattern, 0, 0), grid_size));
+            assert!(recipe.matches(&grid_with(grid_size, &mirrored, grid_size - 2, grid_size - 2), grid_size));
+            assert!(!recipe.matches(&grid_with(grid_size, &upside_down, 0, 0), grid_size));
+            assert!(!recipe.matches(&grid_with(grid_size, &rotated, 0, 0), grid_size));
+        }
+    }
+
+    #[test]
+    fn shaped_recipe_doesnt_match_extra_items() {
+        let recipe = recipe("shaped stick 4 : P/P P=oak_planks");
+        let mut grid = grid_with(3, &[vec![Some(PLANKS)], vec![Some(PLANKS)]], 0, 0);
+        grid[8] = Some(PLANKS);
+        assert!(!recipe.matches(&grid, 3));
+        assert!(!recipe.matches(&[None; 9], 3));
+    }
+
+    #[test]
+    fn shapeless_recipe_matches_in_any_order() {
+        let recipe = recipe("shapeless stick 1 : oak_planks oak_log oak_log");
+        for &grid_size in &[2, 3] {
+            let slots = grid_size * grid_size;
+            for planks_slot in 0..slots {
+                for log_slot in (0..slots).filter(|&slot| slot != planks_slot) {
+                    let other_log_slot = (0..slots).find(|&slot| slot != planks_slot && slot != log_slot).unwrap();
+                    let mut grid = vec![None; slots];
+                    grid[planks_slot] = Some(PLANKS);
+                    grid[log_slot] = Some(LOG);
+                    grid[other_log_slot] = Some(LOG);
+                    assert!(recipe.matches(&grid, grid_size));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shapeless_recipe_needs_exactly_its_ingredients() {
+        let recipe = recipe("shapeless oak_planks 4 : oak_log");
+        assert!(recipe.matches(&[None, Some(LOG), None, None], 2));
+        assert!(!recipe.matches(&[None, Some(LOG), Some(LOG), None], 2));
+        assert!(!recipe.matches(&[None, Some(LOG), Some(PLANKS), None], 2));
+        assert!(!recipe.matches(&[None, Some(PLANKS), None, None], 2));
+        assert!(!recipe.matches(&[None; 4], 2));
+    }
+
+    #[test]
+    fn malformed_lines_are_rejected() {
+        let lines = [
+            "shapeless oak_planks 4 oak_log",
+            "shapeless oak_planks : oak_log",
+            "shapeless oak_planks four : oak_log",
+            "shapeless oak_planks 4 :",
+            "shapeless oak_planks 4 : diamond",
+            "shapeless diamond 4 : oak_log",
+            "smelted oak_planks 4 : oak_log",
+            "shaped stick 4 :",
+            "shaped stick 4 : P/P",
+            "shaped stick 4 : P/P P=",
+            "shaped stick 4 : P/P PP=oak_planks",
+            "shaped stick 4 : PP/P P=oak_planks",
+            "shaped stick 4 : ../.. P=oak_planks",
+        ];
+        for line in lines.iter() {
+            assert!(parse_recipe(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn empty_rows_and_columns_are_trimmed() {
+        assert_eq!(
+            recipe("shaped stick 4 : .../.P./.P. P=oak_planks"),
+            recipe("shaped stick 4 : P/P P=oak_planks"),
+        );
+    }
+
+    #[test]
+    fn recipe_file_loads() {
+        let registry = RecipeRegistry::load(RECIPES_PATH);
+        assert!(!registry.recipes.is_empty());
+        assert_eq!(registry.find_result(&[Some(LOG), None, None, None], 2), Some(ItemStack::new(4, PLANKS)));
+    }
+}
@@ -0,0 +1,55 @@
+use nalgebra_glm::{IVec3, Vec3};
+
+/// Statistics gathered by the systems during the last frame, shown by the debug overlay (F3)
+#[derive(Default)]
+pub struct DebugInfo {
+    pub is_visible: bool,
+    pub fps: u32,
+    // In seconds
+    pub frame_time: f32,
+    // Chunk columns waiting to be handed to the world generation
+    pub pending_column_requests: usize,
+    // Chunks waiting to be uploaded to the GPU
+    pub pending_chunk_uploads: usize,
+    // Jobs of the world generation thread pool, running or waiting for a thread
+    pub world_generation_jobs: usize,
+    pub world_generation_threads: usize,
+}
+
+impl DebugInfo {
+    pub fn toggle(&mut self) {
+        self.is_visible = !self.is_visible;
+    }
+
+    /// Proportion of the world generation threads which are busy, from 0 to 1
+    pub fn world_generation_utilization(&self) -> f32 {
+        if self.world_generation_threads == 0 {
+            return 0.0;
+        }
+        self.world_generation_jobs.min(self.world_generation_threads) as f32 / self.world_generation_threads as f32
+    }
+}
+
+/// Compass direction of a horizontal vector, north being towards negative z
+pub fn facing(forward: &Vec3) -> &'static str {
+    if forward.x.abs() > forward.z.abs() {
+        if forward.x > 0.0 { "east (towards positive X)" } else { "west (towards negative X)" }
+    } else if forward.z > 0.0 {
+        "south (towards positive Z)"
+    } else {
+        "north (towards negative Z)"
+    }
+}
+
+/// Name of the face of a block from its normal
+pub fn face_name(normal: &IVec3) -> &'static str {
+    match (normal.x, normal.y, normal.z) {
+        (1, 0, 0) => "east",
+        (-1, 0, 0) => "west",
+        (0, 1, 0) => "up",
+        (0, -1, 0) => "down",
+        (0, 0, 1) => "south",
+        (0, 0, -1) => "north",
+        _ => "none",
+    }
+}
@@ -1,6 +1,6 @@
 //
-// Unused for now
-// Will be useful for drawing the UI
+// Batches textured quads into as few draw calls as possible
+// Used to draw the text of the UI
 //
 use gl;
 use std::collections::HashMap;
@@ -12,12 +12,22 @@ use std::cmp::Ordering;
 
 pub const NULLPTR: *mut c_void = 0 as *mut c_void;
 
+// The texture units below are bound once for the whole game (blocks, GUI textures...),
+// the batches bind their textures to the following ones
+pub const FIRST_BATCH_TEXTURE_UNIT: u32 = 8;
+// Size of the array of samplers of the shader
+pub const MAX_BATCH_TEXTURES: u32 = 8;
+// Position: 3 floats, texture index and UV coords: 3 floats, color: 4 floats
+const VERTEX_SIZE: usize = 10;
+
 #[derive(Clone, Debug)]
 pub struct QuadProps {
     pub position: (f32, f32, f32),
     pub size: (f32, f32),
     pub texture_id: u32,
     pub texture_coords: (f32, f32, f32, f32),
+    // Multiplied with the color of the texture
+    pub color: (f32, f32, f32, f32),
 }
 
 pub struct Renderer2D {
@@ -38,8 +48,8 @@ impl Renderer2D {
     pub fn new(capacity: usize) -> Self {
         let mut texture_units: i32 = 0;
         gl_call!(gl::GetIntegerv(gl::MAX_TEXTURE_IMAGE_UNITS, &mut texture_units));
+        let texture_units = (texture_units as u32).saturating_sub(FIRST_BATCH_TEXTURE_UNIT).min(MAX_BATCH_TEXTURES);
         assert!(texture_units > 0);
-        let texture_units = texture_units as u32;
 
         // Group by texture ID
         let quads: HashMap<u32, Vec<QuadProps>> = HashMap::new();
@@ -60,7 +70,8 @@ impl Renderer2D {
         let mut vao = 0;
 
         let binding_index_pos = 0;
-        let binding_index_color = 1;
+        let binding_index_texture_coords = 1;
+        let binding_index_color = 2;
 
         gl_call!(gl::CreateVertexArrays(1, &mut vao));
 
@@ -68,14 +79,21 @@ impl Renderer2D {
         gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3, gl::FLOAT, gl::FALSE, 0));
 
         gl_call!(gl::VertexArrayAttribBinding(vao, 0, binding_index_pos));
-        gl_call!(gl::VertexArrayVertexBuffer(vao, binding_index_pos, vbo, 0, (6 * std::mem::size_of::<f32>()) as i32));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, binding_index_pos, vbo, 0, (VERTEX_SIZE * std::mem::size_of::<f32>()) as i32));
 
 
         gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
         gl_call!(gl::VertexArrayAttribFormat(vao, 1, 3, gl::FLOAT, gl::FALSE, (3 * std::mem::size_of::<f32>()) as u32));
 
-        gl_call!(gl::VertexArrayAttribBinding(vao, 1, binding_index_color));
-        gl_call!(gl::VertexArrayVertexBuffer(vao, binding_index_color, vbo, 0, (6 * std::mem::size_of::<f32>() as isize) as i32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 1, binding_index_texture_coords));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, binding_index_texture_coords, vbo, 0, (VERTEX_SIZE * std::mem::size_of::<f32>()) as i32));
+
+
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 2, 4, gl::FLOAT, gl::FALSE, (6 * std::mem::size_of::<f32>()) as u32));
+
+        gl_call!(gl::VertexArrayAttribBinding(vao, 2, binding_index_color));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, binding_index_color, vbo, 0, (VERTEX_SIZE * std::mem::size_of::<f32>()) as i32));
 
         Renderer2D {
             texture_units,
@@ -128,20 +146,22 @@ impl Renderer2D {
                         position: (x, y, z),
                         size: (w, h),
                         texture_id: _,
-                        texture_coords: (tex_x_min, tex_y_min, tex_x_max, tex_y_max)
+                        texture_coords: (tex_x_min, tex_y_min, tex_x_max, tex_y_max),
+                        color: (r, g, b, a),
                     } = *quad;
 
                     let tex_unit = tex_unit as f32;
-                    self.vertices.extend_from_slice(&[x, y, z, tex_unit, tex_x_min, tex_y_min]);
-                    self.vertices.extend_from_slice(&[x + w, y, z, tex_unit, tex_x_max, tex_y_min]);
-                    self.vertices.extend_from_slice(&[x + w, y + h, z, tex_unit, tex_x_max, tex_y_max]);
-                    self.vertices.extend_from_slice(&[x + w, y + h, z, tex_unit, tex_x_max, tex_y_max]);
-                    self.vertices.extend_from_slice(&[x, y + h, z, tex_unit, tex_x_min, tex_y_max]);
-                    self.vertices.extend_from_slice(&[x, y, z, tex_unit, tex_x_min, tex_y_min]);
+                    self.vertices.extend_from_slice(&[x, y, z, tex_unit, tex_x_min, tex_y_min, r, g, b, a]);
+                    self.vertices.extend_from_slice(&[x + w, y, z, tex_unit, tex_x_max, tex_y_min, r, g, b, a]);
+                    self.vertices.extend_from_slice(&[x + w, y + h, z, tex_unit, tex_x_max, tex_y_max, r, g, b, a]);
+                    self.vertices.extend_from_slice(&[x + w, y + h, z, tex_unit, tex_x_max, tex_y_max, r, g, b, a]);
+                    self.vertices.extend_from_slice(&[x, y + h, z, tex_unit, tex_x_min, tex_y_max, r, g, b, a]);
+                    self.vertices.extend_from_slice(&[x, y, z, tex_unit, tex_x_min, tex_y_min, r, g, b, a]);
                 }
 
-                gl_call!(gl::BindTextureUnit(tex_unit as u32, texture_id));
-                tex_units.push(tex_unit as i32);
+                let unit = FIRST_BATCH_TEXTURE_UNIT + tex_unit as u32;
+                gl_call!(gl::BindTextureUnit(unit, texture_id));
+                tex_units.push(unit as i32);
             };
 
             program.use_program();
@@ -153,7 +173,7 @@ impl Renderer2D {
             self.vertices.as_ptr() as *mut c_void));
 
             gl_call!(gl::BindVertexArray(self.vao));
-            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, (self.vertices.len() / 6) as i32));
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, (self.vertices.len() / VERTEX_SIZE) as i32));
             _draw_calls += 1;
         }
 //        println!("Total draw calls: {}", draw_calls);
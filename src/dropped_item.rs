@@ -0,0 +1,155 @@
+use std::os::raw::c_void;
+use std::time::{Duration, Instant};
+
+use nalgebra::Matrix4;
+use nalgebra_glm::{Mat4, Vec3, vec3};
+use rand::random;
+use specs::{Builder, Entities, LazyUpdate};
+
+use crate::constants::{BROKEN_BLOCK_PICKUP_DELAY, DROPPED_ITEM_DRAG, DROPPED_ITEM_HALF_SIZE, DROPPED_ITEM_SIZE, DROPPED_ITEM_THROW_SPEED, PHYSICS_TICKRATE, THROWN_ITEM_PICKUP_DELAY};
+use crate::inventory::item::{Item, ItemStack};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::PlayerState;
+use crate::shader_compilation::ShaderProgram;
+use crate::types::{ItemIcons, TexturePack};
+use crate::util::Forward;
+
+/// A stack of items lying in the world
+/// Its position is stored in an `Interpolator<EntityPhysicsState>` component, the bottom center of the item
+pub struct DroppedItem {
+    pub stack: ItemStack,
+    pub spawned_at: Instant,
+    // The item can't be picked up before this delay has passed
+    pub pickup_delay: Duration,
+}
+
+impl DroppedItem {
+    pub fn can_be_picked_up(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.spawned_at) >= self.pickup_delay
+    }
+}
+
+fn dropped_item_physics_body() -> PhysicsBody {
+    PhysicsBody {
+        drag: DROPPED_ITEM_DRAG,
+        ..PhysicsBody::new(DROPPED_ITEM_SIZE, DROPPED_ITEM_SIZE)
+    }
+}
+
+/// Creates a dropped item entity, it is added to the world at the end of the frame
+pub fn spawn_dropped_item(lazy_update: &LazyUpdate, entities: &Entities, stack: ItemStack, position: Vec3, velocity: Vec3, pickup_delay: Duration) {
+    let body = dropped_item_physics_body();
+    let mut state = EntityPhysicsState::new_at_position(position, &body);
+    state.velocity = velocity;
+    lazy_update.create_entity(entities)
+        .with(DroppedItem {
+            stack,
+            spawned_at: Instant::now(),
+            pickup_delay,
+        })
+        .with(Interpolator::new(1.0 / PHYSICS_TICKRATE, state))
+        .with(body)
+        .build();
+}
+
+/// Drops the item of a broken block at its center, with a little random jump
+pub fn spawn_block_drop(lazy_update: &LazyUpdate, entities: &Entities, stack: ItemStack, (x, y, z): (i32, i32, i32)) {
+    let position = vec3(x as f32 + 0.5, y as f32 + 0.5 - DROPPED_ITEM_HALF_SIZE, z as f32 + 0.5);
+    let velocity = vec3(random::<f32>() * 2.0 - 1.0, 4.0, random::<f32>() * 2.0 - 1.0);
+    spawn_dropped_item(lazy_update, entities, stack, position, velocity, *BROKEN_BLOCK_PICKUP_DELAY);
+}
+
+/// Throws a stack from the eyes of the player in the direction they are looking at
+pub fn throw_stack(lazy_update: &LazyUpdate, entities: &Entities, stack: ItemStack, player_state: &PlayerState, player_physics_state: &EntityPhysicsState) {
+    let eyes = player_physics_state.position + vec3(0.0, *player_state.camera_height.get_latest_state(), 0.0);
+    let forward = player_state.rotation.forward().normalize();
+    let position = eyes - vec3(0.0, 0.3, 0.0);
+    let velocity = forward * DROPPED_ITEM_THROW_SPEED + vec3(0.0, 2.0, 0.0) + player_physics_state.velocity;
+    info!("Threw {} {:?}", stack.amount, stack.item);
+    spawn_dropped_item(lazy_update, entities, stack, position, velocity, *THROWN_ITEM_PICKUP_DELAY);
+}
+
+/// Drops stacks around a position in every direction, like the inventory of a player who died
+pub fn scatter_stacks(lazy_update: &LazyUpdate, entities: &Entities, stacks: Vec<ItemStack>, position: Vec3) {
+    for stack in stacks {
+        let velocity = vec3(random::<f32>() * 4.0 - 2.0, 3.0 + random::<f32>() * 2.0, random::<f32>() * 4.0 - 2.0);
+        spawn_dropped_item(lazy_update, entities, stack, position + vec3(0.0, 1.0, 0.0), velocity, *THROWN_ITEM_PICKUP_DELAY);
+    }
+}
+
+/// GPU buffers of the model of an item, shared by all of the dropped items of this kind
+pub struct ItemModel {
+    vao: u32,
+    vbo: u32,
+    vertices: i32,
+}
+
+impl ItemModel {
+    pub fn new(item: Item, texture_pack: &TexturePack, item_icons: &ItemIcons) -> Self {
+        let mut vao = 0;
+        gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 1, 3 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+        // Normals
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 2, 3 as i32, gl::FLOAT, gl::FALSE, 6 * std::mem::size_of::<f32>() as u32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 2, 0));
+
+        let vbo_data = item.model_vertices(&texture_pack, &item_icons);
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+        gl_call!(gl::NamedBufferData(vbo,
+                    (vbo_data.len() * std::mem::size_of::<f32>() as usize) as isize,
+                    vbo_data.as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, (9 * std::mem::size_of::<f32>()) as i32));
+
+        ItemModel {
+            vao,
+            vbo,
+            vertices: (vbo_data.len() / 9) as i32,
+        }
+    }
+
+    /// Draws a small cube (or icon) spinning around the y axis and bobbing up and down
+    /// `age` is the time since the item was dropped
+    pub fn draw(&self, position: &Vec3, age: f32, shader: &mut ShaderProgram) {
+        let model_matrix: Mat4 = {
+            let bobbing = (age * 2.0).sin() * 0.05 + 0.05;
+            let translate_matrix = Matrix4::new_translation(&(position + vec3(0.0, DROPPED_ITEM_HALF_SIZE + bobbing, 0.0)));
+            let rotate_matrix = Matrix4::from_euler_angles(0.0, age * 1.5, 0.0);
+            let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(DROPPED_ITEM_SIZE, DROPPED_ITEM_SIZE, DROPPED_ITEM_SIZE));
+            translate_matrix * rotate_matrix * scale_matrix
+        };
+        self.draw_with_model_matrix(&model_matrix, shader);
+    }
+
+    /// Draws the cube of a block at its full size, `position` being the center of its bottom face
+    pub fn draw_full_block(&self, position: &Vec3, shader: &mut ShaderProgram) {
+        let model_matrix: Mat4 = Matrix4::new_translation(&(position + vec3(0.0, 0.5, 0.0)));
+        self.draw_with_model_matrix(&model_matrix, shader);
+    }
+
+    fn draw_with_model_matrix(&self, model_matrix: &Mat4, shader: &mut ShaderProgram) {
+        shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+
+        gl_call!(gl::BindVertexArray(self.vao));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices));
+    }
+}
+
+impl Drop for ItemModel {
+    fn drop(&mut self) {
+        gl_call!(gl::DeleteBuffers(1, &self.vbo));
+        gl_call!(gl::DeleteVertexArrays(1, &self.vao));
+    }
+}
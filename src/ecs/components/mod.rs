@@ -2,11 +2,19 @@ use specs::Component;
 use specs::DenseVecStorage;
 use specs::NullStorage;
 
+use crate::dropped_item::DroppedItem;
+use crate::falling_block::FallingBlock;
+use crate::health::Health;
 use crate::inventory::Inventory;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::mob::{Mob, MobAI};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::PlayerState;
 
-impl Component for Interpolator<PlayerPhysicsState> {
+impl Component for Interpolator<EntityPhysicsState> {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for PhysicsBody {
     type Storage = DenseVecStorage<Self>;
 }
 
@@ -23,4 +31,24 @@ impl Component for MainHandItemChanged {
 
 impl Component for Inventory {
     type Storage = DenseVecStorage<Self>;
-}
\ No newline at end of file
+}
+
+impl Component for DroppedItem {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for FallingBlock {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for Health {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for Mob {
+    type Storage = DenseVecStorage<Self>;
+}
+
+impl Component for MobAI {
+    type Storage = DenseVecStorage<Self>;
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::{Rng, thread_rng};
+use specs::{Entities, LazyUpdate, Read, System};
+
+use crate::block_tick::{BlockBehaviours, TickContext};
+use crate::chunk::ChunkColumn;
+use crate::chunk_manager::{CHUNK_SIZE, ChunkManager};
+use crate::constants::{BLOCK_UPDATES_PER_TICK, MAX_BLOCK_TICKS_PER_FRAME, PHYSICS_TICKRATE, RANDOM_TICKS_PER_SECTION, SCHEDULED_TICKS_PER_TICK};
+use crate::timer::Timer;
+
+/// Runs the scheduled and random block ticks on the physics timestep
+pub struct UpdateBlockTicks {
+    // Time not simulated yet, in ticks
+    ticks_behind: f32,
+    last_updated: Instant,
+}
+
+impl UpdateBlockTicks {
+    pub fn new() -> Self {
+        Self {
+            ticks_behind: 0.0,
+            last_updated: Instant::now(),
+        }
+    }
+
+    fn run_tick(columns: &[((i32, i32), Arc<ChunkColumn>)], behaviours: &BlockBehaviours, context: &TickContext) {
+        let chunk_manager = context.chunk_manager;
+        let tick = chunk_manager.advance_tick();
+
+        // Block updates, taken before running them so that blocks updating each other run once per tick
+        let updates = chunk_manager.block_updates.write().take(BLOCK_UPDATES_PER_TICK);
+        for update in updates {
+            let (x, y, z) = update.position;
+            let behaviour = chunk_manager.get_block(x, y, z)
+                .and_then(|block| behaviours.get(block));
+            if let Some(behaviour) = behaviour {
+                behaviour.neighbour_changed(context, x, y, z, update.from);
+            }
+        }
+
+        // Scheduled ticks, the ticks left over by the budget run at the next tick
+        let mut due = Vec::new();
+        'columns: for (_, column) in columns {
+            for chunk in column.chunks.iter() {
+                let mut scheduled_ticks = chunk.scheduled_ticks.write();
+                while let Some(scheduled_tick) = scheduled_ticks.pop_due(tick) {
+                    due.push(scheduled_tick);
+                    if due.len() >= SCHEDULED_TICKS_PER_TICK {
+                        break 'columns;
+                    }
+                }
+            }
+        }
+        due.sort();
+        for scheduled_tick in due {
+            let (x, y, z) = scheduled_tick.position;
+            if chunk_manager.get_block(x, y, z) != Some(scheduled_tick.block) {
+                continue;
+            }
+            if let Some(behaviour) = behaviours.get(scheduled_tick.block) {
+                behaviour.scheduled_tick(context, x, y, z);
+            }
+        }
+
+        // Random ticks
+        let mut rng = thread_rng();
+        for &((c_x, c_z), ref column) in columns {
+            for (c_y, chunk) in column.chunks.iter().enumerate() {
+                if chunk.is_empty() {
+                    continue;
+                }
+                for _ in 0..RANDOM_TICKS_PER_SECTION {
+                    let (b_x, b_y, b_z) = (
+                        rng.gen_range(0, CHUNK_SIZE),
+                        rng.gen_range(0, CHUNK_SIZE),
+                        rng.gen_range(0, CHUNK_SIZE));
+                    if let Some(behaviour) = behaviours.get(chunk.get_block(b_x, b_y, b_z)) {
+                        let size = CHUNK_SIZE as i32;
+                        behaviour.random_tick(context,
+                                              c_x * size + b_x as i32,
+                                              c_y as i32 * size + b_y as i32,
+                                              c_z * size + b_z as i32);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for UpdateBlockTicks {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, BlockBehaviours>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            chunk_manager,
+            behaviours,
+            lazy_update,
+            entities,
+        ) = data;
+
+        let now = global_timer.time();
+        self.ticks_behind += now.saturating_duration_since(self.last_updated).as_secs_f32() * PHYSICS_TICKRATE;
+        self.ticks_behind = self.ticks_behind.min(MAX_BLOCK_TICKS_PER_FRAME as f32);
+        self.last_updated = now;
+        if self.ticks_behind < 1.0 {
+            return;
+        }
+
+        // The behaviours change blocks, which needs the lock of the loaded columns
+        let columns: Vec<_> = chunk_manager.loaded_chunk_columns.read().iter()
+            .map(|(&xz, column)| (xz, Arc::clone(column)))
+            .collect();
+        let context = TickContext {
+            chunk_manager: &chunk_manager,
+            lazy_update: &lazy_update,
+            entities: &entities,
+        };
+        while self.ticks_behind >= 1.0 {
+            self.ticks_behind -= 1.0;
+            Self::run_tick(&columns, &behaviours, &context);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use glfw::{Action, Key, WindowEvent};
+use specs::{Entities, Join, LazyUpdate, Read, System, Write, WriteStorage};
+
+use crate::chat::{Chat, PLAYER_NAME};
+use crate::chunk_manager::ChunkManager;
+use crate::command::{Command, fill_volume, HELP, MAX_FILL_VOLUME, parse_command, TimeQuery};
+use crate::constants::{GUI_SCALING, WINDOW_WIDTH, WORLD_SEED};
+use crate::dropped_item::scatter_stacks;
+use crate::ecs::components::MainHandItemChanged;
+use crate::gui::{create_overlay_vao, draw_rectangle, GuiState, Screen};
+use crate::input::InputCache;
+use crate::inventory::Inventory;
+use crate::inventory::item::ItemStack;
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
+use crate::text::{LINE_HEIGHT, TextRenderer, WHITE};
+use crate::types::Shaders;
+use crate::weather::{Weather, WeatherKind};
+use crate::world_time::WorldTime;
+
+pub struct HandleChatInput;
+
+impl HandleChatInput {
+    /// Runs a command for the player, returns the message to show in the chat
+    fn execute(
+        command: Command,
+        chunk_manager: &ChunkManager,
+        world_time: &mut WorldTime,
+        weather: &mut Weather,
+        player_state: &mut PlayerState,
+        player_physics_state: &mut Interpolator<EntityPhysicsState>,
+        inventory: &mut Inventory,
+        f: &mut dyn FnMut(),
+        drop: &mut dyn FnMut(Vec<ItemStack>),
+    ) -> Result<String, String> {
+        let origin = player_physics_state.get_latest_state().position;
+        match command {
+            // There is a single player, every selector targets it
+            Command::Teleport { target: _, destination } => {
+                let destination = destination.resolve(&origin);
+                let state = player_physics_state.get_latest_state().teleported_to(destination);
+                player_physics_state.reset(state);
+                Ok(format!("Teleported {} to {:.2} {:.2} {:.2}", PLAYER_NAME, destination.x, destination.y, destination.z))
+            }
+            Command::SetBlock { position, block } => {
+                let (x, y, z) = position.resolve_block(&origin);
+                if chunk_manager.put_block(block, x, y, z) {
+                    Ok(format!("Changed the block at {} {} {}", x, y, z))
+                } else {
+                    Err(format!("The block at {} {} {} is not loaded", x, y, z))
+                }
+            }
+            Command::Fill { from, to, block } => {
+                let (x1, y1, z1) = from.resolve_block(&origin);
+                let (x2, y2, z2) = to.resolve_block(&origin);
+                let volume = fill_volume((x1, y1, z1), (x2, y2, z2));
+                if volume > MAX_FILL_VOLUME {
+                    return Err(format!("Too many blocks in the specified area (maximum {}, specified {})", MAX_FILL_VOLUME, volume));
+                }
+                let mut changed = 0;
+                for x in x1.min(x2)..=x1.max(x2) {
+                    for y in y1.min(y2)..=y1.max(y2) {
+                        for z in z1.min(z2)..=z1.max(z2) {
+                            if chunk_manager.set_block(block, x, y, z) {
+                                chunk_manager.update_heighest_block(block, x, y, z);
+                                changed += 1;
+                            }
+                        }
+                    }
+                }
+                Ok(format!("Successfully filled {} blocks", changed))
+            }
+            Command::Give { target: _, item, amount } => {
+                let mut remaining = amount;
+                let mut overflow = vec![];
+                while remaining > 0 {
+                    let stack_size = remaining.min(item.max_stack_size());
+                    remaining -= stack_size;
+                    if let Err(stack) = inventory.try_insert(ItemStack::new(stack_size, item)) {
+                        overflow.push(stack);
+                    }
+                }
+                f();
+                // What doesn't fit in the inventory is dropped at the feet of the player
+                drop(overflow);
+                Ok(format!("Gave {} {} to {}", amount, item.name().unwrap_or("?"), PLAYER_NAME))
+            }
+            Command::GameMode { game_mode, target: _ } => {
+                player_state.game_mode = game_mode;
+                player_state.breaking_block = None;
+                Ok(format!("Set the game mode of {} to {:?}", PLAYER_NAME, game_mode))
+            }
+            Command::Seed => Ok(format!("Seed: {}", *WORLD_SEED)),
+            Command::SetTime(time) => {
+                world_time.set_time_of_day(time);
+                Ok(format!("Set the time to {}", time))
+            }
+            Command::AddTime(ticks) => {
+                world_time.add_ticks(ticks);
+                Ok(format!("Set the time to {}", world_time.time_of_day()))
+            }
+            Command::QueryTime(query) => Ok(match query {
+                TimeQuery::DayTime => format!("The time is {}", world_time.time_of_day()),
+                TimeQuery::GameTime => format!("The time is {}", world_time.ticks()),
+                TimeQuery::Day => format!("The day is {}", world_time.day()),
+            }),
+            Command::Fly(state) => {
+                player_state.is_flying = state.unwrap_or(!player_state.is_flying);
+                Ok(format!("Flying {}", if player_state.is_flying { "enabled" } else { "disabled" }))
+            }
+            Command::Weather { kind, duration } => {
+                weather.set(kind, duration.map(|duration| Duration::from_secs(duration as u64)));
+                Ok(match kind {
+                    WeatherKind::Clear => "Changing to clear",
+                    WeatherKind::Rain => "Changing to rain",
+                    WeatherKind::Snow => "Changing to snow",
+                    WeatherKind::Thunder => "Changing to rain and thunder",
+                }.to_string())
+            }
+            Command::Help => Ok(HELP.to_string()),
+        }
+    }
+}
+
+impl<'a> System<'a> for HandleChatInput {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, InputCache>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, LazyUpdate>,
+        Write<'a, GuiState>,
+        Write<'a, Chat>,
+        Write<'a, WorldTime>,
+        Write<'a, Weather>,
+        WriteStorage<'a, PlayerState>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input_cache,
+            chunk_manager,
+            lazy_update,
+            mut gui_state,
+            mut chat,
+            mut world_time,
+            mut weather,
+            mut player_state,
+            mut player_physics_state,
+            mut inventory,
+            mut main_hand_item_changed,
+        ) = data;
+
+        for (e, player_state, player_physics_state, inventory) in (&entities, &mut player_state, &mut player_physics_state, &mut inventory).join() {
+            // The character of the key opening the chat is not typed in it
+            let mut is_opening = false;
+
+            for event in &input_cache.events {
+                match event {
+                    WindowEvent::Key(Key::T, _, Action::Press, _) if !gui_state.is_screen_open() => {
+                        chat.open("");
+                        gui_state.open_screen(Screen::Chat);
+                        is_opening = true;
+                    }
+                    WindowEvent::Key(Key::Slash, _, Action::Press, _) if !gui_state.is_screen_open() => {
+                        chat.open("/");
+                        gui_state.open_screen(Screen::Chat);
+                        is_opening = true;
+                    }
+                    _ if gui_state.screen != Some(Screen::Chat) => {}
+                    &WindowEvent::Char(c) => {
+                        if !is_opening {
+                            chat.type_char(c);
+                        }
+                    }
+                    &WindowEvent::Key(Key::Backspace, _, action, _) if action != Action::Release => chat.erase_char(),
+                    WindowEvent::Key(Key::Tab, _, Action::Press, _) => chat.complete(),
+                    WindowEvent::Key(Key::Up, _, Action::Press, _) => chat.history_previous(),
+                    WindowEvent::Key(Key::Down, _, Action::Press, _) => chat.history_next(),
+                    WindowEvent::Key(Key::Enter, _, Action::Press, _) |
+                    WindowEvent::Key(Key::KpEnter, _, Action::Press, _) => {
+                        gui_state.close_screen();
+                        let line = match chat.submit() {
+                            Some(line) => line,
+                            None => continue,
+                        };
+                        match line.strip_prefix('/') {
+                            Some(command) => {
+                                let mut f = || {
+                                    if let Err(e) = main_hand_item_changed.insert(e, MainHandItemChanged) {
+                                        error!("{}", e);
+                                    }
+                                };
+                                let position = player_physics_state.get_latest_state().position;
+                                let mut drop = |stacks: Vec<ItemStack>| {
+                                    scatter_stacks(&lazy_update, &entities, stacks, position);
+                                };
+                                let result = parse_command(command).and_then(|command| Self::execute(
+                                    command, &chunk_manager, &mut world_time, &mut weather, player_state,
+                                    player_physics_state, inventory, &mut f, &mut drop));
+                                match result {
+                                    Ok(message) => chat.add_info(&message),
+                                    Err(error) => chat.add_error(&error),
+                                }
+                            }
+                            None => chat.add_info(&format!("<{}> {}", PLAYER_NAME, line)),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+// Messages stay on the screen for a while after being received, the last second fading out
+const MESSAGE_DURATION: f32 = 10.0;
+const MAX_VISIBLE_MESSAGES: usize = 10;
+// More messages are shown while the chat is open
+const MAX_VISIBLE_MESSAGES_OPEN: usize = 20;
+const CHAT_WIDTH: f32 = 320.0;
+const INPUT_HEIGHT: f32 = 12.0;
+const SUGGESTIONS_COLOR: (f32, f32, f32, f32) = (1.0, 1.0, 0.33, 1.0);
+
+pub struct RenderChat {
+    text_renderer: TextRenderer,
+    overlay_vao: u32,
+    created_at: Instant,
+}
+
+impl RenderChat {
+    pub fn new() -> Self {
+        Self {
+            text_renderer: TextRenderer::new(),
+            overlay_vao: create_overlay_vao(),
+            created_at: Instant::now(),
+        }
+    }
+
+    /// The end of the input which fits in the box, followed by a blinking cursor
+    fn input_line(&self, input: &str, width: f32) -> String {
+        let mut start = 0;
+        while self.text_renderer.text_width(&input[start..], GUI_SCALING) > width {
+            start += input[start..].chars().next().map_or(0, |c| c.len_utf8());
+        }
+        let blink = (self.created_at.elapsed().as_millis() / 300) % 2 == 0;
+        format!("{}{}", &input[start..], if blink { "_" } else { "" })
+    }
+}
+
+impl<'a> System<'a> for RenderChat {
+    type SystemData = (
+        Read<'a, Chat>,
+        Read<'a, GuiState>,
+        Write<'a, Shaders>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            chat,
+            gui_state,
+            mut shaders,
+        ) = data;
+
+        let is_open = gui_state.screen == Some(Screen::Chat);
+        let now = Instant::now();
+        let max_visible_messages = if is_open { MAX_VISIBLE_MESSAGES_OPEN } else { MAX_VISIBLE_MESSAGES };
+        // Newest first, with their opacity
+        let messages = chat.messages.iter()
+            .rev()
+            .take(max_visible_messages)
+            .filter_map(|message| {
+                let age = now.saturating_duration_since(message.received_at).as_secs_f32();
+                match is_open {
+                    true => Some((message, 1.0)),
+                    false if age < MESSAGE_DURATION => Some((message, (MESSAGE_DURATION - age).min(1.0))),
+                    false => None,
+                }
+            })
+            .collect::<Vec<_>>();
+        if !is_open && messages.is_empty() {
+            return;
+        }
+
+        gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+        gl_call!(gl::Disable(gl::DEPTH_TEST));
+        let margin = 2.0 * GUI_SCALING;
+        let line_height = LINE_HEIGHT * GUI_SCALING;
+        let chat_width = CHAT_WIDTH * GUI_SCALING;
+        let input_height = INPUT_HEIGHT * GUI_SCALING;
+        let text_x = margin * 2.0;
+        // Bottom of the line of text drawn at the top of the input box
+        let mut line_y = margin + input_height + margin;
+        self.text_renderer.begin();
+
+        let color_shader = shaders.get_mut("color_shader").unwrap();
+        if is_open {
+            draw_rectangle(self.overlay_vao, (margin, margin, WINDOW_WIDTH as f32 - margin * 2.0, input_height), [0.0, 0.0, 0.0, 0.5], color_shader);
+            let input_line = self.input_line(&chat.input, WINDOW_WIDTH as f32 - text_x * 2.0);
+            self.text_renderer.draw_text_with_shadow((text_x, margin + input_height - margin), GUI_SCALING, WHITE, &input_line);
+
+            if let Some(candidates) = chat.completion_candidates() {
+                draw_rectangle(self.overlay_vao, (margin, line_y, chat_width, line_height), [0.0, 0.0, 0.0, 0.8], color_shader);
+                self.text_renderer.draw_text_with_shadow((text_x, line_y + line_height), GUI_SCALING, SUGGESTIONS_COLOR, &candidates.join(" "));
+                line_y += line_height;
+            }
+        }
+
+        for (message, opacity) in messages {
+            draw_rectangle(self.overlay_vao, (margin, line_y, chat_width, line_height), [0.0, 0.0, 0.0, 0.5 * opacity], color_shader);
+            let (r, g, b, a) = message.color;
+            self.text_renderer.draw_text_with_shadow((text_x, line_y + line_height), GUI_SCALING, (r, g, b, a * opacity), &message.text);
+            line_y += line_height;
+        }
+
+        self.text_renderer.end(shaders.get_mut("renderer2d_shader").unwrap());
+        gl_call!(gl::Enable(gl::DEPTH_TEST));
+    }
+}
@@ -2,6 +2,7 @@ use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, VecDeque};
 use std::ops::Deref;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 use std::time::{Duration, Instant};
 
 use bit_vec::BitVec;
@@ -9,14 +10,16 @@ use crossbeam_channel::{Receiver, Sender, unbounded};
 use noise::{NoiseFn, Point2, Point3, Seedable, SuperSimplex};
 use num_traits::abs;
 use parking_lot::RwLock;
-use specs::{Join, Read, ReadStorage, System};
+use specs::{Join, Read, ReadStorage, System, Write};
 
 use crate::chunk::{BlockID, BlockIterator, Chunk, ChunkColumn};
 use crate::chunk_manager::ChunkManager;
-use crate::constants::{CHUNK_UPLOADS_PER_FRAME, RENDER_DISTANCE, WORLD_GENERATION_THREAD_POOL_SIZE, WORLD_SEED};
-use crate::physics::Interpolator;
-use crate::player::PlayerPhysicsState;
+use crate::constants::{CHUNK_UPLOADS_PER_FRAME, RENDER_DISTANCE, SEA_FLOOR_DEPTH, SEA_LEVEL, WORLD_GENERATION_THREAD_POOL_SIZE, WORLD_SEED};
+use crate::debug_overlay::DebugInfo;
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
 use crate::types::TexturePack;
+use crate::vegetation::oak_tree;
 
 #[derive(Eq)]
 struct PrioritizedItem<T> {
@@ -50,6 +53,22 @@ impl<T> Deref for PrioritizedItem<T> {
     }
 }
 
+/// Counts a job of the world generation thread pool until it is dropped at the end of the job
+struct WorldGenerationJob(Arc<AtomicUsize>);
+
+impl WorldGenerationJob {
+    fn new(counter: &Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, AtomicOrdering::SeqCst);
+        WorldGenerationJob(Arc::clone(counter))
+    }
+}
+
+impl Drop for WorldGenerationJob {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, AtomicOrdering::SeqCst);
+    }
+}
+
 pub struct ChunkLoading {
     noise_fn: SuperSimplex,
     chunk_column_pool: Arc<RwLock<Vec<Arc<ChunkColumn>>>>,
@@ -68,6 +87,8 @@ pub struct ChunkLoading {
     expand_chunks: Arc<RwLock<bool>>,
     world_generation_thread_pool: rayon::ThreadPool,
     player_interaction_thread_pool: rayon::ThreadPool,
+    // Jobs of the world generation thread pool, running or waiting for a thread
+    world_generation_jobs: Arc<AtomicUsize>,
 }
 
 fn compute_tree_placement_in_chunk(noise: &SuperSimplex, x: f64, z: f64) -> Vec<(u32, u32)> {
@@ -149,6 +170,7 @@ impl ChunkLoading {
             player_interaction_thread_pool: rayon::ThreadPoolBuilder::new()
                 .num_threads(1)
                 .build().unwrap(),
+            world_generation_jobs: Arc::new(AtomicUsize::new(0)),
         }
     }
 
@@ -369,19 +391,23 @@ impl ChunkLoading {
 
 impl<'a> System<'a> for ChunkLoading {
     type SystemData = (
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
         Read<'a, Arc<ChunkManager>>,
         Read<'a, TexturePack>,
+        Write<'a, DebugInfo>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
+            player_state,
             player_physics_state,
             chunk_manager,
             texture_pack,
+            mut debug_info,
         ) = data;
 
-        for player_physics_state in (&player_physics_state).join() {
+        for (_, player_physics_state) in (&player_state, &player_physics_state).join() {
             let state = player_physics_state.get_latest_state();
             let (c_x, c_y, c_z, _, _, _) = ChunkManager::get_chunk_coords(
                 state.position.x as i32,
@@ -399,7 +425,7 @@ impl<'a> System<'a> for ChunkLoading {
                             if abs(x - c_x) > RENDER_DISTANCE ||
                                 abs(y - c_y) > RENDER_DISTANCE ||
                                 abs(z - c_z) > RENDER_DISTANCE {
-                                chunk.unload_from_gpu();
+                                chunk.unload_from_gpu(&mut chunk_manager.geometry_arena.write());
                             }
                         }
 
@@ -425,6 +451,7 @@ impl<'a> System<'a> for ChunkLoading {
                     let column = match self.chunk_column_pool.write().pop() {
                         Some(column) => {
                             for chunk in column.chunks.iter() {
+                                chunk.unload_from_gpu(&mut chunk_manager.geometry_arena.write());
                                 chunk.reset();
                             }
                             column.heighest_blocks.write().fill(0);
@@ -453,7 +480,7 @@ impl<'a> System<'a> for ChunkLoading {
                     if let Some(prioritized_chunk) = self.chunk_upload_priority_queue.pop() {
                         let (c_x, c_y, c_z) = *prioritized_chunk;
                         if let Some(chunk) = chunk_manager.get_chunk(c_x, c_y, c_z) {
-                            chunk.upload_to_gpu(&texture_pack);
+                            chunk.upload_to_gpu(&texture_pack, &mut chunk_manager.geometry_arena.write());
                             *chunk.is_uploaded_to_gpu.write() = true;
                         }
                     }
@@ -470,7 +497,9 @@ impl<'a> System<'a> for ChunkLoading {
                 let request_chunk_columns_tx = self.request_chunk_columns_tx.clone();
                 let requested_chunk_column_rx = self.requested_chunk_column_rx.clone();
 
+                let job = WorldGenerationJob::new(&self.world_generation_jobs);
                 self.world_generation_thread_pool.spawn(move || {
+                    let _job = job;
                     let new_columns = Self::flood_fill_unloaded_columns(&chunk_manager, c_x, c_z, RENDER_DISTANCE + 2);
                     for _ in 0..new_columns.len() {
                         request_chunk_columns_tx.send(()).unwrap();
@@ -527,14 +556,24 @@ impl<'a> System<'a> for ChunkLoading {
                                             }
                                         }
 
-                                        // Grass and dirt
+                                        // Grass and dirt, sand on the beaches and gravel at the bottom of the sea
                                         for b_x in 0..16 {
                                             for b_z in 0..16 {
                                                 let y = column.heighest_blocks.read()[16 * b_z + b_x] as i32;
 
                                                 let chunk_y = (y / 16) as i32;
                                                 let block_y = (y % 16) as usize;
-                                                column.get_chunk(chunk_y).set_block(BlockID::GrassBlock, b_x as u32, block_y as u32, b_z as u32);
+                                                let (top_block, filler_block) = if y < SEA_LEVEL - SEA_FLOOR_DEPTH {
+                                                    (BlockID::Gravel, BlockID::Gravel)
+                                                } else if y <= SEA_LEVEL + 1 {
+                                                    (BlockID::Sand, BlockID::Sand)
+                                                } else {
+                                                    (BlockID::GrassBlock, BlockID::Dirt)
+                                                };
+                                                column.get_chunk(chunk_y).set_block(top_block, b_x as u32, block_y as u32, b_z as u32);
+                                                for y in y + 1..=SEA_LEVEL {
+                                                    column.set_block(BlockID::Water, b_x as u32, y as u32, b_z as u32);
+                                                }
 
                                                 for y in (y - 3)..y {
                                                     let chunk_y = (y / 16) as i32;
@@ -544,7 +583,7 @@ impl<'a> System<'a> for ChunkLoading {
                                                     if chunk.get_block(b_x as u32, block_y as u32, b_z as u32).is_air() {
                                                         continue;
                                                     }
-                                                    chunk.set_block(BlockID::Dirt, b_x as u32, block_y as u32, b_z as u32);
+                                                    chunk.set_block(filler_block, b_x as u32, block_y as u32, b_z as u32);
                                                 }
                                             }
                                         }
@@ -579,38 +618,15 @@ impl<'a> System<'a> for ChunkLoading {
                                         let (x, z) = (x as usize, z as usize);
                                         let y = column.heighest_blocks.read()[16 * z + x] as i32;
 
-                                        if true {
+                                        let top_block = column.get_chunk(y / 16).get_block(x as u32, (y % 16) as u32, z as u32);
+                                        if top_block == BlockID::GrassBlock {
                                             let x = cx * 16 + x as i32;
                                             let z = cz * 16 + z as i32;
-
-                                            let h = 5;
-                                            for i in y + 1..y + 1 + h {
-                                                chunk_manager.set_block(BlockID::OakLog, x, i, z);
-                                            }
-
-                                            for yy in y + h - 2..=y + h - 1 {
-                                                for xx in x - 2..=x + 2 {
-                                                    for zz in z - 2..=z + 2 {
-                                                        if xx != x || zz != z {
-                                                            chunk_manager.set_block(BlockID::OakLeaves, xx, yy, zz);
-                                                        }
-                                                    }
-                                                }
-                                            }
-
-                                            for xx in x - 1..=x + 1 {
-                                                for zz in z - 1..=z + 1 {
-                                                    if xx != x || zz != z {
-                                                        chunk_manager.set_block(BlockID::OakLeaves, xx, y + h, zz);
-                                                    }
-                                                }
+                                            // Trees are part of the heightmap, so snow and rain stop on their leaves
+                                            for (block, (x, y, z)) in oak_tree(x, y, z) {
+                                                chunk_manager.set_block(block, x, y, z);
+                                                chunk_manager.update_heighest_block(block, x, y, z);
                                             }
-
-                                            chunk_manager.set_block(BlockID::OakLeaves, x, y + h + 1, z);
-                                            chunk_manager.set_block(BlockID::OakLeaves, x + 1, y + h + 1, z);
-                                            chunk_manager.set_block(BlockID::OakLeaves, x - 1, y + h + 1, z);
-                                            chunk_manager.set_block(BlockID::OakLeaves, x, y + h + 1, z + 1);
-                                            chunk_manager.set_block(BlockID::OakLeaves, x, y + h + 1, z - 1);
                                         }
                                     }
                                 }
@@ -673,13 +689,14 @@ impl<'a> System<'a> for ChunkLoading {
             let send_chunks = self.upload_chunks_tx.clone();
             let chunk_manager = Arc::clone(&chunk_manager);
             let highest_priority = dirty_blocks.iter().map(|i| i.0).max().unwrap_or(0);
-            let thread_pool = if highest_priority == 0 {
-                &self.world_generation_thread_pool
+            let (thread_pool, job) = if highest_priority == 0 {
+                (&self.world_generation_thread_pool, Some(WorldGenerationJob::new(&self.world_generation_jobs)))
             } else {
-                &self.player_interaction_thread_pool
+                (&self.player_interaction_thread_pool, None)
             };
 
             thread_pool.spawn(move || {
+                let _job = job;
                 let bxyz = dirty_blocks.iter().map(|i| (i.1, i.2, i.3));
 
                 match chunk_manager.get_chunk(c_x, c_y, c_z) {
@@ -697,5 +714,10 @@ impl<'a> System<'a> for ChunkLoading {
                 }
             });
         }
+
+        debug_info.pending_column_requests = self.request_chunk_columns_rx.len();
+        debug_info.pending_chunk_uploads = self.upload_chunks_rx.len() + self.chunk_upload_priority_queue.len();
+        debug_info.world_generation_jobs = self.world_generation_jobs.load(AtomicOrdering::SeqCst);
+        debug_info.world_generation_threads = self.world_generation_thread_pool.current_num_threads();
     }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use specs::{Join, Read, ReadStorage, System, Write};
+
+use crate::chunk_manager::{ChunkCullingStats, ChunkManager};
+use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WORLD_SEED};
+use crate::debug_overlay::{DebugInfo, face_name, facing};
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
+use crate::text::{TextRenderer, WHITE};
+use crate::types::Shaders;
+use crate::util::Forward;
+use crate::weather::Weather;
+use crate::world_time::WorldTime;
+
+pub struct RenderDebugOverlay {
+    text_renderer: TextRenderer,
+}
+
+impl RenderDebugOverlay {
+    pub fn new() -> Self {
+        Self {
+            text_renderer: TextRenderer::new(),
+        }
+    }
+
+    fn lines(
+        debug_info: &DebugInfo,
+        culling_stats: &ChunkCullingStats,
+        chunk_manager: &ChunkManager,
+        player_state: &PlayerState,
+        player_physics_state: &EntityPhysicsState,
+        world_time: &WorldTime,
+        weather: &Weather,
+    ) -> Vec<String> {
+        let position = player_physics_state.position;
+        let (x, y, z) = (position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+        let (c_x, c_y, c_z, b_x, b_y, b_z) = ChunkManager::get_chunk_coords(x, y, z);
+        let forward = player_state.rotation.forward();
+
+        let (loaded_columns, generated_chunks) = {
+            let columns = chunk_manager.loaded_chunk_columns.read();
+            let generated_chunks = columns.values()
+                .flat_map(|column| column.chunks.iter())
+                .filter(|chunk| *chunk.is_generated.read())
+                .count();
+            (columns.len(), generated_chunks)
+        };
+
+        let mut lines = vec![
+            format!("Meinkraft ({} fps, {:.2} ms)", debug_info.fps, debug_info.frame_time * 1000.0),
+            format!("XYZ: {:.3} / {:.3} / {:.3}", position.x, position.y, position.z),
+            format!("Block: {} {} {}", x, y, z),
+            format!("Chunk: {} {} {} in {} {} {}", b_x, b_y, b_z, c_x, c_y, c_z),
+            format!("Facing: {} ({:.1} / {:.1})", facing(&forward),
+                    player_state.rotation.y.to_degrees(), player_state.rotation.x.to_degrees()),
+        ];
+        match player_state.targeted_block {
+            Some(((x, y, z), normal)) => {
+                let block = chunk_manager.get_block(x, y, z)
+                    .map_or("unloaded".to_string(), |block| format!("{:?}", block));
+                lines.push(format!("Targeted block: {} {} {}, {}, face: {}", x, y, z, block, face_name(&normal)));
+            }
+            None => lines.push("Targeted block: none".to_string()),
+        }
+        lines.extend(vec![
+            format!("Seed: {}", *WORLD_SEED),
+            format!("Day {}, time: {}", world_time.day(), world_time.time_of_day()),
+            format!("Weather: {:?} ({:.0}%, {}s left), temperature: {:.2}",
+                    weather.kind(), weather.precipitation_strength() * 100.0,
+                    weather.remaining_duration().as_secs(), weather.temperature(x, y, z)),
+            format!("Loaded columns: {}, generated chunks: {}", loaded_columns, generated_chunks),
+            format!("Chunks drawn: {}/{} ({} culled, {} occluded), vertices: {}",
+                    culling_stats.drawn, culling_stats.considered, culling_stats.culled,
+                    culling_stats.occluded, culling_stats.vertices),
+            format!("Pending column requests: {}, chunk uploads: {}",
+                    debug_info.pending_column_requests, debug_info.pending_chunk_uploads),
+            format!("World generation: {:.0}% of {} threads, {} jobs",
+                    debug_info.world_generation_utilization() * 100.0,
+                    debug_info.world_generation_threads, debug_info.world_generation_jobs),
+        ]);
+        lines
+    }
+}
+
+impl<'a> System<'a> for RenderDebugOverlay {
+    type SystemData = (
+        Read<'a, DebugInfo>,
+        Read<'a, ChunkCullingStats>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, WorldTime>,
+        Read<'a, Weather>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            debug_info,
+            culling_stats,
+            chunk_manager,
+            world_time,
+            weather,
+            mut shaders,
+            player_state,
+            player_physics_state,
+        ) = data;
+
+        if !debug_info.is_visible {
+            return;
+        }
+
+        for (player_state, player_physics_state) in (&player_state, &player_physics_state).join() {
+            let lines = Self::lines(&debug_info, &culling_stats, &chunk_manager,
+                                    player_state, player_physics_state.get_interpolated_state(), &world_time, &weather);
+
+            gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+            gl_call!(gl::Disable(gl::DEPTH_TEST));
+            self.text_renderer.begin();
+            let margin = 2.0 * GUI_SCALING;
+            self.text_renderer.draw_text_with_shadow((margin, WINDOW_HEIGHT as f32 - margin), GUI_SCALING, WHITE, &lines.join("\n"));
+            self.text_renderer.end(shaders.get_mut("renderer2d_shader").unwrap());
+            gl_call!(gl::Enable(gl::DEPTH_TEST));
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use nalgebra_glm::{distance, vec3};
+use specs::{Entities, Join, Read, ReadStorage, System, Write, WriteStorage};
+
+use crate::constants::{DROPPED_ITEM_LIFETIME, DROPPED_ITEM_MERGE_RADIUS, DROPPED_ITEM_PICKUP_RANGE};
+use crate::dropped_item::{DroppedItem, ItemModel};
+use crate::ecs::components::MainHandItemChanged;
+use crate::inventory::Inventory;
+use crate::inventory::item::Item;
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
+use crate::timer::Timer;
+use crate::types::{ItemIcons, Shaders, TexturePack};
+
+pub struct UpdateDroppedItems;
+
+impl UpdateDroppedItems {
+    /// Merges the stacks of the same item which are close to each other, the emptied entities are deleted
+    fn merge_nearby_items(entities: &Entities, dropped_items: &mut WriteStorage<DroppedItem>, physics_states: &ReadStorage<Interpolator<EntityPhysicsState>>) {
+        let items = (entities, &*dropped_items, physics_states).join()
+            .map(|(e, _, physics_state)| (e, physics_state.get_latest_state().position))
+            .collect::<Vec<_>>();
+
+        for (i, &(e1, position1)) in items.iter().enumerate() {
+            for &(e2, position2) in &items[i + 1..] {
+                if distance(&position1, &position2) > DROPPED_ITEM_MERGE_RADIUS {
+                    continue;
+                }
+                let (mut stack1, mut stack2) = match (dropped_items.get(e1), dropped_items.get(e2)) {
+                    (Some(item1), Some(item2)) => (item1.stack, item2.stack),
+                    _ => continue,
+                };
+                if !stack1.is_same_item(&stack2) || stack1.amount == 0 || stack2.amount == 0 {
+                    continue;
+                }
+
+                let amount = stack2.amount;
+                if stack1.merge_from(&mut stack2, amount) == 0 {
+                    continue;
+                }
+                dropped_items.get_mut(e1).unwrap().stack = stack1;
+                dropped_items.get_mut(e2).unwrap().stack = stack2;
+                if stack2.amount == 0 {
+                    if let Err(e) = entities.delete(e2) {
+                        error!("{}", e);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<'a> System<'a> for UpdateDroppedItems {
+    type SystemData = (
+        Read<'a, Timer>,
+        Entities<'a>,
+        WriteStorage<'a, DroppedItem>,
+        // Of the items and of the players
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            entities,
+            mut dropped_items,
+            physics_states,
+            mut inventory,
+            mut main_hand_item_changed,
+        ) = data;
+
+        let now = global_timer.time();
+
+        for (e, dropped_item, physics_state) in (&entities, &dropped_items, &physics_states).join() {
+            let position = physics_state.get_latest_state().position;
+            if now.saturating_duration_since(dropped_item.spawned_at) >= *DROPPED_ITEM_LIFETIME || position.y < 0.0 {
+                if let Err(e) = entities.delete(e) {
+                    error!("{}", e);
+                }
+            }
+        }
+
+        Self::merge_nearby_items(&entities, &mut dropped_items, &physics_states);
+
+        // Pick up the items close to the players
+        for (player_entity, player_physics_state, inventory) in (&entities, &physics_states, &mut inventory).join() {
+            let mut pickup_aabb = player_physics_state.get_latest_state().aabb;
+            pickup_aabb.mins -= vec3(DROPPED_ITEM_PICKUP_RANGE, DROPPED_ITEM_PICKUP_RANGE / 2.0, DROPPED_ITEM_PICKUP_RANGE);
+            pickup_aabb.maxs += vec3(DROPPED_ITEM_PICKUP_RANGE, DROPPED_ITEM_PICKUP_RANGE / 2.0, DROPPED_ITEM_PICKUP_RANGE);
+
+            let mut picked_up_something = false;
+            for (e, dropped_item, physics_state) in (&entities, &mut dropped_items, &physics_states).join() {
+                if dropped_item.stack.amount == 0
+                    || !dropped_item.can_be_picked_up(now)
+                    || !pickup_aabb.contains_point(&physics_state.get_latest_state().position) {
+                    continue;
+                }
+                match inventory.try_insert(dropped_item.stack) {
+                    Ok(()) => {
+                        dropped_item.stack.amount = 0;
+                        if let Err(e) = entities.delete(e) {
+                            error!("{}", e);
+                        }
+                        picked_up_something = true;
+                    }
+                    Err(overflow) => {
+                        picked_up_something |= overflow.amount != dropped_item.stack.amount;
+                        dropped_item.stack = overflow;
+                    }
+                }
+            }
+
+            // The selected hotbar slot may have been filled
+            if picked_up_something {
+                if let Err(e) = main_hand_item_changed.insert(player_entity, MainHandItemChanged) {
+                    error!("{}", e);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderDroppedItems {
+    models: HashMap<Item, ItemModel>,
+}
+
+impl RenderDroppedItems {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for RenderDroppedItems {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, TexturePack>,
+        Read<'a, ItemIcons>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, DroppedItem>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            texture_pack,
+            item_icons,
+            mut shaders,
+            player_state,
+            dropped_items,
+            physics_states,
+        ) = data;
+
+        let now = global_timer.time();
+        let hand_shader = shaders.get_mut("hand_shader").unwrap();
+        hand_shader.use_program();
+        hand_shader.set_uniform1i("tex", 0);
+
+        for player_state in (&player_state).join() {
+            hand_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
+            hand_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
+
+            for (dropped_item, physics_state) in (&dropped_items, &physics_states).join() {
+                let age = now.saturating_duration_since(dropped_item.spawned_at).as_secs_f32();
+                let model = self.models.entry(dropped_item.stack.item)
+                    .or_insert_with(|| ItemModel::new(dropped_item.stack.item, &texture_pack, &item_icons));
+                model.draw(&physics_state.get_interpolated_state().position, age, hand_shader);
+            }
+        }
+    }
+}
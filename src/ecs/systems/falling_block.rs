@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write};
+
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::dropped_item::{ItemModel, spawn_block_drop};
+use crate::falling_block::{FallingBlock, is_replaced_by_falling_blocks};
+use crate::inventory::item::{Item, ItemStack};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::PlayerState;
+use crate::types::{ItemIcons, Shaders, TexturePack};
+
+/// Puts the falling blocks back in the world where they land, the `EntityPhysics` system moves them
+pub struct UpdateFallingBlocks;
+
+impl<'a> System<'a> for UpdateFallingBlocks {
+    type SystemData = (
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+        ReadStorage<'a, FallingBlock>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+        ReadStorage<'a, PhysicsBody>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            chunk_manager,
+            lazy_update,
+            entities,
+            falling_blocks,
+            physics_states,
+            bodies,
+        ) = data;
+
+        for (e, falling_block, physics_state, body) in (&entities, &falling_blocks, &physics_states, &bodies).join() {
+            let position = physics_state.get_latest_state().position;
+            if position.y < 0.0 {
+                if let Err(e) = entities.delete(e) {
+                    error!("{}", e);
+                }
+                continue;
+            }
+            if !body.is_on_ground {
+                continue;
+            }
+            // Blocks landing where something else was put in the meantime are dropped as items
+            let (x, y, z) = (position.x.floor() as i32, position.y.round() as i32, position.z.floor() as i32);
+            match chunk_manager.get_block(x, y, z) {
+                Some(block) if is_replaced_by_falling_blocks(block) => {
+                    chunk_manager.put_block(falling_block.block, x, y, z);
+                }
+                _ => spawn_block_drop(&lazy_update, &entities, ItemStack::new(1, Item::Block(falling_block.block)), (x, y, z)),
+            }
+            if let Err(e) = entities.delete(e) {
+                error!("{}", e);
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderFallingBlocks {
+    models: HashMap<BlockID, ItemModel>,
+}
+
+impl RenderFallingBlocks {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for RenderFallingBlocks {
+    type SystemData = (
+        Read<'a, TexturePack>,
+        Read<'a, ItemIcons>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, FallingBlock>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            texture_pack,
+            item_icons,
+            mut shaders,
+            player_state,
+            falling_blocks,
+            physics_states,
+        ) = data;
+
+        let hand_shader = shaders.get_mut("hand_shader").unwrap();
+        hand_shader.use_program();
+        hand_shader.set_uniform1i("tex", 0);
+
+        for player_state in (&player_state).join() {
+            hand_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
+            hand_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
+
+            for (falling_block, physics_state) in (&falling_blocks, &physics_states).join() {
+                let model = self.models.entry(falling_block.block)
+                    .or_insert_with(|| ItemModel::new(Item::Block(falling_block.block), &texture_pack, &item_icons));
+                model.draw_full_block(&physics_state.get_interpolated_state().position, hand_shader);
+            }
+        }
+    }
+}
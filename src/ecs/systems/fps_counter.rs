@@ -1,5 +1,7 @@
 use std::time::Instant;
-use specs::System;
+use specs::{System, Write};
+
+use crate::debug_overlay::DebugInfo;
 
 const MAXIMUM_OPTIMAL_FRAMETIME: f32 = 1.0 / 60.0;
 
@@ -10,14 +12,17 @@ pub struct FpsCounter {
 }
 
 impl<'a> System<'a> for FpsCounter {
-    type SystemData = ();
+    type SystemData = (
+        Write<'a, DebugInfo>,
+    );
 
-    fn run(&mut self, _data: Self::SystemData) {
+    fn run(&mut self, (mut debug_info, ): Self::SystemData) {
         let current_time = Instant::now();
         self.nb_frames += 1;
 
         {
             let frame_time = current_time.duration_since(self.last_frame).as_secs_f32();
+            debug_info.frame_time = frame_time;
             if frame_time > MAXIMUM_OPTIMAL_FRAMETIME {
                 warn!("Suboptimal frame time: {:.2} ms", frame_time * 1000.0);
             }
@@ -26,6 +31,7 @@ impl<'a> System<'a> for FpsCounter {
 
         if current_time.duration_since(self.last_second).as_secs_f32() >= 1.0 {
             info!("{} fps", self.nb_frames);
+            debug_info.fps = self.nb_frames as u32;
             self.nb_frames = 0;
             self.last_second = current_time;
         }
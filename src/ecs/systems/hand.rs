@@ -8,10 +8,10 @@ use crate::constants::{FAR_PLANE, NEAR_PLANE, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::ecs::components::MainHandItemChanged;
 use crate::inventory::Inventory;
 use crate::main_hand::MainHand;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
 use crate::timer::Timer;
-use crate::types::{Shaders, TexturePack};
+use crate::types::{ItemIcons, Shaders, TexturePack};
 use crate::util::Forward;
 
 pub struct UpdateMainHand;
@@ -56,8 +56,9 @@ impl<'a> System<'a> for RenderMainHand {
     type SystemData = (
         WriteStorage<'a, MainHand>,
         ReadStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
         Read<'a, TexturePack>,
+        Read<'a, ItemIcons>,
         Read<'a, Timer>,
         Write<'a, Shaders>,
     );
@@ -68,6 +69,7 @@ impl<'a> System<'a> for RenderMainHand {
             player_state,
             player_physics_state,
             texture_pack,
+            item_icons,
             global_timer,
             mut shaders,
         ) = data;
@@ -105,7 +107,7 @@ impl<'a> System<'a> for RenderMainHand {
                 nalgebra_glm::look_at(&camera_position, &(camera_position + looking_dir), &Vector3::y())
             };
 
-            main_hand.update_if_dirty(&texture_pack);
+            main_hand.update_if_dirty(&texture_pack, &item_icons);
 
             let player_pos = player_physics_state.get_interpolated_state().position;
             let camera_height = *player_state.camera_height.get_interpolated_state();
@@ -144,7 +146,7 @@ impl<'a> System<'a> for RenderMainHand {
             gl_call!(gl::BindVertexArray(main_hand.render.vao));
 
             gl_call!(gl::Disable(gl::DEPTH_TEST));
-            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 36 as i32));
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, main_hand.render.vertices));
             gl_call!(gl::Enable(gl::DEPTH_TEST));
         }
     }
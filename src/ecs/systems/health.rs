@@ -0,0 +1,118 @@
+use std::sync::Arc;
+
+use glfw::{Action, MouseButton, WindowEvent};
+use nalgebra_glm::vec3;
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write, WriteStorage};
+
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{PHYSICS_TICKRATE, WORLD_SPAWN};
+use crate::dropped_item::scatter_stacks;
+use crate::ecs::components::MainHandItemChanged;
+use crate::gui::{GuiState, is_over_respawn_button, Screen};
+use crate::health::{Health, spawn_height};
+use crate::input::InputCache;
+use crate::inventory::Inventory;
+use crate::inventory::screen::cursor_to_gui_coords;
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::{GameMode, PlayerState};
+use crate::timer::Timer;
+
+/// Spawning, death and respawning, regeneration, starvation and eating
+pub struct UpdatePlayerHealth;
+
+impl<'a> System<'a> for UpdatePlayerHealth {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, InputCache>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, LazyUpdate>,
+        Write<'a, GuiState>,
+        Entities<'a>,
+        ReadStorage<'a, PlayerState>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            input_cache,
+            chunk_manager,
+            lazy_update,
+            mut gui_state,
+            entities,
+            player_state,
+            mut player_physics_state,
+            mut health,
+            mut inventory,
+            mut main_hand_item_changed,
+        ) = data;
+
+        for (e, player_state, player_physics_state, health, inventory) in (&entities, &player_state, &mut player_physics_state, &mut health, &mut inventory).join() {
+            let mut f = || {
+                if let Err(e) = main_hand_item_changed.insert(e, MainHandItemChanged) {
+                    error!("{}", e);
+                }
+            };
+
+            // Put the player on the ground once the spawn point is loaded
+            if health.is_waiting_for_spawn {
+                let (x, z) = WORLD_SPAWN;
+                if let Some(y) = spawn_height(&chunk_manager, x, z) {
+                    *player_physics_state = Interpolator::new(
+                        1.0 / PHYSICS_TICKRATE,
+                        player_physics_state.get_latest_state().teleported_to(vec3(x as f32 + 0.5, y, z as f32 + 0.5)),
+                    );
+                    health.is_waiting_for_spawn = false;
+                    info!("Spawned at ({} {} {})", x, y, z);
+                }
+                continue;
+            }
+
+            if health.is_dead() {
+                if gui_state.screen != Some(Screen::Death) {
+                    let position = player_physics_state.get_latest_state().position;
+                    scatter_stacks(&lazy_update, &entities, inventory.take_all_stacks(), position);
+                    f();
+                    gui_state.open_screen(Screen::Death);
+                    info!("Died at ({} {} {})", position.x, position.y, position.z);
+                }
+
+                let (x, y) = cursor_to_gui_coords(input_cache.last_cursor_pos.x, input_cache.last_cursor_pos.y);
+                for event in &input_cache.events {
+                    if let WindowEvent::MouseButton(MouseButton::Button1, Action::Press, _) = event {
+                        if is_over_respawn_button(x, y) {
+                            health.respawn();
+                            gui_state.close_screen();
+                        }
+                    }
+                }
+                continue;
+            }
+
+            if player_state.game_mode == GameMode::Creative {
+                continue;
+            }
+            health.tick(global_timer.time());
+
+            // Eat the selected item by right clicking
+            if gui_state.is_screen_open() {
+                continue;
+            }
+            for event in &input_cache.events {
+                if let WindowEvent::MouseButton(MouseButton::Button2, Action::Press, _) = event {
+                    let food_value = inventory.get_selected_item().and_then(|item| item.food_value());
+                    if let Some(food_value) = food_value {
+                        if health.can_eat() {
+                            inventory.remove_from_slot(inventory.selected_hotbar_slot, 1);
+                            health.eat(food_value);
+                            f();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,9 +1,12 @@
 use std::process::exit;
 use std::sync::mpsc::Receiver;
 
-use glfw::{Action, Context, Glfw, Key, Window, WindowEvent};
+use glfw::{Action, Context, CursorMode, Glfw, Key, Window, WindowEvent};
 use specs::{System, Write};
 
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::debug_overlay::DebugInfo;
+use crate::gui::{GuiState, Screen};
 use crate::input::InputCache;
 use crate::timer::Timer;
 
@@ -17,12 +20,16 @@ impl<'a> System<'a> for ReadWindowEvents {
     type SystemData = (
         Write<'a, InputCache>,
         Write<'a, Timer>,
+        Write<'a, GuiState>,
+        Write<'a, DebugInfo>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             mut input_cache,
             mut global_timer,
+            mut gui_state,
+            mut debug_info,
         ) = data;
 
         if self.window.should_close() {
@@ -38,10 +45,16 @@ impl<'a> System<'a> for ReadWindowEvents {
 
             match event {
                 WindowEvent::Key(Key::Escape, _, Action::Press, _) => {
-                    self.window.set_should_close(true);
+                    if gui_state.is_screen_open() {
+                        gui_state.try_close_screen();
+                    } else {
+                        self.window.set_should_close(true);
+                    }
                 }
 
-                WindowEvent::Key(Key::P, _, Action::Press, _) => {
+                WindowEvent::Key(Key::F3, _, Action::Press, _) => debug_info.toggle(),
+
+                WindowEvent::Key(Key::P, _, Action::Press, _) if gui_state.screen != Some(Screen::Chat) => {
                     if global_timer.is_paused() {
                         global_timer.resume()
                     } else {
@@ -51,5 +64,22 @@ impl<'a> System<'a> for ReadWindowEvents {
                 _ => {}
             }
         }
+
+        // Release the cursor while a screen is open
+        let cursor_mode = if gui_state.is_screen_open() {
+            CursorMode::Normal
+        } else {
+            CursorMode::Disabled
+        };
+        if self.window.get_cursor_mode() != cursor_mode {
+            self.window.set_cursor_mode(cursor_mode);
+            if cursor_mode == CursorMode::Normal {
+                self.window.set_cursor_pos(WINDOW_WIDTH as f64 / 2.0, WINDOW_HEIGHT as f64 / 2.0);
+            }
+            // Don't turn the camera because the cursor jumped
+            let (x, y) = self.window.get_cursor_pos();
+            input_cache.last_cursor_pos.x = x;
+            input_cache.last_cursor_pos.y = y;
+        }
     }
 }
\ No newline at end of file
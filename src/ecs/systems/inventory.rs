@@ -1,12 +1,18 @@
-use glfw::{MouseButton, WindowEvent};
-use specs::{Entities, Join, Read, ReadStorage, System, WriteStorage};
+use glfw::{Modifiers, MouseButton, WindowEvent};
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write, WriteStorage};
 
 use crate::chunk_manager::ChunkManager;
+use crate::crafting::RecipeRegistry;
+use crate::dropped_item::throw_stack;
 use crate::ecs::components::MainHandItemChanged;
+use crate::gui::{GuiState, Screen};
 use crate::input::InputCache;
-use crate::inventory::Inventory;
-use crate::inventory::item::ItemStack;
-use crate::player::PlayerState;
+use crate::chunk::BlockID;
+use crate::inventory::{HOTBAR_SIZE, INVENTORY_CRAFTING_GRID_SIZE, Inventory};
+use crate::inventory::item::{Item, ItemStack};
+use crate::inventory::screen::{cursor_to_gui_coords, is_inside_screen, slot_at};
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::{GameMode, PlayerState};
 use std::sync::Arc;
 
 pub struct InventoryHandleInput;
@@ -18,6 +24,60 @@ impl InventoryHandleInput {
             f();
         }
     }
+
+    /// Selects the hotbar slot holding the block, brings it from the main inventory,
+    /// or puts a full stack of it in the selected slot (only in creative)
+    /// Returns the items which didn't fit in the inventory anymore
+    fn pick_block(inventory: &mut Inventory, block: BlockID, creative: bool) -> Option<ItemStack> {
+        let block = Item::Block(block.item_block());
+        let slot_with_block = inventory.slots.iter()
+            .position(|slot| slot.map_or(false, |stack| stack.item == block));
+        match slot_with_block {
+            Some(index) if index < HOTBAR_SIZE => inventory.select_item(index),
+            Some(index) => inventory.slots.swap(index, inventory.selected_hotbar_slot),
+            None if !creative => {}
+            None => {
+                let stack = ItemStack::new(block.max_stack_size(), block);
+                let selected_slot = &mut inventory.slots[inventory.selected_hotbar_slot];
+                // Keep the stack which was there if there is room for it
+                let previous_stack = selected_slot.replace(stack)?;
+                return inventory.try_insert(previous_stack).err();
+            }
+        }
+        None
+    }
+
+    /// Clicks on the slots of the inventory screen
+    /// The stack held by the cursor is thrown when clicking outside of the screen
+    fn handle_screen_input(inventory: &mut Inventory, input_cache: &InputCache, recipes: &RecipeRegistry, f: &mut dyn FnMut(), throw: &mut dyn FnMut(ItemStack)) {
+        let (x, y) = cursor_to_gui_coords(input_cache.last_cursor_pos.x, input_cache.last_cursor_pos.y);
+        for event in &input_cache.events {
+            match event {
+                WindowEvent::MouseButton(button, glfw::Action::Press, modifiers) => {
+                    match slot_at(x, y, inventory.crafting_grid.size) {
+                        Some(slot) => {
+                            match button {
+                                MouseButton::Button1 if modifiers.contains(Modifiers::Shift) => inventory.shift_click_slot(slot, recipes),
+                                MouseButton::Button1 => inventory.left_click_slot(slot, recipes),
+                                MouseButton::Button2 => inventory.right_click_slot(slot, recipes),
+                                _ => continue,
+                            }
+                            // The selected hotbar slot may have changed
+                            f();
+                        }
+                        None => {
+                            if !is_inside_screen(x, y) {
+                                if let Some(stack) = inventory.cursor_stack.take() {
+                                    throw(stack);
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
 }
 
 impl<'a> System<'a> for InventoryHandleInput {
@@ -25,7 +85,11 @@ impl<'a> System<'a> for InventoryHandleInput {
         Entities<'a>,
         Read<'a, InputCache>,
         Read<'a, Arc<ChunkManager>>,
+        Read<'a, LazyUpdate>,
+        Read<'a, RecipeRegistry>,
+        Write<'a, GuiState>,
         ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
         WriteStorage<'a, Inventory>,
         WriteStorage<'a, MainHandItemChanged>,
     );
@@ -35,21 +99,36 @@ impl<'a> System<'a> for InventoryHandleInput {
             entities,
             input_cache,
             chunk_manager,
+            lazy_update,
+            recipes,
+            mut gui_state,
             player_state,
+            player_physics_state,
             mut inventory,
             mut main_hand_item_changed,
         ) = data;
 
-        for (e, inventory, player_state) in (&entities, &mut inventory, &player_state).join() {
+        for (e, inventory, player_state, player_physics_state) in (&entities, &mut inventory, &player_state, &player_physics_state).join() {
             let mut f = || {
                 if let Err(e) = main_hand_item_changed.insert(e, MainHandItemChanged) {
                     error!("{}", e);
                 }
             };
+            let mut throw = |stack: ItemStack| {
+                throw_stack(&lazy_update, &entities, stack, player_state, player_physics_state.get_latest_state());
+            };
 
             for event in &input_cache.events {
                 use glfw::{Key, Action};
                 match event {
+                    // E is typed in the chat
+                    WindowEvent::Key(Key::E, _, Action::Press, _) if gui_state.screen != Some(Screen::Chat) => {
+                        match gui_state.screen {
+                            Some(_) => gui_state.try_close_screen(),
+                            None => gui_state.open_screen(Screen::Inventory),
+                        }
+                    }
+                    _ if gui_state.is_screen_open() => {}
                     WindowEvent::Scroll(_, y) => {
                         if y.is_sign_positive() {
                             inventory.select_previous_item();
@@ -61,11 +140,25 @@ impl<'a> System<'a> for InventoryHandleInput {
                     WindowEvent::MouseButton(MouseButton::Button3, Action::Press, _) => {
                         if let Some(((x, y, z), _)) = player_state.targeted_block {
                             if let Some(block) = chunk_manager.get_block(x, y, z) {
-                                inventory.slots[inventory.selected_hotbar_slot] = Some(ItemStack::new(1, block));
+                                if let Some(overflow) = Self::pick_block(inventory, block, player_state.game_mode == GameMode::Creative) {
+                                    throw(overflow);
+                                }
                                 f();
                             }
                         }
                     }
+                    // Throw one item of the selected stack, or all of it while holding control
+                    WindowEvent::Key(Key::Q, _, Action::Press, modifiers) => {
+                        let amount = if modifiers.contains(Modifiers::Control) {
+                            u32::MAX
+                        } else {
+                            1
+                        };
+                        if let Some(stack) = inventory.remove_from_slot(inventory.selected_hotbar_slot, amount) {
+                            throw(stack);
+                            f();
+                        }
+                    }
                     WindowEvent::Key(Key::Num1, _, Action::Press, _) => Self::select_item(inventory, 0, &mut f),
                     WindowEvent::Key(Key::Num2, _, Action::Press, _) => Self::select_item(inventory, 1, &mut f),
                     WindowEvent::Key(Key::Num3, _, Action::Press, _) => Self::select_item(inventory, 2, &mut f),
@@ -78,6 +171,29 @@ impl<'a> System<'a> for InventoryHandleInput {
                     _ => {}
                 }
             }
+
+            // The crafting grid is emptied when the screen is closed or when its size changes
+            let crafting_grid_size = match gui_state.screen {
+                Some(Screen::CraftingTable) => 3,
+                _ => INVENTORY_CRAFTING_GRID_SIZE,
+            };
+            if inventory.crafting_grid.size != crafting_grid_size
+                || (!gui_state.is_inventory_open() && !inventory.crafting_grid.is_empty()) {
+                for stack in inventory.return_crafting_grid(crafting_grid_size) {
+                    throw(stack);
+                }
+                f();
+            }
+
+            if gui_state.is_inventory_open() {
+                Self::handle_screen_input(inventory, &input_cache, &recipes, &mut f, &mut throw);
+            } else if inventory.cursor_stack.is_some() {
+                // The screen was closed (with E or escape) while holding a stack
+                if let Some(stack) = inventory.return_cursor_stack() {
+                    throw(stack);
+                }
+                f();
+            }
         }
     }
 }
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::Arc;
+use std::time::Instant;
+
+use nalgebra_glm::{distance, vec3};
+use num_traits::Zero;
+use rand::{Rng, thread_rng};
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write, WriteStorage};
+
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{HORIZONTAL_ACCELERATION, JUMP_IMPULSE, MAX_MOBS, MOB_DESPAWN_DISTANCE, MOB_SPAWN_INTERVAL, MOB_SPAWN_MAX_DISTANCE, MOB_SPAWN_MIN_DISTANCE, SWIMMING_UP_SPEED};
+use crate::health::{fall_damage, Health};
+use crate::mob::{can_spawn_at, Mob, MobAI, MobKind, MobModel, spawn_mob};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::PlayerState;
+use crate::timer::Timer;
+use crate::types::{MobTextures, Shaders};
+
+/// Spawns mobs on the grass around the player, and removes the ones left far behind
+pub struct SpawnMobs {
+    last_attempt: Instant,
+}
+
+impl SpawnMobs {
+    pub fn new() -> Self {
+        Self {
+            last_attempt: Instant::now(),
+        }
+    }
+}
+
+impl<'a> System<'a> for SpawnMobs {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Arc<ChunkManager>>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Mob>,
+        // Of the players and of the mobs
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            chunk_manager,
+            lazy_update,
+            entities,
+            player_state,
+            health,
+            mobs,
+            physics_states,
+        ) = data;
+
+        let now = global_timer.time();
+        if now.saturating_duration_since(self.last_attempt) < *MOB_SPAWN_INTERVAL {
+            return;
+        }
+        self.last_attempt = now;
+
+        for (_, player_physics_state, health) in (&player_state, &physics_states, &health).join() {
+            if health.is_waiting_for_spawn {
+                continue;
+            }
+            let player = player_physics_state.get_latest_state().position;
+
+            let mut mob_count = 0;
+            for (e, _, mob_physics_state) in (&entities, &mobs, &physics_states).join() {
+                let position = mob_physics_state.get_latest_state().position;
+                if distance(&position.xz(), &player.xz()) > MOB_DESPAWN_DISTANCE {
+                    if let Err(e) = entities.delete(e) {
+                        error!("{}", e);
+                    }
+                } else {
+                    mob_count += 1;
+                }
+            }
+            if mob_count >= MAX_MOBS {
+                continue;
+            }
+
+            // A single try per interval, on the highest block of a column around the player
+            let mut rng = thread_rng();
+            let angle = rng.gen_range(0.0, 2.0 * PI);
+            let spawn_distance = rng.gen_range(MOB_SPAWN_MIN_DISTANCE, MOB_SPAWN_MAX_DISTANCE);
+            let x = (player.x + spawn_distance * angle.cos()).floor() as i32;
+            let z = (player.z + spawn_distance * angle.sin()).floor() as i32;
+            let kind = MobKind::Pig;
+            if let Some(y) = chunk_manager.heighest_block_at(x, z) {
+                if can_spawn_at(&chunk_manager, kind, x, y + 1, z) {
+                    spawn_mob(&lazy_update, &entities, kind, vec3(x as f32 + 0.5, (y + 1) as f32, z as f32 + 0.5));
+                }
+            }
+        }
+    }
+}
+
+/// Runs the AI of the mobs, which drives their bodies
+pub struct UpdateMobs;
+
+impl<'a> System<'a> for UpdateMobs {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Arc<ChunkManager>>,
+        Entities<'a>,
+        WriteStorage<'a, Mob>,
+        WriteStorage<'a, MobAI>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, PhysicsBody>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            chunk_manager,
+            entities,
+            mut mobs,
+            mut mob_ais,
+            mut physics_states,
+            mut bodies,
+            mut health,
+        ) = data;
+
+        let now = global_timer.time();
+        for (e, mob, ai, physics_state, body, health) in (&entities, &mut mobs, &mut mob_ais, &mut physics_states, &mut bodies, &mut health).join() {
+            if let Some(landing_speed) = body.landing_speed.take() {
+                health.damage(fall_damage(landing_speed));
+            }
+            let state = physics_state.get_latest_state_mut();
+            if health.is_dead() || state.position.y < 0.0 {
+                info!("A {:?} died at ({} {} {})", mob.kind, state.position.x, state.position.y, state.position.z);
+                if let Err(e) = entities.delete(e) {
+                    error!("{}", e);
+                }
+                continue;
+            }
+            // Mobs stay where they are until the chunk under them is loaded
+            let (x, y, z) = state.feet_block();
+            if chunk_manager.get_block(x, y, z).is_none() {
+                continue;
+            }
+
+            let (direction, jump) = ai.update(mob.kind, state, &chunk_manager, now);
+            if !direction.is_zero() {
+                mob.yaw = direction.x.atan2(direction.z);
+            }
+            state.acceleration = direction * HORIZONTAL_ACCELERATION;
+            // Mobs swim up in fluids
+            if body.fluid.is_some() {
+                state.velocity.y = state.velocity.y.max(SWIMMING_UP_SPEED / 2.0);
+            } else if jump && body.is_on_ground {
+                state.velocity.y = *JUMP_IMPULSE;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RenderMobs {
+    models: HashMap<MobKind, MobModel>,
+}
+
+impl RenderMobs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'a> System<'a> for RenderMobs {
+    type SystemData = (
+        Read<'a, MobTextures>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Mob>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mob_textures,
+            mut shaders,
+            player_state,
+            mobs,
+            physics_states,
+        ) = data;
+
+        let hand_shader = shaders.get_mut("hand_shader").unwrap();
+        hand_shader.use_program();
+        hand_shader.set_uniform1i("tex", 0);
+
+        for player_state in (&player_state).join() {
+            hand_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
+            hand_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
+
+            for (mob, physics_state) in (&mobs, &physics_states).join() {
+                let model = self.models.entry(mob.kind)
+                    .or_insert_with(|| MobModel::new(mob.kind, &mob_textures));
+                model.draw(&physics_state.get_interpolated_state().position, mob.yaw, hand_shader);
+            }
+        }
+    }
+}
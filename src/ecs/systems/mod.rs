@@ -1,14 +1,23 @@
 use specs::{System, Write};
 
+pub use block_tick::*;
+pub use chat::*;
+pub use debug_overlay::*;
+pub use dropped_item::*;
+pub use falling_block::*;
 pub use fps_counter::*;
 pub use hand::*;
+pub use health::*;
 pub use input::*;
 pub use inventory::*;
+pub use mob::*;
 pub use physics::*;
 pub use player::*;
 pub use rendering::*;
+pub use weather::*;
 
 use crate::timer::Timer;
+use crate::world_time::WorldTime;
 
 pub mod input;
 pub mod physics;
@@ -18,16 +27,26 @@ pub mod hand;
 pub mod inventory;
 pub mod rendering;
 pub mod chunk_loading;
+pub mod dropped_item;
+pub mod health;
+pub mod debug_overlay;
+pub mod chat;
+pub mod weather;
+pub mod block_tick;
+pub mod falling_block;
+pub mod mob;
 
 pub struct AdvanceGlobalTime;
 
 impl<'a> System<'a> for AdvanceGlobalTime {
     type SystemData = (
         Write<'a, Timer>,
+        Write<'a, WorldTime>,
     );
 
-    fn run(&mut self, (mut global_timer, ): Self::SystemData) {
+    fn run(&mut self, (mut global_timer, mut world_time): Self::SystemData) {
         global_timer.tick();
+        world_time.advance(global_timer.time());
     }
 }
 
@@ -1,108 +1,109 @@
 use nalgebra_glm::vec3;
-use num_traits::Zero;
-use specs::{Read, System, WriteStorage};
+use specs::{Join, Read, ReadStorage, System, WriteStorage};
 
 use crate::chunk_manager::ChunkManager;
-use crate::constants::{GRAVITY, PLAYER_HALF_WIDTH};
+use crate::constants::{JUMPING_EXHAUSTION, SPRINT_JUMPING_EXHAUSTION, SPRINTING_EXHAUSTION, SWIMMING_UP_SPEED};
+use crate::gui::GuiState;
+use crate::health::{fall_damage, Health};
 use crate::input::InputCache;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::{GameMode, PlayerState};
 use crate::timer::Timer;
 use std::sync::Arc;
 
+/// Turns the keyboard into the acceleration of the body of the player, the body is moved by `EntityPhysics`
 pub struct UpdatePlayerPhysics;
 
 impl<'a> System<'a> for UpdatePlayerPhysics {
     type SystemData = (
-        Read<'a, Timer>,
         Read<'a, InputCache>,
-        Read<'a, Arc<ChunkManager>>,
-        WriteStorage<'a, Interpolator<PlayerPhysicsState>>,
+        Read<'a, GuiState>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, PhysicsBody>,
         WriteStorage<'a, PlayerState>,
+        WriteStorage<'a, Health>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            global_timer,
             input_cache,
-            chunk_manager,
+            gui_state,
             mut player_physics_state,
-            mut player_state) = data;
-
-        use specs::Join;
-        for (player_physics_state, player_state) in (&mut player_physics_state, &mut player_state).join() {
-            player_physics_state.step(global_timer.time(), &mut |player: &PlayerPhysicsState, _t: f32, dt: f32| {
-                let mut player = player.clone();
-                if !player_state.is_flying {
-                    player.acceleration.y += GRAVITY;
-                }
+            mut bodies,
+            mut player_state,
+            mut health) = data;
 
-                player.apply_keyboard_mouvement(player_state, &input_cache);
-                player.velocity += player.acceleration * dt;
-                player.apply_friction(dt, &player_state);
-                player.limit_velocity(&player_state);
+        for (player_physics_state, body, player_state, health) in (&mut player_physics_state, &mut bodies, &mut player_state, &mut health).join() {
+            if health.is_waiting_for_spawn || health.is_dead() {
+                continue;
+            }
+            let is_survival = player_state.game_mode == GameMode::Survival;
 
-                let will_hit_ground = |player: &PlayerPhysicsState| {
-                    let mut player = player.clone();
-                    let vy = vec3(0.0, player.velocity.y, 0.0);
-                    player.aabb.ip_translate(&(vy * dt));
-                    let colliding_block = player.get_colliding_block_coords(&chunk_manager);
-                    if let Some(colliding_block) = colliding_block {
-                        player.separate_from_block(&vy, &colliding_block)
-                    } else {
-                        false
-                    }
-                };
-
-                // We are using the Separated Axis Theorem
-                // We decompose the velocity vector into 3 vectors for each dimension
-                // For each one, we move the entity and do the collision detection/resolution
-                let mut is_player_on_ground = false;
-                let separated_axis = &[
-                    vec3(player.velocity.x, 0.0, 0.0),
-                    vec3(0.0, 0.0, player.velocity.z),
-                    vec3(0.0, player.velocity.y, 0.0)];
-
-                for v in separated_axis {
-                    let bk = player.clone();
-                    player.aabb.ip_translate(&(v * dt));
-                    let colliding_block = player.get_colliding_block_coords(&chunk_manager);
-
-                    // Collision resolution
-                    if let Some(colliding_block) = colliding_block {
-                        is_player_on_ground |= player.separate_from_block(&v, &colliding_block);
-                    }
-
-                    // Don't let the player fall if he's sneaking on the block
-                    if input_cache.is_key_pressed(glfw::Key::LeftShift)
-                        && player_state.is_on_ground
-                        && !will_hit_ground(&player)
-                        && player.velocity.y < 0. {
-                        player = bk;
+            // What happened to the body since the last frame
+            if let Some(landing_speed) = body.landing_speed.take() {
+                if is_survival {
+                    health.damage(fall_damage(landing_speed));
+                }
+            }
+            let walked_distance = std::mem::replace(&mut body.walked_distance, 0.0);
+            if is_survival && player_state.is_sprinting {
+                health.add_exhaustion(walked_distance * SPRINTING_EXHAUSTION);
+            }
+            if body.is_on_ground {
+                player_state.is_flying = false;
+            }
 
-                        if !v.x.is_zero() {
-                            player.velocity.x = 0.0;
-                        }
-                        if !v.z.is_zero() {
-                            player.velocity.z = 0.0;
-                        }
-                    }
+            let player = player_physics_state.get_latest_state_mut();
+            player.acceleration = vec3(0.0, 0.0, 0.0);
+            if !gui_state.is_screen_open() {
+                let jump_last_executed = player_state.jump_last_executed;
+                player.apply_keyboard_mouvement(player_state, body, &input_cache);
+                if is_survival && player_state.jump_last_executed != jump_last_executed {
+                    health.add_exhaustion(if player_state.is_sprinting { SPRINT_JUMPING_EXHAUSTION } else { JUMPING_EXHAUSTION });
                 }
-                player_state.is_on_ground = is_player_on_ground;
-                if player_state.is_on_ground {
-                    player_state.is_flying = false;
+                // Swim up
+                if body.fluid.is_some() && !player_state.is_flying && input_cache.is_key_pressed(glfw::Key::Space) {
+                    player.velocity.y = player.velocity.y.max(SWIMMING_UP_SPEED);
                 }
+            }
+            player_state.configure_body(body, &input_cache);
+        }
+    }
+}
+
+/// Moves every entity with a `PhysicsBody` and makes it collide with the blocks
+pub struct EntityPhysics;
 
-                // Update the position of the player and reset the acceleration
-                player.position.x = player.aabb.mins.x + PLAYER_HALF_WIDTH;
-                player.position.y = player.aabb.mins.y;
-                player.position.z = player.aabb.mins.z + PLAYER_HALF_WIDTH;
+impl<'a> System<'a> for EntityPhysics {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Arc<ChunkManager>>,
+        ReadStorage<'a, Health>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, PhysicsBody>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            chunk_manager,
+            health,
+            mut physics_states,
+            mut bodies,
+        ) = data;
 
-                player.acceleration.x = 0.0;
-                player.acceleration.y = 0.0;
-                player.acceleration.z = 0.0;
-                player
-            })
+        let now = global_timer.time();
+        for (physics_state, body, health) in (&mut physics_states, &mut bodies, health.maybe()).join() {
+            if health.map_or(false, |health| health.is_waiting_for_spawn || health.is_dead()) {
+                continue;
+            }
+            // Entities stay where they are until the chunk under them is loaded
+            let position = physics_state.get_latest_state().position;
+            let (chunk_x, _, chunk_z, _, _, _) = ChunkManager::get_chunk_coords(position.x.floor() as i32, 0, position.z.floor() as i32);
+            if chunk_manager.get_column(chunk_x, chunk_z).is_none() {
+                continue;
+            }
+            physics_state.update_body(now, &chunk_manager, body);
         }
     }
-}
\ No newline at end of file
+}
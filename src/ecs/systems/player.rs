@@ -3,17 +3,22 @@ use std::time::Instant;
 use glfw::{Action, MouseButton};
 use nalgebra::Vector3;
 use nalgebra_glm::{IVec3, vec3};
-use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+use specs::{Entities, Join, LazyUpdate, Read, ReadStorage, System, Write, WriteStorage};
 
 use crate::aabb::{AABB, get_block_aabb};
+use crate::block_tick::{BlockBehaviours, TickContext};
 use crate::chunk::BlockID;
 use crate::chunk_manager::ChunkManager;
+use crate::ecs::components::MainHandItemChanged;
+use crate::gui::{GuiState, Screen};
+use crate::dropped_item::spawn_block_drop;
 use crate::constants::{FAR_PLANE, FLYING_TRIGGER_INTERVAL, FOV, JUMP_IMPULSE, NEAR_PLANE, PLAYER_EYES_HEIGHT, REACH_DISTANCE, SPRINTING_TRIGGER_INTERVAL, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::input::InputCache;
 use crate::inventory::Inventory;
+use crate::inventory::item::{Item, ItemStack};
 use crate::particle_system::ParticleSystem;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::{block_break_time, can_harvest, GameMode, PlayerState};
 use crate::raycast;
 use crate::timer::Timer;
 use crate::types::{ParticleSystems, TexturePack};
@@ -25,20 +30,28 @@ pub struct HandlePlayerInput;
 impl<'a> System<'a> for HandlePlayerInput {
     type SystemData = (
         Read<'a, InputCache>,
+        Read<'a, GuiState>,
         WriteStorage<'a, PlayerState>,
-        WriteStorage<'a, Interpolator<PlayerPhysicsState>>,
+        WriteStorage<'a, Interpolator<EntityPhysicsState>>,
+        ReadStorage<'a, PhysicsBody>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             input_cache,
+            gui_state,
             mut player_state,
             mut player_physics_state,
+            bodies,
         ) = data;
 
-        for (player_state, player_physics_state) in (&mut player_state, &mut player_physics_state).join() {
+        if gui_state.is_screen_open() {
+            return;
+        }
+
+        for (player_state, player_physics_state, body) in (&mut player_state, &mut player_physics_state, &bodies).join() {
             let mut player_state = player_state as &mut PlayerState;
-            let player_physics_state = player_physics_state as &mut Interpolator<PlayerPhysicsState>;
+            let player_physics_state = player_physics_state as &mut Interpolator<EntityPhysicsState>;
             let player_physics_state = player_physics_state.get_latest_state_mut();
 
             for event in &input_cache.events {
@@ -61,7 +74,7 @@ impl<'a> System<'a> for HandlePlayerInput {
                         player_state.fly_last_toggled = Instant::now();
 
                         // Player physics state
-                        if player_state.is_on_ground {
+                        if body.is_on_ground {
                             player_physics_state.velocity.y = *JUMP_IMPULSE;
                             player_state.jump_last_executed = Instant::now();
                         }
@@ -93,7 +106,7 @@ impl<'a> System<'a> for HandlePlayerInput {
             }
 
             // Sneaking
-            if input_cache.is_key_pressed(glfw::Key::LeftShift) && player_state.is_on_ground {
+            if input_cache.is_key_pressed(glfw::Key::LeftShift) && body.is_on_ground {
                 player_state.is_sneaking = true;
                 player_state.is_sprinting = false;
             }
@@ -115,7 +128,7 @@ impl<'a> System<'a> for UpdatePlayerState {
         Read<'a, Timer>,
         Read<'a, Arc<ChunkManager>>,
         WriteStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -128,7 +141,7 @@ impl<'a> System<'a> for UpdatePlayerState {
 
         for (player_state, player_physics_state) in (&mut player_state, &player_physics_state).join() {
             let mut player_state = player_state as &mut PlayerState;
-            let player_physics_state = player_physics_state as &Interpolator<PlayerPhysicsState>;
+            let player_physics_state = player_physics_state as &Interpolator<EntityPhysicsState>;
             let t = global_timer.time();
 
             // Camera height
@@ -157,8 +170,11 @@ impl<'a> System<'a> for UpdatePlayerState {
 
             // Targeted block
             player_state.targeted_block = {
+                // Blocks without collision, like snow, can still be targeted, the fluids are seen through
                 let is_solid_block_at = |x: i32, y: i32, z: i32| {
-                    chunk_manager.is_solid_block_at(x, y, z)
+                    chunk_manager.get_block(x, y, z)
+                        .filter(|&block| !block.is_air() && !block.is_fluid())
+                        .is_some()
                 };
 
                 let fw = player_state.rotation.forward();
@@ -193,25 +209,67 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
         Write<'a, Arc<ChunkManager>>,
         Write<'a, ParticleSystems>,
         Read<'a, InputCache>,
+        Write<'a, GuiState>,
         Read<'a, TexturePack>,
+        Read<'a, BlockBehaviours>,
+        Read<'a, LazyUpdate>,
+        Entities<'a>,
         WriteStorage<'a, PlayerState>,
-        ReadStorage<'a, Interpolator<PlayerPhysicsState>>,
-        ReadStorage<'a, Inventory>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+        WriteStorage<'a, Inventory>,
+        WriteStorage<'a, MainHandItemChanged>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
-            mut chunk_manager,
+            chunk_manager,
             mut particle_systems,
             input_cache,
+            mut gui_state,
             texture_pack,
+            behaviours,
+            lazy_update,
+            entities,
             mut player_state,
             player_physics_state,
-            inventory,
+            mut inventory,
+            mut main_hand_item_changed,
         ) = data;
 
-        for (player_state, player_physics_state, inventory) in (&mut player_state, &player_physics_state, &inventory).join() {
+        if gui_state.is_screen_open() {
+            return;
+        }
+
+        for (e, player_state, player_physics_state, inventory) in (&entities, &mut player_state, &player_physics_state, &mut inventory).join() {
             let player_physics_state = player_physics_state.get_latest_state();
+            let game_mode = player_state.game_mode;
+            let held_item = inventory.get_selected_item();
+            // The horizontal direction the player looks at
+            let facing = {
+                let forward = player_state.rotation.forward();
+                if forward.x.abs() > forward.z.abs() {
+                    (forward.x.signum() as i32, 0)
+                } else {
+                    (0, forward.z.signum() as i32)
+                }
+            };
+            let mut place_block = |(x, y, z), normal: &IVec3| {
+                let consume_item = game_mode == GameMode::Survival;
+                if place_block((x, y, z), normal, facing, &player_physics_state.aabb, inventory, consume_item, &chunk_manager, &behaviours) {
+                    // The stack may have run out
+                    if let Err(e) = main_hand_item_changed.insert(e, MainHandItemChanged) {
+                        error!("{}", e);
+                    }
+                }
+            };
+            let mut break_block = |(x, y, z)| {
+                let mut particle_system = particle_systems.get_mut("block_particles").unwrap();
+                if let Some(block) = break_block((x, y, z), &chunk_manager, &mut particle_system, &texture_pack) {
+                    if game_mode == GameMode::Survival && can_harvest(block, held_item) {
+                        spawn_block_drop(&lazy_update, &entities, ItemStack::new(1, Item::Block(block.item_block())), (x, y, z));
+                    }
+                }
+            };
 
             // Place or break a block by clicking on a mouse button
             for event in &input_cache.events {
@@ -220,15 +278,32 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                         player_state.block_placing_last_executed = Instant::now();
 
                         match button {
-                            MouseButton::Button1 => {
+                            // In survival, blocks are broken by holding the button
+                            MouseButton::Button1 if game_mode == GameMode::Creative => {
                                 if let &Some(((x, y, z), _)) = &player_state.targeted_block {
-                                    let mut particle_system = particle_systems.get_mut("block_particles").unwrap();
-                                    break_block((x, y, z), &chunk_manager, &mut particle_system, &texture_pack);
+                                    break_block((x, y, z));
                                 }
                             }
                             MouseButton::Button2 => {
                                 if let &Some(((x, y, z), normal)) = &player_state.targeted_block {
-                                    place_block((x, y, z), &normal, &player_physics_state.aabb, &inventory, &chunk_manager);
+                                    // Sneaking allows placing blocks against a crafting table or a lever
+                                    if !player_state.is_sneaking {
+                                        let block = chunk_manager.get_block(x, y, z);
+                                        if block == Some(BlockID::CraftingTable) {
+                                            gui_state.open_screen(Screen::CraftingTable);
+                                            break;
+                                        }
+                                        let context = TickContext {
+                                            chunk_manager: &chunk_manager,
+                                            lazy_update: &lazy_update,
+                                            entities: &entities,
+                                        };
+                                        let behaviour = block.and_then(|block| behaviours.get(block));
+                                        if behaviour.map_or(false, |behaviour| behaviour.used(&context, x, y, z)) {
+                                            break;
+                                        }
+                                    }
+                                    place_block((x, y, z), &normal);
                                 }
                             }
                             _ => {}
@@ -238,18 +313,46 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
                 }
             }
 
-            // Repeated block placing or breaking while the mouse button is pressed
+            // Breaking progress while the button is held on the same block in survival
             let now = Instant::now();
+            let dt = now.duration_since(player_state.break_progress_last_updated).as_secs_f32();
+            player_state.break_progress_last_updated = now;
+            let target = player_state.targeted_block.map(|(position, _)| position);
+            match target {
+                Some((x, y, z)) if game_mode == GameMode::Survival
+                    && input_cache.is_mouse_button_pressed(glfw::MouseButtonLeft) => {
+                    if player_state.breaking_block != target {
+                        player_state.breaking_block = target;
+                        player_state.break_progress = 0.0;
+                    }
+                    let break_time = chunk_manager.get_block(x, y, z)
+                        .and_then(|block| block_break_time(block, held_item));
+                    // Unbreakable blocks never make progress
+                    if let Some(break_time) = break_time {
+                        player_state.break_progress += dt / break_time.max(0.001);
+                        if player_state.break_progress >= 1.0 {
+                            break_block((x, y, z));
+                            player_state.breaking_block = None;
+                            player_state.break_progress = 0.0;
+                        }
+                    }
+                }
+                _ => {
+                    player_state.breaking_block = None;
+                    player_state.break_progress = 0.0;
+                }
+            }
+
+            // Repeated block placing or breaking while the mouse button is pressed
             if now.duration_since(player_state.block_placing_last_executed).as_secs_f32() >= 0.25 {
-                if input_cache.is_mouse_button_pressed(glfw::MouseButtonLeft) {
+                if game_mode == GameMode::Creative && input_cache.is_mouse_button_pressed(glfw::MouseButtonLeft) {
                     if let &Some(((x, y, z), _)) = &player_state.targeted_block {
-                        let mut particle_system = particle_systems.get_mut("block_particles").unwrap();
-                        break_block((x, y, z), &chunk_manager, &mut particle_system, &texture_pack);
+                        break_block((x, y, z));
                     }
                     player_state.block_placing_last_executed = Instant::now();
                 } else if input_cache.is_mouse_button_pressed(glfw::MouseButtonRight) {
                     if let &Some(((x, y, z), normal)) = &player_state.targeted_block {
-                        place_block((x, y, z), &normal, &player_physics_state.aabb, &inventory, &mut chunk_manager);
+                        place_block((x, y, z), &normal);
                     }
                     player_state.block_placing_last_executed = Instant::now();
                 }
@@ -258,25 +361,39 @@ impl<'a> System<'a> for PlaceAndBreakBlocks {
     }
 }
 
-fn break_block((x, y, z): (i32, i32, i32), chunk_manager: &ChunkManager, particle_system: &mut ParticleSystem, uv_map: &TexturePack) {
+/// Returns the block which was broken, if any
+fn break_block((x, y, z): (i32, i32, i32), chunk_manager: &ChunkManager, particle_system: &mut ParticleSystem, uv_map: &TexturePack) -> Option<BlockID> {
     let block = chunk_manager.get_block(x, y, z).unwrap();
     if block != BlockID::Air {
         chunk_manager.put_block(BlockID::Air, x, y, z);
         particle_system.spawn_block_breaking_particles(vec3(x as f32, y as f32, z as f32), &uv_map, block);
         info!("Destroyed block at ({} {} {})", x, y, z);
+        Some(block)
+    } else {
+        None
     }
 }
 
-fn place_block((x, y, z): (i32, i32, i32), normal: &IVec3, player_aabb: &AABB, inventory: &Inventory, chunk_manager: &ChunkManager) {
+/// Places one block of the selected stack against the targeted face, `facing` is where the player looks
+/// The item is only used up when `consume_item` is set
+/// Returns whether a block was placed
+fn place_block((x, y, z): (i32, i32, i32), normal: &IVec3, facing: (i32, i32), player_aabb: &AABB, inventory: &mut Inventory, consume_item: bool, chunk_manager: &ChunkManager, behaviours: &BlockBehaviours) -> bool {
     let adjacent_block = IVec3::new(x, y, z) + normal;
     let adjacent_block_aabb = get_block_aabb(&vec3(
         adjacent_block.x as f32,
         adjacent_block.y as f32,
         adjacent_block.z as f32));
     if !player_aabb.intersects(&adjacent_block_aabb) {
-        if let Some(block) = inventory.get_selected_item() {
-            chunk_manager.put_block(block, adjacent_block.x, adjacent_block.y, adjacent_block.z);
+        let block = if consume_item {
+            inventory.consume_selected_block()
+        } else {
+            inventory.get_selected_item().and_then(|item| item.block())
+        };
+        if let Some(block) = block {
+            chunk_manager.put_block_with_state(block, behaviours.placed_state(block, facing), adjacent_block.x, adjacent_block.y, adjacent_block.z);
+            info!("Put block at ({} {} {})", adjacent_block.x, adjacent_block.y, adjacent_block.z);
+            return true;
         }
-        info!("Put block at ({} {} {})", adjacent_block.x, adjacent_block.y, adjacent_block.z);
     }
+    false
 }
\ No newline at end of file
@@ -1,48 +1,162 @@
 use nalgebra::Matrix4;
-use nalgebra_glm::vec3;
-use specs::{Join, Read, ReadStorage, System, Write, WriteStorage};
+use nalgebra_glm::{Mat4, pi, vec3};
+use specs::{Join, Read, ReadStorage, System, Write};
 
-use crate::chunk_manager::ChunkManager;
-use crate::constants::{BACKGROUND_COLOR, BLOCK_OUTLINE_WIDTH, RENDER_DISTANCE, ENABLE_FOG};
-use crate::gui::{create_block_outline_vao, create_crosshair_vao, create_hotbar_selection_vao, create_hotbar_vao, draw_crosshair};
+use crate::chunk::RenderLayer;
+use crate::chunk_manager::{ChunkCullingStats, ChunkManager};
+use crate::constants::{BLOCK_OUTLINE_WIDTH, DESTROY_STAGES, GUI_SCALING, RENDER_DISTANCE, ENABLE_FOG, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::frustum::Frustum;
+use crate::gui::{create_block_crack_vao, create_block_outline_vao, create_button_vaos, create_crosshair_vao, create_digit_vaos, create_hotbar_selection_vao, create_hotbar_vao, create_overlay_vao, create_slot_vao, draw_crosshair, draw_overlay, draw_respawn_button, GuiState, RESPAWN_BUTTON_Y, Screen};
+use crate::health::{Health, StatusBarIcons};
+use crate::input::InputCache;
 use crate::inventory::Inventory;
-use crate::player::PlayerState;
+use crate::inventory::item::ItemRenders;
+use crate::inventory::screen::cursor_to_gui_coords;
+use crate::player::{GameMode, PlayerState};
+use crate::sky::{celestial_body_model_matrix, MOON_SIZE, Sky, sky_view_matrix, SUN_SIZE, SUNSET_COLOR};
+use crate::text::{TextRenderer, WHITE};
 use crate::timer::Timer;
-use crate::types::{ParticleSystems, Shaders, TexturePack};
+use crate::types::{ItemIcons, ParticleSystems, Shaders, TexturePack};
+use crate::weather::Weather;
+use crate::world_time::WorldTime;
 use std::sync::Arc;
 
+/// Clears the window with the sky, before anything else is drawn
+pub struct RenderSky {
+    // Covers the window for the sky, and is the quad of the sun and the moon
+    quad_vao: u32,
+}
+
+impl RenderSky {
+    pub fn new() -> Self {
+        Self {
+            quad_vao: create_overlay_vao(),
+        }
+    }
+}
+
+impl<'a> System<'a> for RenderSky {
+    type SystemData = (
+        Read<'a, WorldTime>,
+        Read<'a, Weather>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            world_time,
+            weather,
+            mut shaders,
+            player_state,
+        ) = data;
+
+        let sky = Sky::at(world_time.day_fraction())
+            .with_weather(weather.sky_darkness(), weather.lightning_flash());
+        let horizon_color = sky.horizon_color;
+        gl_call!(gl::ClearColor(horizon_color.x, horizon_color.y, horizon_color.z, 1.0));
+        gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
+
+        for player_state in (&player_state).join() {
+            let view_matrix = sky_view_matrix(&player_state.view_matrix);
+            let inverse_view_projection = (player_state.projection_matrix * view_matrix)
+                .try_inverse()
+                .unwrap_or_else(Mat4::identity);
+
+            // The sky is behind everything and seen from the inside
+            gl_call!(gl::Disable(gl::DEPTH_TEST));
+            gl_call!(gl::Disable(gl::CULL_FACE));
+            gl_call!(gl::BindVertexArray(self.quad_vao));
+
+            let sky_shader = shaders.get_mut("sky_shader").unwrap();
+            sky_shader.use_program();
+            sky_shader.set_uniform_matrix4fv("inverse_view_projection", inverse_view_projection.as_ptr());
+            sky_shader.set_uniform3f("zenith_color", sky.zenith_color.as_slice());
+            sky_shader.set_uniform3f("horizon_color", horizon_color.as_slice());
+            sky_shader.set_uniform3f("sunset_color", &SUNSET_COLOR);
+            sky_shader.set_uniform1f("sunset_strength", sky.sunset_strength);
+            sky_shader.set_uniform1f("star_brightness", sky.star_brightness);
+            sky_shader.set_uniform1f("celestial_angle", sky.celestial_angle);
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+
+            // The sun and the moon light up the sky behind them
+            gl_call!(gl::Enable(gl::BLEND));
+            gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE));
+            let celestial_shader = shaders.get_mut("celestial_shader").unwrap();
+            celestial_shader.use_program();
+            celestial_shader.set_uniform_matrix4fv("view", view_matrix.as_ptr());
+            celestial_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
+            celestial_shader.set_uniform1f("brightness", sky.celestial_brightness);
+            for &(texture_unit, angle, size) in &[(5, sky.celestial_angle, SUN_SIZE), (6, sky.celestial_angle + pi::<f32>(), MOON_SIZE)] {
+                let model_matrix = celestial_body_model_matrix(angle, size);
+                celestial_shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+                celestial_shader.set_uniform1i("tex", texture_unit);
+                gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+            }
+
+            gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+            gl_call!(gl::Enable(gl::CULL_FACE));
+            gl_call!(gl::Enable(gl::DEPTH_TEST));
+        }
+    }
+}
+
 pub struct RenderChunks;
 
 impl<'a> System<'a> for RenderChunks {
     type SystemData = (
         ReadStorage<'a, PlayerState>,
         Read<'a, Arc<ChunkManager>>,
+        Read<'a, WorldTime>,
+        Read<'a, Weather>,
         Write<'a, Shaders>,
+        Write<'a, ChunkCullingStats>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             player_state,
             chunk_manager,
+            world_time,
+            weather,
             mut shaders,
+            mut culling_stats,
         ) = data;
 
-        let mut voxel_shader = shaders.get_mut("voxel_shader").unwrap();
+        let sky = Sky::at(world_time.day_fraction())
+            .with_weather(weather.sky_darkness(), weather.lightning_flash());
+        let voxel_shader = shaders.get_mut("voxel_shader").unwrap();
         voxel_shader.use_program();
         voxel_shader.set_uniform1i("array_texture", 0);
-        let (r, g, b, a) = BACKGROUND_COLOR;
         voxel_shader.set_uniform1i("enable_fog", ENABLE_FOG as i32);
-        voxel_shader.set_uniform3f("sky_color", &[r, g, b]);
+        // The fog blends the world into the horizon
+        voxel_shader.set_uniform3f("sky_color", sky.horizon_color.as_slice());
+        voxel_shader.set_uniform1f("sky_light", sky.sky_light);
         voxel_shader.set_uniform1f("render_distance", RENDER_DISTANCE as f32);
 
-        gl_call!(gl::ClearColor(r, g, b, a));
-        gl_call!(gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT));
         gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
 
         for player_state in (&player_state).join() {
             voxel_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
             voxel_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
-            chunk_manager.render_loaded_chunks(&mut voxel_shader);
+            let camera_position = player_state.view_matrix.try_inverse()
+                .map(|inverse_view| inverse_view.column(3).xyz())
+                .unwrap_or_else(|| vec3(0.0, 0.0, 0.0));
+
+            let frustum = Frustum::from_view_projection(&player_state.view_matrix, &player_state.projection_matrix);
+            let (visible_chunks, stats) = chunk_manager.cull_loaded_chunks(&frustum, &camera_position);
+            *culling_stats = stats;
+
+            // Opaque and cutout geometry writes to the depth buffer without blending
+            gl_call!(gl::Disable(gl::BLEND));
+            chunk_manager.render_chunks(&visible_chunks, RenderLayer::Opaque, &camera_position);
+            chunk_manager.render_chunks(&visible_chunks, RenderLayer::Cutout, &camera_position);
+
+            // Translucent geometry is blended from back to front on top of the rest of the world
+            gl_call!(gl::Enable(gl::BLEND));
+            gl_call!(gl::DepthMask(gl::FALSE));
+            chunk_manager.render_chunks(&visible_chunks, RenderLayer::Translucent, &camera_position);
+            gl_call!(gl::DepthMask(gl::TRUE));
         }
     }
 }
@@ -85,12 +199,14 @@ impl<'a> System<'a> for RenderParticles {
 
 pub struct RenderBlockOutline {
     vao: u32,
+    crack_vao: u32,
 }
 
 impl RenderBlockOutline {
     pub fn new() -> Self {
         Self {
-            vao: create_block_outline_vao()
+            vao: create_block_outline_vao(),
+            crack_vao: create_block_crack_vao(),
         }
     }
 }
@@ -121,6 +237,26 @@ impl<'a> System<'a> for RenderBlockOutline {
                 gl_call!(gl::LineWidth(BLOCK_OUTLINE_WIDTH));
                 gl_call!(gl::BindVertexArray(self.vao));
                 gl_call!(gl::DrawArrays(gl::LINES, 0, 24));
+
+                // Cracks on the block being broken, slightly bigger than it to avoid z-fighting
+                if player_state.breaking_block.is_some() && player_state.break_progress > 0.0 {
+                    let stage = ((player_state.break_progress * DESTROY_STAGES as f32) as i32).min(DESTROY_STAGES as i32 - 1);
+                    let model_matrix = Matrix4::new_translation(&vec3(x - 0.001, y - 0.001, z - 0.001))
+                        * Matrix4::new_scaling(1.002);
+
+                    let hand_shader = shaders.get_mut("hand_shader").unwrap();
+                    hand_shader.use_program();
+                    hand_shader.set_uniform1i("tex", 4);
+                    hand_shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+                    hand_shader.set_uniform_matrix4fv("view", player_state.view_matrix.as_ptr());
+                    hand_shader.set_uniform_matrix4fv("projection", player_state.projection_matrix.as_ptr());
+
+                    gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+                    gl_call!(gl::DepthMask(gl::FALSE));
+                    gl_call!(gl::BindVertexArray(self.crack_vao));
+                    gl_call!(gl::DrawArrays(gl::TRIANGLES, stage * 36, 36));
+                    gl_call!(gl::DepthMask(gl::TRUE));
+                }
             }
         }
     }
@@ -130,6 +266,13 @@ pub(crate) struct RenderGUI {
     crosshair_vao: u32,
     hotbar_vao: u32,
     hotbar_selection_vao: u32,
+    slot_vao: u32,
+    digit_vaos: [u32; 10],
+    overlay_vao: u32,
+    button_vaos: [u32; 2],
+    status_bar_icons: StatusBarIcons,
+    item_renders: ItemRenders,
+    text_renderer: TextRenderer,
 }
 
 impl RenderGUI {
@@ -138,6 +281,13 @@ impl RenderGUI {
             crosshair_vao: create_crosshair_vao(),
             hotbar_vao: create_hotbar_vao(),
             hotbar_selection_vao: create_hotbar_selection_vao(),
+            slot_vao: create_slot_vao(),
+            digit_vaos: create_digit_vaos(),
+            overlay_vao: create_overlay_vao(),
+            button_vaos: create_button_vaos(),
+            status_bar_icons: StatusBarIcons::new(),
+            item_renders: ItemRenders::default(),
+            text_renderer: TextRenderer::new(),
         }
     }
 }
@@ -145,28 +295,78 @@ impl RenderGUI {
 impl<'a> System<'a> for RenderGUI {
     type SystemData = (
         Read<'a, TexturePack>,
+        Read<'a, ItemIcons>,
+        Read<'a, GuiState>,
+        Read<'a, InputCache>,
         Write<'a, Shaders>,
-        WriteStorage<'a, Inventory>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Inventory>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
         let (
             texture_pack,
+            item_icons,
+            gui_state,
+            input_cache,
             mut shaders,
-            mut inventory,
+            player_state,
+            health,
+            inventory,
         ) = data;
 
-        for inventory in (&mut inventory).join() {
+        for (player_state, health, inventory) in (&player_state, &health, &inventory).join() {
+            if gui_state.screen == Some(Screen::Death) {
+                let cursor = cursor_to_gui_coords(input_cache.last_cursor_pos.x, input_cache.last_cursor_pos.y);
+                gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+                gl_call!(gl::Disable(gl::DEPTH_TEST));
+                let mut color_shader = shaders.get_mut("color_shader").unwrap();
+                draw_overlay(self.overlay_vao, [0.5, 0.0, 0.0, 0.5], &mut color_shader);
+                let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
+                draw_respawn_button(&self.button_vaos, cursor, &mut gui_shader);
+
+                let center_x = WINDOW_WIDTH as f32 / 2.0;
+                let button_y = WINDOW_HEIGHT as f32 / 2.0 + RESPAWN_BUTTON_Y * GUI_SCALING;
+                self.text_renderer.begin();
+                self.text_renderer.draw_centered_text_with_shadow((center_x, button_y + 60.0 * GUI_SCALING), 2.0 * GUI_SCALING, WHITE, "You died!");
+                self.text_renderer.draw_centered_text_with_shadow((center_x, button_y + 4.0 * GUI_SCALING), GUI_SCALING, WHITE, "Respawn");
+                self.text_renderer.end(shaders.get_mut("renderer2d_shader").unwrap());
+                gl_call!(gl::Enable(gl::DEPTH_TEST));
+                continue;
+            }
+
+            if gui_state.is_inventory_open() {
+                let cursor = cursor_to_gui_coords(input_cache.last_cursor_pos.x, input_cache.last_cursor_pos.y);
+                gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+                gl_call!(gl::Disable(gl::DEPTH_TEST));
+                let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
+                inventory.draw_screen_slots(self.hotbar_vao, self.slot_vao, &mut gui_shader);
+
+                let mut item_shader = shaders.get_mut("item_shader").unwrap();
+                inventory.draw_screen_items(cursor, &mut self.item_renders, &texture_pack, &item_icons, &mut item_shader);
+
+                let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
+                inventory.draw_screen_item_counts(cursor, &self.digit_vaos, &mut gui_shader);
+                gl_call!(gl::Enable(gl::DEPTH_TEST));
+                continue;
+            }
+
             let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
             draw_crosshair(self.crosshair_vao, &mut gui_shader);
             gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
             gl_call!(gl::Disable(gl::DEPTH_TEST));
-            inventory.update_dirty_items(&texture_pack);
             inventory.draw_hotbar(self.hotbar_vao, &mut gui_shader);
             inventory.draw_hotbar_selection_box(self.hotbar_selection_vao, &mut gui_shader);
+            if player_state.game_mode == GameMode::Survival {
+                health.draw_status_bars(&self.status_bar_icons, &mut gui_shader);
+            }
 
             let mut item_shader = shaders.get_mut("item_shader").unwrap();
-            inventory.draw_hotbar_items(&mut item_shader);
+            inventory.draw_hotbar_items(&mut self.item_renders, &texture_pack, &item_icons, &mut item_shader);
+
+            let mut gui_shader = shaders.get_mut("gui_shader").unwrap();
+            inventory.draw_hotbar_item_counts(&self.digit_vaos, &mut gui_shader);
             gl_call!(gl::Enable(gl::DEPTH_TEST));
         }
     }
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Instant;
+
+use rand::{Rng, thread_rng};
+use specs::{Join, Read, ReadStorage, System, Write};
+
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::physics::{EntityPhysicsState, Interpolator};
+use crate::player::PlayerState;
+use crate::precipitation::PrecipitationRenderer;
+use crate::timer::Timer;
+use crate::types::Shaders;
+use crate::weather::{FREEZING_TEMPERATURE, Precipitation, Weather};
+
+// Layers of snow put down every second during a whole snowfall, and how far from the player
+const SNOW_LAYERS_PER_SECOND: f32 = 20.0;
+const SNOW_RADIUS: i32 = 32;
+
+/// Changes the weather and covers the exposed ground with snow where it is cold enough
+pub struct UpdateWeather {
+    // Layers of snow not put down yet, the fraction left at the last frame
+    snow_layers: f32,
+    last_updated: Instant,
+}
+
+impl UpdateWeather {
+    pub fn new() -> Self {
+        Self {
+            snow_layers: 0.0,
+            last_updated: Instant::now(),
+        }
+    }
+
+    /// Puts a layer of snow on the top face of the highest block of the column, if snow falls there
+    fn cover_with_snow(weather: &Weather, chunk_manager: &ChunkManager, x: i32, z: i32) {
+        let y = match chunk_manager.heighest_block_at(x, z) {
+            Some(y) => y,
+            None => return,
+        };
+        let can_hold_snow = chunk_manager.get_block(x, y, z)
+            .filter(|block| block.has_collision())
+            .is_some();
+        let is_exposed = chunk_manager.get_block(x, y + 1, z) == Some(BlockID::Air);
+        if can_hold_snow
+            && is_exposed
+            && weather.precipitation_at(x, y + 1, z) == Some(Precipitation::Snow)
+            && weather.temperature(x, y + 1, z) < FREEZING_TEMPERATURE {
+            chunk_manager.put_block(BlockID::Snow, x, y + 1, z);
+        }
+    }
+}
+
+impl<'a> System<'a> for UpdateWeather {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Arc<ChunkManager>>,
+        Write<'a, Weather>,
+        ReadStorage<'a, PlayerState>,
+        ReadStorage<'a, Interpolator<EntityPhysicsState>>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            chunk_manager,
+            mut weather,
+            player_state,
+            player_physics_state,
+        ) = data;
+
+        let now = global_timer.time();
+        let elapsed = now.saturating_duration_since(self.last_updated).as_secs_f32();
+        self.last_updated = now;
+        weather.advance(now);
+
+        self.snow_layers += SNOW_LAYERS_PER_SECOND * weather.precipitation_strength() * elapsed;
+        let mut rng = thread_rng();
+        for (_, player_physics_state) in (&player_state, &player_physics_state).join() {
+            let position = player_physics_state.get_latest_state().position;
+            let (p_x, p_z) = (position.x.floor() as i32, position.z.floor() as i32);
+            while self.snow_layers >= 1.0 {
+                self.snow_layers -= 1.0;
+                let x = p_x + rng.gen_range(-SNOW_RADIUS, SNOW_RADIUS + 1);
+                let z = p_z + rng.gen_range(-SNOW_RADIUS, SNOW_RADIUS + 1);
+                Self::cover_with_snow(&weather, &chunk_manager, x, z);
+            }
+        }
+    }
+}
+
+pub struct RenderPrecipitation {
+    precipitation_renderer: PrecipitationRenderer,
+}
+
+impl RenderPrecipitation {
+    pub fn new() -> Self {
+        Self {
+            precipitation_renderer: PrecipitationRenderer::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for RenderPrecipitation {
+    type SystemData = (
+        Read<'a, Timer>,
+        Read<'a, Weather>,
+        Read<'a, Arc<ChunkManager>>,
+        Write<'a, Shaders>,
+        ReadStorage<'a, PlayerState>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            global_timer,
+            weather,
+            chunk_manager,
+            mut shaders,
+            player_state,
+        ) = data;
+
+        for player_state in (&player_state).join() {
+            let camera_position = match player_state.view_matrix.try_inverse() {
+                Some(inverse_view) => inverse_view.column(3).xyz(),
+                None => continue,
+            };
+            self.precipitation_renderer.update(global_timer.time(), &camera_position, &weather, &chunk_manager);
+
+            // The drops are blended with what is behind them without hiding each other
+            gl_call!(gl::Disable(gl::CULL_FACE));
+            gl_call!(gl::DepthMask(gl::FALSE));
+            gl_call!(gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA));
+            self.precipitation_renderer.render(shaders.get_mut("precipitation_shader").unwrap(),
+                                               &player_state.view_matrix, &player_state.projection_matrix);
+            gl_call!(gl::DepthMask(gl::TRUE));
+            gl_call!(gl::Enable(gl::CULL_FACE));
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use nalgebra_glm::vec3;
+use specs::{Builder, Entities, LazyUpdate};
+
+use crate::block_tick::{BlockBehaviour, TickContext};
+use crate::chunk::BlockID;
+use crate::constants::{FALLING_BLOCK_DELAY, PHYSICS_TICKRATE};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+
+/// A block of sand or gravel falling, it turns back into a block where it lands
+/// Its position is stored in an `Interpolator<EntityPhysicsState>` component, the bottom center of the block
+pub struct FallingBlock {
+    pub block: BlockID,
+}
+
+fn falling_block_physics_body() -> PhysicsBody {
+    PhysicsBody {
+        drag: 0.0,
+        ..PhysicsBody::new(1.0, 1.0)
+    }
+}
+
+/// Whether a falling block can take the place of `block`, when it starts falling or lands
+pub fn is_replaced_by_falling_blocks(block: BlockID) -> bool {
+    block.is_air() || block.is_fluid() || block == BlockID::Snow
+}
+
+/// Creates a falling block entity, it is added to the world at the end of the frame
+pub fn spawn_falling_block(lazy_update: &LazyUpdate, entities: &Entities, block: BlockID, (x, y, z): (i32, i32, i32)) {
+    let body = falling_block_physics_body();
+    let state = EntityPhysicsState::new_at_position(vec3(x as f32 + 0.5, y as f32, z as f32 + 0.5), &body);
+    lazy_update.create_entity(entities)
+        .with(FallingBlock { block })
+        .with(Interpolator::new(1.0 / PHYSICS_TICKRATE, state))
+        .with(body)
+        .build();
+}
+
+/// Sand and gravel turn into falling blocks when the block under them goes away
+pub struct Gravity;
+
+impl BlockBehaviour for Gravity {
+    fn neighbour_changed(&self, context: &TickContext, x: i32, y: i32, z: i32, _from: (i32, i32, i32)) {
+        let chunk_manager = context.chunk_manager;
+        if let Some(block) = chunk_manager.get_block(x, y, z) {
+            chunk_manager.schedule_tick(block, x, y, z, FALLING_BLOCK_DELAY, 0);
+        }
+    }
+
+    fn scheduled_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        let chunk_manager = context.chunk_manager;
+        let can_fall = y > 0 && chunk_manager.get_block(x, y - 1, z)
+            .filter(|&block| is_replaced_by_falling_blocks(block))
+            .is_some();
+        if !can_fall {
+            return;
+        }
+        let block = chunk_manager.get_block(x, y, z).unwrap();
+        chunk_manager.put_block(BlockID::Air, x, y, z);
+        spawn_falling_block(context.lazy_update, context.entities, block, (x, y, z));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chunk_manager::test_util::{empty_world, fill};
+
+    use super::*;
+
+    #[test]
+    fn falls_down_a_narrow_shaft_onto_the_ground() {
+        let chunk_manager = empty_world(0..=0, 0..=0);
+        fill(&chunk_manager, BlockID::Stone, (0, 0, 0), (15, 30, 15));
+        // The walls of the shaft touch the sides of the block without stopping it
+        fill(&chunk_manager, BlockID::Air, (5, 11, 5), (5, 30, 5));
+
+        let mut body = falling_block_physics_body();
+        let state = EntityPhysicsState::new_at_position(vec3(5.5, 30.0, 5.5), &body);
+        let mut interpolator = Interpolator::new(1.0 / PHYSICS_TICKRATE, state);
+        let start = interpolator.current_time;
+        for frame in 1..=3 * PHYSICS_TICKRATE as u32 {
+            interpolator.update_body(start + Duration::from_secs_f32(frame as f32 / PHYSICS_TICKRATE), &chunk_manager, &mut body);
+        }
+        assert!(body.is_on_ground);
+        assert_eq!(interpolator.get_latest_state().position, vec3(5.5, 11.0, 5.5));
+    }
+}
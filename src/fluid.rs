@@ -0,0 +1,311 @@
+use crate::block_tick::{BlockBehaviour, TickContext};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{IN_LAVA_FRICTION, IN_LAVA_SPEED_FACTOR, IN_WATER_FRICTION, IN_WATER_SPEED_FACTOR};
+
+// The level of a fluid is 0 for a source, and grows with the distance to the source
+pub const MAX_FLUID_LEVEL: u8 = 7;
+// Layout of the block state of a fluid
+const LEVEL_MASK: u8 = 0b0111;
+// Set on fluids falling from the block above, they spread like sources where they land
+const FALLING_BIT: u8 = 0b1000;
+
+const HORIZONTAL_DIRECTIONS: [(i32, i32); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct FluidState {
+    pub level: u8,
+    pub is_falling: bool,
+}
+
+impl FluidState {
+    pub const SOURCE: FluidState = FluidState { level: 0, is_falling: false };
+
+    pub fn flowing(level: u8) -> Self {
+        FluidState { level, is_falling: false }
+    }
+
+    pub fn falling() -> Self {
+        FluidState { level: 0, is_falling: true }
+    }
+
+    pub fn from_block_state(state: u8) -> Self {
+        FluidState {
+            level: state & LEVEL_MASK,
+            is_falling: state & FALLING_BIT != 0,
+        }
+    }
+
+    pub fn to_block_state(&self) -> u8 {
+        self.level & LEVEL_MASK | if self.is_falling { FALLING_BIT } else { 0 }
+    }
+
+    pub fn is_source(&self) -> bool {
+        *self == FluidState::SOURCE
+    }
+
+    /// Level from which the fluid spreads sideways
+    fn spreading_level(&self) -> u8 {
+        if self.is_falling { 0 } else { self.level }
+    }
+
+    /// Height of the surface in eighths of a block, sources are a bit lower than a full block
+    pub fn height(&self) -> u32 {
+        if self.is_falling {
+            8
+        } else {
+            (7 - self.level as i32).max(1) as u32
+        }
+    }
+}
+
+impl BlockID {
+    /// Physics ticks between two steps of the flow of a fluid
+    pub fn fluid_tick_delay(&self) -> u64 {
+        match self {
+            BlockID::Lava => 90,
+            _ => 15,
+        }
+    }
+
+    /// How much the level grows from a block to the next, lava doesn't flow as far as water
+    fn fluid_level_step(&self) -> u8 {
+        match self {
+            BlockID::Lava => 2,
+            _ => 1,
+        }
+    }
+
+    /// Friction of the fluid on the player swimming in it
+    pub fn fluid_friction(&self) -> f32 {
+        match self {
+            BlockID::Lava => IN_LAVA_FRICTION,
+            _ => IN_WATER_FRICTION,
+        }
+    }
+
+    /// Fraction of the usual speed at which the player moves in the fluid
+    pub fn fluid_speed_factor(&self) -> f32 {
+        match self {
+            BlockID::Lava => IN_LAVA_SPEED_FACTOR,
+            _ => IN_WATER_SPEED_FACTOR,
+        }
+    }
+
+    /// How far a fluid looks for a hole to flow towards
+    fn fluid_slope_distance(&self) -> u32 {
+        match self {
+            BlockID::Lava => 2,
+            _ => 4,
+        }
+    }
+}
+
+fn fluid_state_at(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> FluidState {
+    FluidState::from_block_state(chunk_manager.get_block_state(x, y, z).unwrap_or(0))
+}
+
+/// Snow is washed away by the fluids
+fn is_replaced_by_fluids(block: BlockID) -> bool {
+    block.is_air() || block == BlockID::Snow
+}
+
+/// Whether `fluid` in `state` can flow into (x, y, z), replacing what is there or reacting with another fluid
+fn can_flow_into(chunk_manager: &ChunkManager, fluid: BlockID, state: FluidState, x: i32, y: i32, z: i32) -> bool {
+    match chunk_manager.get_block(x, y, z) {
+        Some(block) if is_replaced_by_fluids(block) => true,
+        Some(block) if block == fluid => {
+            let current = fluid_state_at(chunk_manager, x, y, z);
+            !current.is_source() && !current.is_falling
+                && (state.is_falling || state.level < current.level)
+        }
+        Some(block) => block.is_fluid(),
+        None => false,
+    }
+}
+
+/// Puts `fluid` at (x, y, z), water and lava meeting turn into stone
+fn flow_into(chunk_manager: &ChunkManager, fluid: BlockID, state: FluidState, x: i32, y: i32, z: i32) {
+    let block = match chunk_manager.get_block(x, y, z) {
+        Some(BlockID::Lava) if fluid == BlockID::Water => {
+            if fluid_state_at(chunk_manager, x, y, z).is_source() {
+                BlockID::Obsidian
+            } else {
+                BlockID::Cobblestone
+            }
+        }
+        Some(BlockID::Water) if fluid == BlockID::Lava => BlockID::Stone,
+        _ => fluid,
+    };
+    let state = if block == fluid { state.to_block_state() } else { 0 };
+    chunk_manager.put_block_with_state(block, state, x, y, z);
+}
+
+/// Whether a fluid moving sideways to (x, y, z) could keep flowing from there
+fn can_pass_through(chunk_manager: &ChunkManager, fluid: BlockID, x: i32, y: i32, z: i32) -> bool {
+    match chunk_manager.get_block(x, y, z) {
+        Some(block) if is_replaced_by_fluids(block) => true,
+        Some(block) => block == fluid && !fluid_state_at(chunk_manager, x, y, z).is_source(),
+        None => false,
+    }
+}
+
+fn is_hole(chunk_manager: &ChunkManager, fluid: BlockID, x: i32, y: i32, z: i32) -> bool {
+    chunk_manager.get_block(x, y, z)
+        .filter(|&block| is_replaced_by_fluids(block) || block == fluid)
+        .is_some()
+}
+
+/// Number of blocks to walk from (x, y, z) to reach a hole the fluid can fall into, None if it is too far
+fn distance_to_hole(chunk_manager: &ChunkManager, fluid: BlockID, x: i32, y: i32, z: i32, distance: u32, came_from: (i32, i32)) -> Option<u32> {
+    let mut shortest = None;
+    for &(dx, dz) in HORIZONTAL_DIRECTIONS.iter().filter(|&&direction| direction != came_from) {
+        let (nx, nz) = (x + dx, z + dz);
+        if !can_pass_through(chunk_manager, fluid, nx, y, nz) {
+            continue;
+        }
+        if is_hole(chunk_manager, fluid, nx, y - 1, nz) {
+            return Some(distance);
+        }
+        if distance < fluid.fluid_slope_distance() {
+            if let Some(found) = distance_to_hole(chunk_manager, fluid, nx, y, nz, distance + 1, (-dx, -dz)) {
+                shortest = Some(shortest.map_or(found, |shortest: u32| shortest.min(found)));
+            }
+        }
+    }
+    shortest
+}
+
+/// The fluid only spreads towards the nearest holes, or in every direction when there are none nearby
+fn flow_directions(chunk_manager: &ChunkManager, fluid: BlockID, x: i32, y: i32, z: i32) -> Vec<(i32, i32)> {
+    // Directions without a hole nearby are the farthest
+    let mut shortest = u32::MAX;
+    let mut directions = Vec::new();
+    for &(dx, dz) in &HORIZONTAL_DIRECTIONS {
+        let (nx, nz) = (x + dx, z + dz);
+        if !can_pass_through(chunk_manager, fluid, nx, y, nz) {
+            continue;
+        }
+        let distance = if is_hole(chunk_manager, fluid, nx, y - 1, nz) {
+            0
+        } else {
+            distance_to_hole(chunk_manager, fluid, nx, y, nz, 1, (-dx, -dz)).unwrap_or(u32::MAX)
+        };
+        if distance < shortest {
+            shortest = distance;
+            directions.clear();
+        }
+        if distance == shortest {
+            directions.push((dx, dz));
+        }
+    }
+    directions
+}
+
+/// State a flowing fluid gets from its neighbours, None when nothing feeds it anymore
+fn fed_state(chunk_manager: &ChunkManager, fluid: BlockID, x: i32, y: i32, z: i32) -> Option<FluidState> {
+    if chunk_manager.get_block(x, y + 1, z) == Some(fluid) {
+        return Some(FluidState::falling());
+    }
+
+    let mut sources = 0;
+    let mut lowest_level = None;
+    for &(dx, dz) in &HORIZONTAL_DIRECTIONS {
+        if chunk_manager.get_block(x + dx, y, z + dz) != Some(fluid) {
+            continue;
+        }
+        let neighbour = fluid_state_at(chunk_manager, x + dx, y, z + dz);
+        if neighbour.is_source() {
+            sources += 1;
+        }
+        let level = neighbour.spreading_level();
+        lowest_level = Some(lowest_level.map_or(level, |lowest: u8| lowest.min(level)));
+    }
+
+    // Water between two sources turns into a source if it doesn't run away below
+    if fluid == BlockID::Water && sources >= 2 {
+        let below = chunk_manager.get_block(x, y - 1, z);
+        let below_is_source = below == Some(fluid) && fluid_state_at(chunk_manager, x, y - 1, z).is_source();
+        if below.map_or(false, |block| block.has_collision()) || below_is_source {
+            return Some(FluidState::SOURCE);
+        }
+    }
+
+    lowest_level
+        .map(|level| level + fluid.fluid_level_step())
+        .filter(|&level| level <= MAX_FLUID_LEVEL)
+        .map(FluidState::flowing)
+}
+
+/// Makes the fluid at (x, y, z) flow by a block
+/// The fluids next to the blocks which change are scheduled to flow in turn by their block updates
+fn tick_fluid(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) {
+    let fluid = match chunk_manager.get_block(x, y, z) {
+        Some(block) if block.is_fluid() => block,
+        _ => return,
+    };
+    let mut state = fluid_state_at(chunk_manager, x, y, z);
+
+    // Lava hardens when water flows next to it
+    if fluid == BlockID::Lava {
+        let touches_water = HORIZONTAL_DIRECTIONS.iter()
+            .map(|&(dx, dz)| (x + dx, y, z + dz))
+            .chain(std::iter::once((x, y + 1, z)))
+            .any(|(x, y, z)| chunk_manager.get_block(x, y, z) == Some(BlockID::Water));
+        if touches_water {
+            let block = if state.is_source() { BlockID::Obsidian } else { BlockID::Cobblestone };
+            chunk_manager.put_block_with_state(block, 0, x, y, z);
+            return;
+        }
+    }
+
+    // Flowing fluids dry up when nothing feeds them anymore
+    if !state.is_source() {
+        match fed_state(chunk_manager, fluid, x, y, z) {
+            None => {
+                chunk_manager.put_block_with_state(BlockID::Air, 0, x, y, z);
+                return;
+            }
+            Some(fed_state) if fed_state != state => {
+                state = fed_state;
+                chunk_manager.put_block_with_state(fluid, state.to_block_state(), x, y, z);
+            }
+            Some(_) => {}
+        }
+    }
+
+    // Fluids fall first, only sources spread sideways over a hole
+    if can_flow_into(chunk_manager, fluid, FluidState::falling(), x, y - 1, z) {
+        flow_into(chunk_manager, fluid, FluidState::falling(), x, y - 1, z);
+        if !state.is_source() {
+            return;
+        }
+    }
+
+    let level = state.spreading_level() + fluid.fluid_level_step();
+    if level > MAX_FLUID_LEVEL {
+        return;
+    }
+    let spread_state = FluidState::flowing(level);
+    for (dx, dz) in flow_directions(chunk_manager, fluid, x, y, z) {
+        if can_flow_into(chunk_manager, fluid, spread_state, x + dx, y, z + dz) {
+            flow_into(chunk_manager, fluid, spread_state, x + dx, y, z + dz);
+        }
+    }
+}
+
+/// Water and lava flow a few ticks after a block next to them changes
+pub struct FluidFlow;
+
+impl BlockBehaviour for FluidFlow {
+    fn neighbour_changed(&self, context: &TickContext, x: i32, y: i32, z: i32, _from: (i32, i32, i32)) {
+        let chunk_manager = context.chunk_manager;
+        if let Some(fluid) = chunk_manager.get_block(x, y, z) {
+            chunk_manager.schedule_tick(fluid, x, y, z, fluid.fluid_tick_delay(), 0);
+        }
+    }
+
+    fn scheduled_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        tick_fluid(context.chunk_manager, x, y, z);
+    }
+}
@@ -0,0 +1,120 @@
+use nalgebra_glm::{Mat4, Vec4, vec3};
+
+use crate::aabb::AABB;
+
+/// View frustum represented by its 6 planes (left, right, bottom, top, near, far)
+/// Each plane is stored as (a, b, c, d) with the normal (a, b, c) pointing inside the frustum
+/// so that a point p is inside the plane when a * p.x + b * p.y + c * p.z + d >= 0
+#[derive(Debug, Copy, Clone)]
+pub struct Frustum {
+    pub planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of the frustum from a view-projection matrix
+    /// http://www.cs.otago.ac.nz/postgrads/alexis/planeExtraction.pdf
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let row = |i: usize| -> Vec4 { view_projection.row(i).transpose() };
+        let (r0, r1, r2, r3) = (row(0), row(1), row(2), row(3));
+
+        let normalize = |plane: Vec4| -> Vec4 {
+            let length = vec3(plane.x, plane.y, plane.z).norm();
+            if length > 0.0 {
+                plane / length
+            } else {
+                plane
+            }
+        };
+
+        Frustum {
+            planes: [
+                normalize(r3 + r0), // Left
+                normalize(r3 - r0), // Right
+                normalize(r3 + r1), // Bottom
+                normalize(r3 - r1), // Top
+                normalize(r3 + r2), // Near
+                normalize(r3 - r2), // Far
+            ]
+        }
+    }
+
+    pub fn from_view_projection(view_matrix: &Mat4, projection_matrix: &Mat4) -> Self {
+        Frustum::from_matrix(&(projection_matrix * view_matrix))
+    }
+
+    /// Checks whether an AABB is at least partially inside the frustum
+    /// For each plane, only the corner of the box the furthest along the normal of the plane
+    /// needs to be tested. This is conservative: some boxes near the corners of the frustum
+    /// are reported as intersecting even though they're outside.
+    pub fn intersects_aabb(&self, aabb: &AABB) -> bool {
+        for plane in &self.planes {
+            let corner = vec3(
+                if plane.x >= 0.0 { aabb.maxs.x } else { aabb.mins.x },
+                if plane.y >= 0.0 { aabb.maxs.y } else { aabb.mins.y },
+                if plane.z >= 0.0 { aabb.maxs.z } else { aabb.mins.z },
+            );
+            if plane.x * corner.x + plane.y * corner.y + plane.z * corner.z + plane.w < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nalgebra_glm::{look_at, perspective, Vec3};
+
+    use super::*;
+
+    /// Camera at the origin looking towards -z, with a field of view of 90° and a far plane at 100
+    fn camera_frustum() -> Frustum {
+        let view = look_at(&vec3(0.0, 0.0, 0.0), &vec3(0.0, 0.0, -1.0), &Vec3::y());
+        let projection = perspective(1.0, std::f32::consts::FRAC_PI_2, 0.1, 100.0);
+        Frustum::from_view_projection(&view, &projection)
+    }
+
+    fn aabb(mins: (f32, f32, f32), maxs: (f32, f32, f32)) -> AABB {
+        AABB::new(vec3(mins.0, mins.1, mins.2), vec3(maxs.0, maxs.1, maxs.2))
+    }
+
+    #[test]
+    fn planes_are_normalized() {
+        for plane in &camera_frustum().planes {
+            assert!((vec3(plane.x, plane.y, plane.z).norm() - 1.0).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn keeps_boxes_in_front_of_the_camera() {
+        let frustum = camera_frustum();
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -11.0), (1.0, 1.0, -9.0))));
+        assert!(frustum.intersects_aabb(&aabb((5.0, 5.0, -50.0), (6.0, 6.0, -49.0))));
+        // Around the camera
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -1.0), (1.0, 1.0, 1.0))));
+    }
+
+    #[test]
+    fn culls_boxes_outside_of_the_view() {
+        let frustum = camera_frustum();
+        // Behind
+        assert!(!frustum.intersects_aabb(&aabb((-1.0, -1.0, 9.0), (1.0, 1.0, 11.0))));
+        // Far to the left, the view is 20 blocks wide 10 blocks away
+        assert!(!frustum.intersects_aabb(&aabb((-30.0, -1.0, -11.0), (-20.0, 1.0, -9.0))));
+        // Above
+        assert!(!frustum.intersects_aabb(&aabb((-1.0, 20.0, -11.0), (1.0, 30.0, -9.0))));
+        // Beyond the far plane
+        assert!(!frustum.intersects_aabb(&aabb((-1.0, -1.0, -150.0), (1.0, 1.0, -120.0))));
+    }
+
+    #[test]
+    fn keeps_boxes_across_a_plane() {
+        let frustum = camera_frustum();
+        // Left plane
+        assert!(frustum.intersects_aabb(&aabb((-12.0, -1.0, -11.0), (-8.0, 1.0, -9.0))));
+        // Near plane
+        assert!(frustum.intersects_aabb(&aabb((-0.5, -0.5, -0.5), (0.5, 0.5, -0.05))));
+        // Far plane
+        assert!(frustum.intersects_aabb(&aabb((-1.0, -1.0, -110.0), (1.0, 1.0, -90.0))));
+    }
+}
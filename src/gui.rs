@@ -1,36 +1,198 @@
 use std::ffi::c_void;
 
-use image::GenericImageView;
+use image::{DynamicImage, GenericImageView};
 use nalgebra::Matrix4;
 use nalgebra_glm::{Mat4, vec3};
 
-use crate::constants::{CROSSHAIR_SIZE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::constants::{CROSSHAIR_SIZE, DESTROY_STAGES, GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::shader_compilation::ShaderProgram;
-use crate::shapes::block_outline;
+use crate::shapes::{block_outline, centered_unit_cube};
 use crate::shapes::quad;
 
-pub fn create_gui_icons_texture() -> u32 {
-    let gui_icons_image = match image::open("textures/gui/icons.png") {
+/// Screen drawn on top of the world which takes over the mouse and the keyboard
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Screen {
+    Inventory,
+    // The inventory screen with a 3x3 crafting grid
+    CraftingTable,
+    // Shown until the player respawns
+    Death,
+    // The input of the chat, the HUD is still drawn
+    Chat,
+}
+
+impl Screen {
+    /// Whether the screen can be closed with escape or the inventory key
+    pub fn is_closable(&self) -> bool {
+        *self != Screen::Death
+    }
+
+    /// Whether the screen shows the slots of the inventory
+    pub fn is_inventory(&self) -> bool {
+        *self == Screen::Inventory || *self == Screen::CraftingTable
+    }
+}
+
+/// The cursor is only released from the window while a screen is open
+#[derive(Default)]
+pub struct GuiState {
+    pub screen: Option<Screen>,
+}
+
+impl GuiState {
+    pub fn is_screen_open(&self) -> bool {
+        self.screen.is_some()
+    }
+
+    pub fn is_inventory_open(&self) -> bool {
+        self.screen.map_or(false, |screen| screen.is_inventory())
+    }
+
+    pub fn open_screen(&mut self, screen: Screen) {
+        self.screen = Some(screen);
+    }
+
+    pub fn close_screen(&mut self) {
+        self.screen = None;
+    }
+
+    /// Closes the open screen unless it has to stay open
+    pub fn try_close_screen(&mut self) {
+        if self.screen.map_or(false, |screen| screen.is_closable()) {
+            self.close_screen();
+        }
+    }
+}
+
+/// Reads a GUI image, which isn't flipped unlike the block textures
+pub fn read_gui_image(path: &str) -> DynamicImage {
+    let image = match image::open(path) {
         Ok(img) => img,
-        Err(err) => panic!("Filename: {}, error: {}", "textures/gui/icons.png", err.to_string())
+        Err(err) => panic!("Filename: {}, error: {}", path, err.to_string())
     };
-    match gui_icons_image.color() {
+    match image.color() {
         image::RGBA(8) => {}
         _ => panic!("Texture format not supported")
     };
+    image
+}
+
+fn create_gui_texture(path: &str) -> u32 {
+    upload_gui_texture(&read_gui_image(path))
+}
 
-    // Upload the image to the GPU
-    let mut gui_icons_texture = 0;
-    gl_call!(gl::CreateTextures(gl::TEXTURE_2D, 1, &mut gui_icons_texture));
-    gl_call!(gl::TextureParameteri(gui_icons_texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST as i32));
-    gl_call!(gl::TextureParameteri(gui_icons_texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32));
-    gl_call!(gl::TextureStorage2D(gui_icons_texture, 1, gl::RGBA8, gui_icons_image.width() as i32, gui_icons_image.height() as i32));
+pub fn upload_gui_texture(image: &DynamicImage) -> u32 {
+    let mut texture = 0;
+    gl_call!(gl::CreateTextures(gl::TEXTURE_2D, 1, &mut texture));
+    gl_call!(gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST as i32));
+    gl_call!(gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32));
+    gl_call!(gl::TextureStorage2D(texture, 1, gl::RGBA8, image.width() as i32, image.height() as i32));
     gl_call!(gl::TextureSubImage2D(
-            gui_icons_texture, 0,
-            0, 0, gui_icons_image.width() as i32, gui_icons_image.height() as i32,
+            texture, 0,
+            0, 0, image.width() as i32, image.height() as i32,
             gl::RGBA, gl::UNSIGNED_BYTE,
-            gui_icons_image.raw_pixels().as_ptr() as *mut c_void));
-    gui_icons_texture
+            image.raw_pixels().as_ptr() as *mut c_void));
+    texture
+}
+
+pub fn create_gui_icons_texture() -> u32 {
+    create_gui_texture("textures/gui/icons.png")
+}
+
+fn create_gui_vao(uv: (f32, f32, f32, f32)) -> u32 {
+    let mut vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(vao, 1, 2 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+    gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+    let mut vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, (5 * std::mem::size_of::<f32>()) as i32));
+    gl_call!(gl::NamedBufferData(vbo,
+                    (30 * std::mem::size_of::<f32>() as usize) as isize,
+                    quad(uv).as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+    vao
+}
+
+/// A rectangle of the icons texture, in pixels
+pub fn create_icon_vao(x: f32, y: f32, width: f32, height: f32) -> u32 {
+    create_gui_vao((x / 256.0, y / 256.0, (x + width) / 256.0, (y + height) / 256.0))
+}
+
+/// A plain quad, to be drawn with the color shader
+pub fn create_overlay_vao() -> u32 {
+    create_gui_vao((0.0, 0.0, 1.0, 1.0))
+}
+
+/// The button of the widgets texture, and its highlighted version
+pub fn create_button_vaos() -> [u32; 2] {
+    [
+        create_gui_vao((0.0, 66.0 / 256.0, 200.0 / 256.0, 86.0 / 256.0)),
+        create_gui_vao((0.0, 86.0 / 256.0, 200.0 / 256.0, 106.0 / 256.0)),
+    ]
+}
+
+// The respawn button of the death screen, relative to the center of the window
+pub const RESPAWN_BUTTON_Y: f32 = -20.0;
+const BUTTON_WIDTH: f32 = 200.0;
+const BUTTON_HEIGHT: f32 = 20.0;
+
+/// Whether a position in GUI coordinates is over the respawn button of the death screen
+pub fn is_over_respawn_button(x: f32, y: f32) -> bool {
+    let (center_x, center_y) = (WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0 + RESPAWN_BUTTON_Y * GUI_SCALING);
+    (x - center_x).abs() <= BUTTON_WIDTH / 2.0 * GUI_SCALING
+        && (y - center_y).abs() <= BUTTON_HEIGHT / 2.0 * GUI_SCALING
+}
+
+/// Covers the whole window with a color, drawn with the color shader
+pub fn draw_overlay(overlay_vao: u32, color: [f32; 4], shader: &mut ShaderProgram) {
+    draw_rectangle(overlay_vao, (0.0, 0.0, WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32), color, shader);
+}
+
+/// Fills a rectangle of the window given as (left, bottom, width, height), drawn with the color shader
+pub fn draw_rectangle(overlay_vao: u32, (x, y, width, height): (f32, f32, f32, f32), color: [f32; 4], shader: &mut ShaderProgram) {
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+    let model_matrix = {
+        let translate_matrix = Matrix4::new_translation(&vec3(x + width / 2.0, y + height / 2.0, 0.0));
+        let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(width, height, 1.0));
+        translate_matrix * scale_matrix
+    };
+    shader.use_program();
+    shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+    shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    shader.set_uniform4f("color", &color);
+    gl_call!(gl::BindVertexArray(overlay_vao));
+    gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+}
+
+/// Draws the respawn button of the death screen, highlighted when the cursor is over it
+pub fn draw_respawn_button(button_vaos: &[u32; 2], (x, y): (f32, f32), shader: &mut ShaderProgram) {
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+    let model_matrix = {
+        let translate_matrix = Matrix4::new_translation(&vec3(
+            WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0 + RESPAWN_BUTTON_Y * GUI_SCALING, 0.0));
+        let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(BUTTON_WIDTH * GUI_SCALING, BUTTON_HEIGHT * GUI_SCALING, 1.0));
+        translate_matrix * scale_matrix
+    };
+    shader.use_program();
+    shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+    shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    shader.set_uniform1i("tex", 2);
+    let button_vao = if is_over_respawn_button(x, y) { button_vaos[1] } else { button_vaos[0] };
+    gl_call!(gl::BindVertexArray(button_vao));
+    gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
 }
 
 pub fn create_crosshair_vao() -> u32 {
@@ -98,30 +260,112 @@ pub fn create_block_outline_vao() -> u32 {
     outline_vao
 }
 
+/// One cube per destroy stage, each one textured with the layer of its stage
+/// The cube of a stage starts at vertex `stage * 36`
+pub fn create_block_crack_vao() -> u32 {
+    let mut crack_vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut crack_vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(crack_vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(crack_vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+    gl_call!(gl::VertexArrayAttribBinding(crack_vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(crack_vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(crack_vao, 1, 3 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+    gl_call!(gl::VertexArrayAttribBinding(crack_vao, 1, 0));
+
+    // Normals
+    gl_call!(gl::EnableVertexArrayAttrib(crack_vao, 2));
+    gl_call!(gl::VertexArrayAttribFormat(crack_vao, 2, 3 as i32, gl::FLOAT, gl::FALSE, 6 * std::mem::size_of::<f32>() as u32));
+    gl_call!(gl::VertexArrayAttribBinding(crack_vao, 2, 0));
+
+    let vbo_data = (0..DESTROY_STAGES)
+        .flat_map(|stage| centered_unit_cube(0.0, 0.0, 0.0, (stage, stage, stage, stage, stage, stage)))
+        .collect::<Vec<f32>>();
+    let mut crack_vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut crack_vbo));
+    gl_call!(gl::VertexArrayVertexBuffer(crack_vao, 0, crack_vbo, 0, (9 * std::mem::size_of::<f32>()) as i32));
+    gl_call!(gl::NamedBufferData(crack_vbo,
+                    (vbo_data.len() * std::mem::size_of::<f32>() as usize) as isize,
+                    vbo_data.as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+    crack_vao
+}
+
 pub fn create_widgets_texture() -> u32 {
-    let widgets_image = match image::open("textures/gui/widgets.png") {
-        Ok(img) => img,
-        Err(err) => panic!("Filename: {}, error: {}", "textures/gui/widgets.png", err.to_string())
-    };
-    match widgets_image.color() {
-        image::RGBA(8) => {}
-        _ => panic!("Texture format not supported")
-    };
+    create_gui_texture("textures/gui/widgets.png")
+}
 
-    // quad((0.0, 0.0, 0.0, 0.0)).chunks(3).take(2).collect::<Vec2>();
+// Every digit is 5x7 pixels with a 1 pixel shadow, in a 6x8 cell
+const DIGIT_WIDTH: f32 = 6.0;
+const DIGIT_HEIGHT: f32 = 8.0;
+const DIGITS_TEXTURE_WIDTH: f32 = 64.0;
 
-    // Upload the image to the GPU
-    let mut widgets_texture = 0;
-    gl_call!(gl::CreateTextures(gl::TEXTURE_2D, 1, &mut widgets_texture));
-    gl_call!(gl::TextureParameteri(widgets_texture, gl::TEXTURE_MIN_FILTER, gl::NEAREST_MIPMAP_NEAREST as i32));
-    gl_call!(gl::TextureParameteri(widgets_texture, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32));
-    gl_call!(gl::TextureStorage2D(widgets_texture, 1, gl::RGBA8, widgets_image.width() as i32, widgets_image.height() as i32));
-    gl_call!(gl::TextureSubImage2D(
-            widgets_texture, 0,
-            0, 0, widgets_image.width() as i32, widgets_image.height() as i32,
-            gl::RGBA, gl::UNSIGNED_BYTE,
-            widgets_image.raw_pixels().as_ptr() as *mut c_void));
-    widgets_texture
+pub fn create_digits_texture() -> u32 {
+    create_gui_texture("textures/gui/digits.png")
+}
+
+/// One VAO per digit of the digits texture
+pub fn create_digit_vaos() -> [u32; 10] {
+    let mut digit_vaos = [0; 10];
+    for (digit, digit_vao) in digit_vaos.iter_mut().enumerate() {
+        gl_call!(gl::CreateVertexArrays(1, digit_vao));
+        let digit_vao = *digit_vao;
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(digit_vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(digit_vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+        gl_call!(gl::VertexArrayAttribBinding(digit_vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(digit_vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(digit_vao, 1, 2 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+        gl_call!(gl::VertexArrayAttribBinding(digit_vao, 1, 0));
+
+        let mut digit_vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut digit_vbo));
+
+        let u = digit as f32 * DIGIT_WIDTH / DIGITS_TEXTURE_WIDTH;
+        gl_call!(gl::VertexArrayVertexBuffer(digit_vao, 0, digit_vbo, 0, (5 * std::mem::size_of::<f32>()) as i32));
+        gl_call!(gl::NamedBufferData(digit_vbo,
+                    (30 * std::mem::size_of::<f32>() as usize) as isize,
+                    quad((u, 0.0, u + DIGIT_WIDTH / DIGITS_TEXTURE_WIDTH, 1.0)).as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+    }
+    digit_vaos
+}
+
+/// Draws the amount of items of a stack, right aligned on `right` with its bottom on `bottom`
+/// Nothing is drawn for single items
+pub fn draw_item_count(digit_vaos: &[u32; 10], amount: u32, right: f32, bottom: f32, shader: &mut ShaderProgram) {
+    if amount <= 1 {
+        return;
+    }
+    let projection_matrix = nalgebra_glm::ortho(
+        0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+
+    shader.use_program();
+    shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+    shader.set_uniform1i("tex", 3);
+
+    let mut amount = amount;
+    let mut x = right - DIGIT_WIDTH * GUI_SCALING / 2.0;
+    while amount > 0 {
+        let model_matrix = {
+            let translate_matrix = Matrix4::new_translation(&vec3(
+                x, bottom + DIGIT_HEIGHT * GUI_SCALING / 2.0, 0.0));
+            let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(DIGIT_WIDTH * GUI_SCALING, DIGIT_HEIGHT * GUI_SCALING, 1.0));
+            translate_matrix * scale_matrix
+        };
+        shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+        gl_call!(gl::BindVertexArray(digit_vaos[(amount % 10) as usize]));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+
+        amount /= 10;
+        x -= DIGIT_WIDTH * GUI_SCALING;
+    }
 }
 
 pub fn create_hotbar_vao() -> u32 {
@@ -174,4 +418,30 @@ pub fn create_hotbar_selection_vao() -> u32 {
                     // quad((0.0, 0.0, 1.0, 1.0)).as_ptr() as *const c_void,
                     gl::STATIC_DRAW));
     hotbar_selection_vao
-}
\ No newline at end of file
+}
+
+/// A single slot, cut from the left end of the hotbar
+pub fn create_slot_vao() -> u32 {
+    let mut slot_vao = 0;
+    gl_call!(gl::CreateVertexArrays(1, &mut slot_vao));
+
+    // Position
+    gl_call!(gl::EnableVertexArrayAttrib(slot_vao, 0));
+    gl_call!(gl::VertexArrayAttribFormat(slot_vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+    gl_call!(gl::VertexArrayAttribBinding(slot_vao, 0, 0));
+
+    // Texture coords
+    gl_call!(gl::EnableVertexArrayAttrib(slot_vao, 1));
+    gl_call!(gl::VertexArrayAttribFormat(slot_vao, 1, 2 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+    gl_call!(gl::VertexArrayAttribBinding(slot_vao, 1, 0));
+
+    let mut slot_vbo = 0;
+    gl_call!(gl::CreateBuffers(1, &mut slot_vbo));
+
+    gl_call!(gl::VertexArrayVertexBuffer(slot_vao, 0, slot_vbo, 0, (5 * std::mem::size_of::<f32>()) as i32));
+    gl_call!(gl::NamedBufferData(slot_vbo,
+                    (30 * std::mem::size_of::<f32>() as usize) as isize,
+                    quad((0.0, 0.0, 22.0 / 256.0, 22.0 / 256.0)).as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+    slot_vao
+}
@@ -0,0 +1,197 @@
+use std::time::Instant;
+
+use nalgebra::Matrix4;
+use nalgebra_glm::{Mat4, vec3};
+
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{DAMAGE_EXHAUSTION, EXHAUSTION_PER_HUNGER_POINT, GRAVITY, GUI_SCALING, HEALTH_TICK_INTERVAL, MAX_HEALTH, MAX_HUNGER, REGENERATION_EXHAUSTION, REGENERATION_HUNGER, SAFE_FALL_DISTANCE, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::gui::create_icon_vao;
+use crate::shader_compilation::ShaderProgram;
+
+/// Health and hunger of a player or a mob, in half hearts and half drumsticks
+pub struct Health {
+    pub health: u32,
+    pub hunger: u32,
+    // Used up before the hunger, not shown on the screen
+    pub saturation: f32,
+    pub exhaustion: f32,
+    // The player doesn't move until they have been put on the ground at the spawn point
+    pub is_waiting_for_spawn: bool,
+    // Last time health was regenerated or lost to starvation
+    pub(crate) last_health_tick: Instant,
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Health {
+            health: MAX_HEALTH,
+            hunger: MAX_HUNGER,
+            saturation: 5.0,
+            exhaustion: 0.0,
+            is_waiting_for_spawn: true,
+            last_health_tick: Instant::now(),
+        }
+    }
+
+    /// Health of a mob, which doesn't get hungry and is already in the world
+    pub fn with_max_health(health: u32) -> Self {
+        Health {
+            health,
+            is_waiting_for_spawn: false,
+            ..Health::new()
+        }
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.health == 0
+    }
+
+    pub fn damage(&mut self, amount: u32) {
+        if amount == 0 {
+            return;
+        }
+        self.health = self.health.saturating_sub(amount);
+        self.add_exhaustion(DAMAGE_EXHAUSTION);
+        info!("Took {} damage, health: {}", amount, self.health);
+    }
+
+    pub fn add_exhaustion(&mut self, exhaustion: f32) {
+        self.exhaustion += exhaustion;
+        while self.exhaustion >= EXHAUSTION_PER_HUNGER_POINT {
+            self.exhaustion -= EXHAUSTION_PER_HUNGER_POINT;
+            if self.saturation > 0.0 {
+                self.saturation = (self.saturation - 1.0).max(0.0);
+            } else {
+                self.hunger = self.hunger.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn can_eat(&self) -> bool {
+        self.hunger < MAX_HUNGER
+    }
+
+    /// The saturation can't go above the hunger
+    pub fn eat(&mut self, (hunger, saturation): (u32, f32)) {
+        self.hunger = (self.hunger + hunger).min(MAX_HUNGER);
+        self.saturation = (self.saturation + saturation).min(self.hunger as f32);
+    }
+
+    /// Regenerates health while the player is well fed, or hurts them while they are starving
+    pub fn tick(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.last_health_tick) < *HEALTH_TICK_INTERVAL {
+            return;
+        }
+        self.last_health_tick = now;
+
+        if self.hunger >= REGENERATION_HUNGER && self.health < MAX_HEALTH {
+            self.health += 1;
+            self.add_exhaustion(REGENERATION_EXHAUSTION);
+        } else if self.hunger == 0 {
+            self.damage(1);
+        }
+    }
+
+    /// Full health and hunger, waiting to be put back at the spawn point
+    pub fn respawn(&mut self) {
+        *self = Health::new();
+    }
+}
+
+/// Damage taken when hitting the ground at `landing_speed`
+/// The height of the fall is found back from the speed, as the player accelerates with the gravity
+pub fn fall_damage(landing_speed: f32) -> u32 {
+    let fall_distance = landing_speed * landing_speed / (2.0 * -GRAVITY);
+    (fall_distance - SAFE_FALL_DISTANCE).ceil().max(0.0) as u32
+}
+
+/// Height at which the player stands on the highest block of a column, or floats on the sea
+/// Returns None if the column isn't loaded yet
+pub fn spawn_height(chunk_manager: &ChunkManager, x: i32, z: i32) -> Option<f32> {
+    chunk_manager.get_block(x, 0, z)?;
+    let y = (0..256).rev()
+        .find(|&y| chunk_manager.is_solid_block_at(x, y, z)
+            || chunk_manager.get_block(x, y, z).filter(|block| block.is_fluid()).is_some())
+        .map_or(0, |y| y + 1);
+    Some(y as f32)
+}
+
+/// The icons of the health and hunger bars, cut from the icons texture
+pub struct StatusBarIcons {
+    heart_container: u32,
+    full_heart: u32,
+    half_heart: u32,
+    hunger_container: u32,
+    full_hunger: u32,
+    half_hunger: u32,
+}
+
+// Every icon is 9x9 pixels, and overlaps the next one by a pixel
+const ICON_SIZE: f32 = 9.0;
+const ICON_SPACING: f32 = 8.0;
+
+impl StatusBarIcons {
+    pub fn new() -> Self {
+        StatusBarIcons {
+            heart_container: create_icon_vao(16.0, 0.0, ICON_SIZE, ICON_SIZE),
+            full_heart: create_icon_vao(52.0, 0.0, ICON_SIZE, ICON_SIZE),
+            half_heart: create_icon_vao(61.0, 0.0, ICON_SIZE, ICON_SIZE),
+            hunger_container: create_icon_vao(16.0, 27.0, ICON_SIZE, ICON_SIZE),
+            full_hunger: create_icon_vao(52.0, 27.0, ICON_SIZE, ICON_SIZE),
+            half_hunger: create_icon_vao(61.0, 27.0, ICON_SIZE, ICON_SIZE),
+        }
+    }
+}
+
+impl Health {
+    /// Draws the hearts above the left half of the hotbar, and the hunger above its right half
+    /// The hunger bar is filled from the right
+    pub fn draw_status_bars(&self, icons: &StatusBarIcons, shader: &mut ShaderProgram) {
+        let projection_matrix = nalgebra_glm::ortho(
+            0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+
+        shader.use_program();
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        shader.set_uniform1i("tex", 1);
+
+        let y = (22.0 + 2.0 + ICON_SIZE / 2.0) * GUI_SCALING;
+        let hotbar_left = WINDOW_WIDTH as f32 / 2.0 - 91.0 * GUI_SCALING;
+        let hotbar_right = WINDOW_WIDTH as f32 / 2.0 + 91.0 * GUI_SCALING;
+        let draw_icon = |vao: u32, x: f32, shader: &mut ShaderProgram| {
+            let model_matrix = {
+                let translate_matrix = Matrix4::new_translation(&vec3(x, y, 0.0));
+                let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(ICON_SIZE * GUI_SCALING, ICON_SIZE * GUI_SCALING, 1.0));
+                translate_matrix * scale_matrix
+            };
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+            gl_call!(gl::BindVertexArray(vao));
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        };
+
+        for i in 0..MAX_HEALTH / 2 {
+            let x = hotbar_left + (i as f32 * ICON_SPACING + ICON_SIZE / 2.0) * GUI_SCALING;
+            draw_icon(icons.heart_container, x, shader);
+            if self.health >= 2 * i + 2 {
+                draw_icon(icons.full_heart, x, shader);
+            } else if self.health == 2 * i + 1 {
+                draw_icon(icons.half_heart, x, shader);
+            }
+        }
+
+        for i in 0..MAX_HUNGER / 2 {
+            let x = hotbar_right - (i as f32 * ICON_SPACING + ICON_SIZE / 2.0) * GUI_SCALING;
+            draw_icon(icons.hunger_container, x, shader);
+            if self.hunger >= 2 * i + 2 {
+                draw_icon(icons.full_hunger, x, shader);
+            } else if self.hunger == 2 * i + 1 {
+                draw_icon(icons.half_hunger, x, shader);
+            }
+        }
+    }
+}
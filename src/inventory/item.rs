@@ -1,42 +1,238 @@
+use std::collections::HashMap;
 use std::os::raw::c_void;
 use std::ptr::null;
 
+use image::GenericImageView;
 use nalgebra::Matrix4;
 use nalgebra_glm::{Mat4, pi, vec3};
 
 use crate::chunk::BlockID;
 use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
 use crate::shader_compilation::ShaderProgram;
-use crate::shapes::centered_unit_cube;
-use crate::types::TexturePack;
+use crate::shapes::{centered_unit_cube, extruded_item_sprite, item_sprite_quad};
+use crate::texture_pack::read_image;
+use crate::types::{ItemIcons, TextureLayer, TexturePack};
 
-#[derive(Copy, Clone)]
+/// Anything that can be held in the inventory
+/// Blocks are drawn as cubes, the other items, the plants, levers and redstone as flat icons
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum Item {
+    Block(BlockID),
+    Stick,
+    WoodenPickaxe,
+    StonePickaxe,
+    Apple,
+}
+
+impl Item {
+    /// Every item which is not a block
+    pub const NON_BLOCK_ITEMS: [Item; 4] = [
+        Item::Stick,
+        Item::WoodenPickaxe,
+        Item::StonePickaxe,
+        Item::Apple,
+    ];
+
+    /// The block placed when using the item
+    pub fn block(&self) -> Option<BlockID> {
+        match self {
+            &Item::Block(block) => Some(block),
+            _ => None,
+        }
+    }
+
+    /// Maximum number of items in a single stack of the inventory
+    pub fn max_stack_size(&self) -> u32 {
+        match self {
+            Item::Block(block) => block.max_stack_size(),
+            Item::WoodenPickaxe |
+            Item::StonePickaxe => 1,
+            _ => 64,
+        }
+    }
+
+    pub fn is_pickaxe(&self) -> bool {
+        match self {
+            Item::WoodenPickaxe |
+            Item::StonePickaxe => true,
+            _ => false,
+        }
+    }
+
+    /// Hunger and saturation restored by eating the item, if it can be eaten
+    pub fn food_value(&self) -> Option<(u32, f32)> {
+        match self {
+            Item::Apple => Some((4, 2.4)),
+            _ => None,
+        }
+    }
+
+    /// How many times faster than by hand a block is broken with the item
+    pub fn mining_speed(&self, block: BlockID) -> f32 {
+        match self {
+            Item::WoodenPickaxe if block.requires_pickaxe() => 2.0,
+            Item::StonePickaxe if block.requires_pickaxe() => 4.0,
+            _ => 1.0,
+        }
+    }
+
+    /// Name of every item in the data files and in commands
+    pub const NAMES: [(&'static str, Item); 24] = [
+        ("dirt", Item::Block(BlockID::Dirt)),
+        ("grass_block", Item::Block(BlockID::GrassBlock)),
+        ("stone", Item::Block(BlockID::Stone)),
+        ("cobblestone", Item::Block(BlockID::Cobblestone)),
+        ("bedrock", Item::Block(BlockID::Bedrock)),
+        ("obsidian", Item::Block(BlockID::Obsidian)),
+        ("oak_log", Item::Block(BlockID::OakLog)),
+        ("oak_leaves", Item::Block(BlockID::OakLeaves)),
+        ("oak_planks", Item::Block(BlockID::OakPlanks)),
+        ("glass", Item::Block(BlockID::Glass)),
+        ("crafting_table", Item::Block(BlockID::CraftingTable)),
+        ("snow", Item::Block(BlockID::Snow)),
+        ("oak_sapling", Item::Block(BlockID::OakSapling)),
+        ("sand", Item::Block(BlockID::Sand)),
+        ("gravel", Item::Block(BlockID::Gravel)),
+        ("lever", Item::Block(BlockID::Lever)),
+        ("stone_button", Item::Block(BlockID::StoneButton)),
+        ("redstone", Item::Block(BlockID::RedstoneWire)),
+        ("repeater", Item::Block(BlockID::Repeater)),
+        ("redstone_lamp", Item::Block(BlockID::RedstoneLamp)),
+        ("stick", Item::Stick),
+        ("wooden_pickaxe", Item::WoodenPickaxe),
+        ("stone_pickaxe", Item::StonePickaxe),
+        ("apple", Item::Apple),
+    ];
+
+    /// Finds an item by the name used in the data files, like "oak_planks" or "stick"
+    pub fn from_name(name: &str) -> Option<Item> {
+        Self::NAMES.iter()
+            .find(|(item_name, _)| *item_name == name)
+            .map(|&(_, item)| item)
+    }
+
+    /// The debug blocks don't have a name
+    pub fn name(&self) -> Option<&'static str> {
+        Self::NAMES.iter()
+            .find(|(_, item)| item == self)
+            .map(|&(name, _)| name)
+    }
+
+    /// Path of the flat icon of the item, the blocks drawn as cubes don't have one
+    pub fn icon_path(&self) -> Option<&'static str> {
+        match self {
+            Item::Block(BlockID::OakSapling) => Some("textures/blocks/oak_sapling.png"),
+            Item::Block(BlockID::Lever) => Some("textures/blocks/lever.png"),
+            Item::Block(BlockID::RedstoneWire) => Some("textures/blocks/redstone_wire.png"),
+            Item::Block(_) => None,
+            Item::Stick => Some("textures/items/stick.png"),
+            Item::WoodenPickaxe => Some("textures/items/wooden_pickaxe.png"),
+            Item::StonePickaxe => Some("textures/items/stone_pickaxe.png"),
+            Item::Apple => Some("textures/items/apple.png"),
+        }
+    }
+
+    /// Whether the item is drawn as a flat icon rather than a cube
+    pub fn is_flat(&self) -> bool {
+        match self {
+            Item::Block(block) => block.is_cross_shaped() || *block == BlockID::RedstoneWire,
+            _ => true,
+        }
+    }
+
+    /// Layer of the array texture of the icon of a flat item, the flat blocks use their block texture
+    fn icon_layer(&self, texture_pack: &TexturePack, item_icons: &ItemIcons) -> TextureLayer {
+        match self {
+            Item::Block(block) => texture_pack.get(block).unwrap().get_uv_of_every_face().0,
+            item => *item_icons.get(item).unwrap(),
+        }
+    }
+
+    /// Vertices of the item in the world, held by the player or lying on the ground, centered on the origin
+    /// Blocks are cubes, the other items are their icon extruded to a thickness of one pixel
+    pub fn model_vertices(&self, texture_pack: &TexturePack, item_icons: &ItemIcons) -> Vec<f32> {
+        match self {
+            Item::Block(block) if !self.is_flat() => centered_unit_cube(
+                -0.5, -0.5, -0.5,
+                texture_pack.get(block).unwrap().get_uv_of_every_face()),
+            item => {
+                let icon = read_image(item.icon_path().unwrap());
+                extruded_item_sprite(
+                    item.icon_layer(texture_pack, item_icons),
+                    &|x, y| icon.get_pixel(x, y)[3] > 0)
+            }
+        }
+    }
+}
+
+impl From<BlockID> for Item {
+    fn from(block: BlockID) -> Self {
+        Item::Block(block)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct ItemStack {
-    pub item: BlockID,
+    pub item: Item,
     pub amount: u32,
-    pub(crate) item_render: ItemRender,
 }
 
 impl ItemStack {
-    pub fn new(amount: u32, block: BlockID) -> Self {
+    pub fn new(amount: u32, item: Item) -> Self {
         ItemStack {
-            item: block,
+            item,
             amount,
-            item_render: ItemRender::new()
         }
     }
 
-    pub fn update_if_dirty(&mut self, texture_pack: &TexturePack) {
-        self.item_render.update_vbo_if_dirty(self.item, &texture_pack);
+    /// Maximum amount of items of this stack
+    pub fn max_amount(&self) -> u32 {
+        self.item.max_stack_size()
+    }
+
+    /// Whether both stacks can be merged into one
+    pub fn is_same_item(&self, other: &ItemStack) -> bool {
+        self.item == other.item
+    }
+
+    /// Moves as many items as possible from `other` to this stack, up to the stack limit
+    /// Returns the number of items moved
+    pub fn merge_from(&mut self, other: &mut ItemStack, amount: u32) -> u32 {
+        if !self.is_same_item(other) {
+            return 0;
+        }
+        let moved = amount
+            .min(other.amount)
+            .min(self.max_amount().saturating_sub(self.amount));
+        self.amount += moved;
+        other.amount -= moved;
+        moved
+    }
+}
+
+/// The GUI renders of every item which has been drawn so far
+/// They are shared by all of the stacks of the same item, so splitting or moving stacks doesn't touch the GPU
+#[derive(Default)]
+pub struct ItemRenders {
+    renders: HashMap<Item, ItemRender>,
+}
+
+impl ItemRenders {
+    pub fn get(&mut self, item: Item, texture_pack: &TexturePack, item_icons: &ItemIcons) -> &ItemRender {
+        self.renders.entry(item).or_insert_with(|| {
+            let mut item_render = ItemRender::new();
+            item_render.update_vbo(item, &texture_pack, &item_icons);
+            item_render
+        })
     }
 }
 
-#[derive(Copy, Clone)]
 pub struct ItemRender {
     vao: u32,
     vbo: u32,
-    // This is dirty when the VBO needs to be updated (at creation and when changing the block)
-    pub(crate) dirty: bool,
+    vertices: i32,
+    // Flat items are drawn as a sprite instead of a rotated cube
+    is_flat: bool,
     projection_matrix: Mat4,
 }
 
@@ -76,22 +272,21 @@ impl ItemRender {
         ItemRender {
             vao,
             vbo,
-            dirty: true,
+            vertices: 0,
+            is_flat: false,
             projection_matrix
         }
     }
 
-    pub fn update_vbo_if_dirty(&mut self, item: BlockID, texture_pack: &TexturePack) {
-        if self.dirty {
-            self.update_vbo(item, &texture_pack);
-            self.dirty = false;
-        }
-    }
-
-    pub fn update_vbo(&mut self, item: BlockID, texture_pack: &TexturePack) {
-        let vbo_data = centered_unit_cube(
-            -0.5, -0.5, -0.5,
-            texture_pack.get(&item).unwrap().get_uv_of_every_face());
+    pub fn update_vbo(&mut self, item: Item, texture_pack: &TexturePack, item_icons: &ItemIcons) {
+        let vbo_data = match item {
+            Item::Block(block) if !item.is_flat() => centered_unit_cube(
+                -0.5, -0.5, -0.5,
+                texture_pack.get(&block).unwrap().get_uv_of_every_face()),
+            item => item_sprite_quad(item.icon_layer(texture_pack, item_icons)),
+        };
+        self.vertices = (vbo_data.len() / 9) as i32;
+        self.is_flat = item.is_flat();
 
         gl_call!(gl::NamedBufferSubData(self.vbo,
                     0,
@@ -103,13 +298,18 @@ impl ItemRender {
         let model_matrix = {
             let translate_matrix = Matrix4::new_translation(&vec3(
                 x, y, 1.0));
-            let rotate_matrix = {
-                let rotate_y = Matrix4::from_euler_angles(0.0, pi::<f32>() / 4.0, 0.0); // 45°
-                let rotate_x = Matrix4::from_euler_angles(pi::<f32>() / 6.0, 0.0, 0.0); // 30°
-                rotate_x * rotate_y
-            };
-            let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&(GUI_SCALING * vec3(10.0, 10.0, 10.0)));
-            translate_matrix * rotate_matrix * scale_matrix
+            if self.is_flat {
+                let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&(GUI_SCALING * vec3(16.0, 16.0, 1.0)));
+                translate_matrix * scale_matrix
+            } else {
+                let rotate_matrix = {
+                    let rotate_y = Matrix4::from_euler_angles(0.0, pi::<f32>() / 4.0, 0.0); // 45°
+                    let rotate_x = Matrix4::from_euler_angles(pi::<f32>() / 6.0, 0.0, 0.0); // 30°
+                    rotate_x * rotate_y
+                };
+                let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&(GUI_SCALING * vec3(10.0, 10.0, 10.0)));
+                translate_matrix * rotate_matrix * scale_matrix
+            }
         };
 
         shader.use_program();
@@ -118,6 +318,6 @@ impl ItemRender {
         shader.set_uniform1i("tex", 0);
 
         gl_call!(gl::BindVertexArray(self.vao));
-        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 36 as i32));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices));
     }
 }
\ No newline at end of file
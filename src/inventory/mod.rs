@@ -3,18 +3,29 @@ use nalgebra_glm::{Mat4, vec3};
 
 use crate::chunk::BlockID;
 use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
-use crate::inventory::item::ItemStack;
+use crate::crafting::{CraftingGrid, RecipeRegistry};
+use crate::gui::draw_item_count;
+use crate::inventory::item::{Item, ItemRenders, ItemStack};
+use crate::inventory::screen::ScreenSlot;
 use crate::shader_compilation::ShaderProgram;
-use crate::types::TexturePack;
+use crate::types::{ItemIcons, TexturePack};
+use std::ops::Range;
 
 pub mod item;
+pub mod screen;
 
-const INVENTORY_SIZE: usize = 36;
-const HOTBAR_SIZE: usize = 9;
+pub const INVENTORY_SIZE: usize = 36;
+pub const HOTBAR_SIZE: usize = 9;
+// Size of the crafting grid of the inventory screen, a crafting table has a bigger one
+pub const INVENTORY_CRAFTING_GRID_SIZE: usize = 2;
 
 pub struct Inventory {
+    // The first HOTBAR_SIZE slots are the hotbar, the others are the main inventory
     pub slots: [Option<ItemStack>; INVENTORY_SIZE],
     pub selected_hotbar_slot: usize,
+    // Stack held by the mouse cursor in the inventory screen
+    pub cursor_stack: Option<ItemStack>,
+    pub crafting_grid: CraftingGrid,
 }
 
 impl Default for Inventory {
@@ -28,22 +39,28 @@ impl Inventory {
         Inventory {
             slots: {
                 let mut slots = [None; INVENTORY_SIZE];
-                slots[0] = Some(ItemStack::new(1, BlockID::Dirt));
-                slots[1] = Some(ItemStack::new(1, BlockID::GrassBlock));
-                slots[2] = Some(ItemStack::new(1, BlockID::Cobblestone));
-                slots[3] = Some(ItemStack::new(1, BlockID::OakLog));
-                slots[4] = Some(ItemStack::new(1, BlockID::OakPlanks));
-                slots[5] = Some(ItemStack::new(1, BlockID::OakLeaves));
-                slots[6] = Some(ItemStack::new(1, BlockID::Glass));
-                slots[7] = Some(ItemStack::new(1, BlockID::Urss));
-                slots[8] = Some(ItemStack::new(1, BlockID::Hitler));
+                slots[0] = Some(ItemStack::new(64, Item::Block(BlockID::Dirt)));
+                slots[1] = Some(ItemStack::new(64, Item::Block(BlockID::GrassBlock)));
+                slots[2] = Some(ItemStack::new(64, Item::Block(BlockID::Cobblestone)));
+                slots[3] = Some(ItemStack::new(64, Item::Block(BlockID::OakLog)));
+                slots[4] = Some(ItemStack::new(64, Item::Block(BlockID::OakPlanks)));
+                slots[5] = Some(ItemStack::new(64, Item::Block(BlockID::OakLeaves)));
+                slots[6] = Some(ItemStack::new(64, Item::Block(BlockID::Glass)));
+                slots[7] = Some(ItemStack::new(64, Item::Block(BlockID::Urss)));
+                slots[8] = Some(ItemStack::new(64, Item::Block(BlockID::Hitler)));
+                slots[9] = Some(ItemStack::new(64, Item::Stick));
+                slots[10] = Some(ItemStack::new(1, Item::WoodenPickaxe));
+                slots[11] = Some(ItemStack::new(1, Item::StonePickaxe));
+                slots[12] = Some(ItemStack::new(16, Item::Apple));
                 slots
             },
             selected_hotbar_slot: 0,
+            cursor_stack: None,
+            crafting_grid: CraftingGrid::new(INVENTORY_CRAFTING_GRID_SIZE),
         }
     }
 
-    pub fn get_selected_item(&self) -> Option<BlockID> {
+    pub fn get_selected_item(&self) -> Option<Item> {
         self.slots[self.selected_hotbar_slot].map(|item_stack| item_stack.item)
     }
 
@@ -66,12 +83,199 @@ impl Inventory {
         }
     }
 
-    pub fn update_dirty_items(&mut self, texture_pack: &TexturePack) {
-        for item_stack in self.slots.iter_mut() {
-            if let Some(item_stack) = item_stack {
-                item_stack.update_if_dirty(&texture_pack);
+    /// Removes one item from the selected hotbar slot, to place it in the world
+    /// Returns the block if the selected item is one, items which aren't blocks are left untouched
+    pub fn consume_selected_block(&mut self) -> Option<BlockID> {
+        let block = self.get_selected_item()?.block()?;
+        self.remove_from_slot(self.selected_hotbar_slot, 1)?;
+        Some(block)
+    }
+
+    /// Adds a stack to the inventory, merging it with the stacks of the same item first
+    /// (hotbar first), then filling the empty slots
+    /// The items that didn't fit are returned as an error
+    pub fn try_insert(&mut self, stack: ItemStack) -> Result<(), ItemStack> {
+        match self.insert_into_range(stack, 0..INVENTORY_SIZE) {
+            Some(overflow) => Err(overflow),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes `amount` items of a kind from the inventory, starting with the last slots
+    /// The number of items which were missing is returned as an error, nothing is removed in that case
+    pub fn remove(&mut self, item: Item, amount: u32) -> Result<(), u32> {
+        let available: u32 = self.slots.iter()
+            .filter_map(|slot| slot.as_ref())
+            .filter(|stack| stack.item == item)
+            .map(|stack| stack.amount)
+            .sum();
+        if available < amount {
+            return Err(amount - available);
+        }
+
+        let mut remaining = amount;
+        for index in (0..INVENTORY_SIZE).rev() {
+            if remaining == 0 {
+                break;
+            }
+            if self.slots[index].map_or(false, |stack| stack.item == item) {
+                let removed = self.remove_from_slot(index, remaining).unwrap();
+                remaining -= removed.amount;
             }
         }
+        Ok(())
+    }
+
+    /// Takes up to `amount` items out of a slot
+    pub fn remove_from_slot(&mut self, index: usize, amount: u32) -> Option<ItemStack> {
+        let slot = &mut self.slots[index];
+        let stack = slot.as_mut()?;
+        let removed = amount.min(stack.amount);
+        stack.amount -= removed;
+        let item = stack.item;
+        if stack.amount == 0 {
+            *slot = None;
+        }
+        if removed == 0 {
+            None
+        } else {
+            Some(ItemStack::new(removed, item))
+        }
+    }
+
+    /// Picks up the stack of a slot, puts down the stack held by the cursor,
+    /// or swaps both of them if they are different items
+    /// Stacks of the same item are merged up to the stack limit, the rest stays on the cursor
+    /// Clicking on the result of the crafting grid crafts it
+    pub fn left_click_slot(&mut self, slot: ScreenSlot, recipes: &RecipeRegistry) {
+        match slot {
+            ScreenSlot::Inventory(index) => left_click_stack(&mut self.slots[index], &mut self.cursor_stack),
+            ScreenSlot::CraftingGrid(index) => left_click_stack(&mut self.crafting_grid.slots[index], &mut self.cursor_stack),
+            ScreenSlot::CraftingResult => self.take_crafting_result(),
+        }
+        self.clear_empty_cursor_stack();
+        self.crafting_grid.update_result(recipes);
+    }
+
+    /// Picks up half of the stack of a slot (rounded up) if the cursor is empty,
+    /// otherwise puts down a single item of the stack held by the cursor
+    pub fn right_click_slot(&mut self, slot: ScreenSlot, recipes: &RecipeRegistry) {
+        match slot {
+            ScreenSlot::Inventory(index) => right_click_stack(&mut self.slots[index], &mut self.cursor_stack),
+            ScreenSlot::CraftingGrid(index) => right_click_stack(&mut self.crafting_grid.slots[index], &mut self.cursor_stack),
+            ScreenSlot::CraftingResult => self.take_crafting_result(),
+        }
+        self.clear_empty_cursor_stack();
+        self.crafting_grid.update_result(recipes);
+    }
+
+    /// Puts the result of the crafting grid on the cursor, if it can hold it, and uses the ingredients
+    fn take_crafting_result(&mut self) {
+        let result = match self.crafting_grid.result {
+            Some(result) => result,
+            None => return,
+        };
+        match self.cursor_stack.as_mut() {
+            None => self.cursor_stack = Some(result),
+            Some(cursor_stack) if cursor_stack.is_same_item(&result)
+                && cursor_stack.amount + result.amount <= cursor_stack.max_amount() => {
+                cursor_stack.amount += result.amount;
+            }
+            _ => return,
+        }
+        self.crafting_grid.consume_ingredients();
+        self.crafting_grid.result = None;
+    }
+
+    fn clear_empty_cursor_stack(&mut self) {
+        if self.cursor_stack.map_or(false, |cursor_stack| cursor_stack.amount == 0) {
+            self.cursor_stack = None;
+        }
+    }
+
+    /// Moves the stack of a slot from the hotbar to the main inventory or the other way around
+    /// Stacks of the crafting grid go back to the inventory, and the result is crafted
+    /// as many times as possible straight into the inventory
+    pub fn shift_click_slot(&mut self, slot: ScreenSlot, recipes: &RecipeRegistry) {
+        match slot {
+            ScreenSlot::Inventory(index) => {
+                let destination = if index < HOTBAR_SIZE {
+                    HOTBAR_SIZE..INVENTORY_SIZE
+                } else {
+                    0..HOTBAR_SIZE
+                };
+                if let Some(stack) = self.slots[index].take() {
+                    self.slots[index] = self.insert_into_range(stack, destination);
+                }
+            }
+            ScreenSlot::CraftingGrid(index) => {
+                if let Some(stack) = self.crafting_grid.slots[index].take() {
+                    self.crafting_grid.slots[index] = self.insert_into_range(stack, 0..INVENTORY_SIZE);
+                }
+            }
+            ScreenSlot::CraftingResult => {
+                while let Some(result) = self.crafting_grid.result {
+                    // Only craft if the whole result fits
+                    let slots = self.slots;
+                    if self.insert_into_range(result, 0..INVENTORY_SIZE).is_some() {
+                        self.slots = slots;
+                        break;
+                    }
+                    self.crafting_grid.consume_ingredients();
+                    self.crafting_grid.update_result(recipes);
+                }
+            }
+        }
+        self.crafting_grid.update_result(recipes);
+    }
+
+    /// Empties the crafting grid into the inventory and gives it a new size
+    /// Returns what didn't fit in the inventory
+    pub fn return_crafting_grid(&mut self, size: usize) -> Vec<ItemStack> {
+        self.crafting_grid.resize(size)
+            .into_iter()
+            .filter_map(|stack| self.insert_into_range(stack, 0..INVENTORY_SIZE))
+            .collect()
+    }
+
+    /// Empties the inventory, the crafting grid and the cursor
+    /// Returns every stack which was in them
+    pub fn take_all_stacks(&mut self) -> Vec<ItemStack> {
+        let mut stacks = self.slots.iter_mut()
+            .filter_map(|slot| slot.take())
+            .collect::<Vec<_>>();
+        stacks.extend(self.crafting_grid.resize(self.crafting_grid.size));
+        stacks.extend(self.cursor_stack.take());
+        stacks
+    }
+
+    /// Puts the stack held by the cursor back into the inventory
+    /// Returns what is left of it if there is no room
+    pub fn return_cursor_stack(&mut self) -> Option<ItemStack> {
+        let stack = self.cursor_stack.take()?;
+        self.insert_into_range(stack, 0..INVENTORY_SIZE)
+    }
+
+    /// Merges the stack with the stacks of the same item which aren't full,
+    /// then puts the rest in the first empty slots of the range
+    /// Returns what is left of the stack if it didn't fit
+    fn insert_into_range(&mut self, mut stack: ItemStack, range: Range<usize>) -> Option<ItemStack> {
+        for slot_stack in self.slots[range.clone()].iter_mut().filter_map(|slot| slot.as_mut()) {
+            let amount = stack.amount;
+            slot_stack.merge_from(&mut stack, amount);
+            if stack.amount == 0 {
+                return None;
+            }
+        }
+        for slot in self.slots[range].iter_mut().filter(|slot| slot.is_none()) {
+            let amount = stack.amount.min(stack.max_amount());
+            *slot = Some(ItemStack::new(amount, stack.item));
+            stack.amount -= amount;
+            if stack.amount == 0 {
+                return None;
+            }
+        }
+        Some(stack)
     }
 
     pub fn draw_hotbar(&self, vao: u32, shader: &mut ShaderProgram) {
@@ -115,18 +319,260 @@ impl Inventory {
         gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
     }
 
-    pub fn draw_hotbar_items(&self, shader: &mut ShaderProgram) {
+    pub fn draw_hotbar_item_counts(&self, digit_vaos: &[u32; 10], shader: &mut ShaderProgram) {
+        let interslot_spacing = 20.0;
+        let hotbar_left_margin = WINDOW_WIDTH as f32 / 2.0 - 4.0 * interslot_spacing * GUI_SCALING;
+
+        for (x, slot) in self.slots[..HOTBAR_SIZE].iter().enumerate() {
+            if let Some(slot) = slot {
+                let item_x_pos = hotbar_left_margin + (x as f32) * interslot_spacing * GUI_SCALING;
+                draw_item_count(digit_vaos, slot.amount, item_x_pos + 9.0 * GUI_SCALING, 2.0 * GUI_SCALING, shader);
+            }
+        }
+    }
+
+    pub fn draw_hotbar_items(&self, item_renders: &mut ItemRenders, texture_pack: &TexturePack, item_icons: &ItemIcons, shader: &mut ShaderProgram) {
         let interslot_spacing = 20.0;
         let hotbar_left_margin = WINDOW_WIDTH as f32 / 2.0 - 4.0 * interslot_spacing * GUI_SCALING;
 
         let mut x = 0;
         let y = 11;
-        for slot in self.slots.iter() {
+        for slot in self.slots[..HOTBAR_SIZE].iter() {
             if let Some(slot) = slot {
                 let item_x_pos = hotbar_left_margin + (x as f32) * interslot_spacing * GUI_SCALING;
-                slot.item_render.draw(item_x_pos, (y as f32) * GUI_SCALING, shader);
+                item_renders.get(slot.item, &texture_pack, &item_icons).draw(item_x_pos, (y as f32) * GUI_SCALING, shader);
             }
             x += 1;
         }
     }
-}
\ No newline at end of file
+}
+
+fn left_click_stack(slot: &mut Option<ItemStack>, cursor_stack: &mut Option<ItemStack>) {
+    match (slot.as_mut(), cursor_stack.as_mut()) {
+        (Some(slot_stack), Some(cursor_stack)) if slot_stack.is_same_item(cursor_stack) => {
+            let amount = cursor_stack.amount;
+            slot_stack.merge_from(cursor_stack, amount);
+        }
+        _ => std::mem::swap(slot, cursor_stack),
+    }
+}
+
+fn right_click_stack(slot: &mut Option<ItemStack>, cursor_stack: &mut Option<ItemStack>) {
+    match (slot.as_mut(), cursor_stack.as_mut()) {
+        (Some(slot_stack), None) => {
+            let picked_up = (slot_stack.amount + 1) / 2;
+            *cursor_stack = Some(ItemStack::new(picked_up, slot_stack.item));
+            slot_stack.amount -= picked_up;
+            if slot_stack.amount == 0 {
+                *slot = None;
+            }
+        }
+        (None, Some(cursor)) => {
+            *slot = Some(ItemStack::new(1, cursor.item));
+            cursor.amount -= 1;
+        }
+        (Some(slot_stack), Some(cursor)) if slot_stack.is_same_item(cursor) => {
+            slot_stack.merge_from(cursor, 1);
+        }
+        _ => std::mem::swap(slot, cursor_stack),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::crafting::parse_recipe;
+
+    use super::*;
+
+    const LOG: Item = Item::Block(BlockID::OakLog);
+    const PLANKS: Item = Item::Block(BlockID::OakPlanks);
+
+    fn empty_inventory() -> Inventory {
+        let mut inventory = Inventory::new();
+        inventory.slots = [None; INVENTORY_SIZE];
+        inventory
+    }
+
+    fn recipes() -> RecipeRegistry {
+        RecipeRegistry::new(vec![parse_recipe("shapeless oak_planks 4 : oak_log").unwrap()])
+    }
+
+    fn stack(amount: u32, item: Item) -> Option<ItemStack> {
+        Some(ItemStack::new(amount, item))
+    }
+
+    #[test]
+    fn insert_merges_before_filling_empty_slots() {
+        let mut inventory = empty_inventory();
+        inventory.slots[20] = stack(60, Item::Stick);
+        assert_eq!(inventory.try_insert(ItemStack::new(10, Item::Stick)), Ok(()));
+        assert_eq!(inventory.slots[20], stack(64, Item::Stick));
+        assert_eq!(inventory.slots[0], stack(6, Item::Stick));
+
+        // Unstackable items take a slot each
+        assert_eq!(inventory.try_insert(ItemStack::new(2, Item::WoodenPickaxe)), Ok(()));
+        assert_eq!(inventory.slots[1], stack(1, Item::WoodenPickaxe));
+        assert_eq!(inventory.slots[2], stack(1, Item::WoodenPickaxe));
+    }
+
+    #[test]
+    fn insert_returns_what_doesnt_fit() {
+        let mut inventory = empty_inventory();
+        inventory.slots = [stack(64, Item::Apple); INVENTORY_SIZE];
+        inventory.slots[5] = stack(60, Item::Stick);
+        assert_eq!(inventory.try_insert(ItemStack::new(10, Item::Stick)), Err(ItemStack::new(6, Item::Stick)));
+        assert_eq!(inventory.slots[5], stack(64, Item::Stick));
+        assert_eq!(inventory.try_insert(ItemStack::new(1, LOG)), Err(ItemStack::new(1, LOG)));
+    }
+
+    #[test]
+    fn remove_takes_from_the_last_slots() {
+        let mut inventory = empty_inventory();
+        inventory.slots[0] = stack(10, Item::Stick);
+        inventory.slots[30] = stack(5, Item::Stick);
+        assert_eq!(inventory.remove(Item::Stick, 7), Ok(()));
+        assert_eq!(inventory.slots[0], stack(8, Item::Stick));
+        assert_eq!(inventory.slots[30], None);
+    }
+
+    #[test]
+    fn remove_fails_without_enough_items() {
+        let mut inventory = empty_inventory();
+        inventory.slots[3] = stack(4, Item::Stick);
+        inventory.slots[4] = stack(4, Item::Apple);
+        assert_eq!(inventory.remove(Item::Stick, 10), Err(6));
+        // Nothing was removed
+        assert_eq!(inventory.slots[3], stack(4, Item::Stick));
+        assert_eq!(inventory.remove(LOG, 1), Err(1));
+    }
+
+    #[test]
+    fn left_click_picks_up_puts_down_merges_and_swaps() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.slots[0] = stack(40, Item::Stick);
+        inventory.slots[1] = stack(40, Item::Stick);
+        inventory.slots[2] = stack(1, Item::Apple);
+
+        inventory.left_click_slot(ScreenSlot::Inventory(0), &recipes);
+        assert_eq!((inventory.slots[0], inventory.cursor_stack), (None, stack(40, Item::Stick)));
+
+        // Merged up to the stack limit, the rest stays on the cursor
+        inventory.left_click_slot(ScreenSlot::Inventory(1), &recipes);
+        assert_eq!((inventory.slots[1], inventory.cursor_stack), (stack(64, Item::Stick), stack(16, Item::Stick)));
+
+        inventory.left_click_slot(ScreenSlot::Inventory(2), &recipes);
+        assert_eq!((inventory.slots[2], inventory.cursor_stack), (stack(16, Item::Stick), stack(1, Item::Apple)));
+
+        inventory.left_click_slot(ScreenSlot::Inventory(0), &recipes);
+        assert_eq!((inventory.slots[0], inventory.cursor_stack), (stack(1, Item::Apple), None));
+    }
+
+    #[test]
+    fn right_click_splits_a_stack_and_puts_down_one_item() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.slots[0] = stack(5, Item::Stick);
+
+        // Half of the stack, rounded up
+        inventory.right_click_slot(ScreenSlot::Inventory(0), &recipes);
+        assert_eq!((inventory.slots[0], inventory.cursor_stack), (stack(2, Item::Stick), stack(3, Item::Stick)));
+
+        inventory.right_click_slot(ScreenSlot::Inventory(1), &recipes);
+        inventory.right_click_slot(ScreenSlot::Inventory(0), &recipes);
+        assert_eq!(inventory.slots[1], stack(1, Item::Stick));
+        assert_eq!(inventory.slots[0], stack(3, Item::Stick));
+        assert_eq!(inventory.cursor_stack, stack(1, Item::Stick));
+
+        // The last item empties the cursor
+        inventory.right_click_slot(ScreenSlot::Inventory(1), &recipes);
+        assert_eq!((inventory.slots[1], inventory.cursor_stack), (stack(2, Item::Stick), None));
+
+        // A single item is picked up whole
+        inventory.slots[2] = stack(1, Item::Apple);
+        inventory.right_click_slot(ScreenSlot::Inventory(2), &recipes);
+        assert_eq!((inventory.slots[2], inventory.cursor_stack), (None, stack(1, Item::Apple)));
+    }
+
+    #[test]
+    fn shift_click_moves_between_the_hotbar_and_the_main_inventory() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.slots[0] = stack(10, Item::Stick);
+        inventory.slots[HOTBAR_SIZE + 3] = stack(60, Item::Stick);
+
+        inventory.shift_click_slot(ScreenSlot::Inventory(0), &recipes);
+        assert_eq!(inventory.slots[0], None);
+        assert_eq!(inventory.slots[HOTBAR_SIZE + 3], stack(64, Item::Stick));
+        assert_eq!(inventory.slots[HOTBAR_SIZE], stack(6, Item::Stick));
+
+        inventory.shift_click_slot(ScreenSlot::Inventory(HOTBAR_SIZE + 3), &recipes);
+        assert_eq!(inventory.slots[HOTBAR_SIZE + 3], None);
+        assert_eq!(inventory.slots[0], stack(64, Item::Stick));
+    }
+
+    #[test]
+    fn shift_click_empties_the_crafting_grid_into_the_inventory() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.slots[7] = stack(62, LOG);
+        inventory.crafting_grid.slots[3] = stack(5, LOG);
+        inventory.shift_click_slot(ScreenSlot::CraftingGrid(3), &recipes);
+        assert_eq!(inventory.crafting_grid.slots[3], None);
+        assert_eq!(inventory.slots[7], stack(64, LOG));
+        assert_eq!(inventory.slots[0], stack(3, LOG));
+    }
+
+    #[test]
+    fn shift_click_crafts_as_many_times_as_possible() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.crafting_grid.slots[0] = stack(20, LOG);
+        inventory.crafting_grid.update_result(&recipes);
+        assert_eq!(inventory.crafting_grid.result, stack(4, PLANKS));
+
+        inventory.shift_click_slot(ScreenSlot::CraftingResult, &recipes);
+        assert_eq!(inventory.crafting_grid.slots[0], None);
+        assert_eq!(inventory.crafting_grid.result, None);
+        assert_eq!(inventory.slots[0], stack(64, PLANKS));
+        assert_eq!(inventory.slots[1], stack(16, PLANKS));
+    }
+
+    #[test]
+    fn shift_click_stops_crafting_when_the_result_doesnt_fit() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.slots = [stack(64, Item::Apple); INVENTORY_SIZE];
+        inventory.slots[0] = stack(58, PLANKS);
+        inventory.crafting_grid.slots[0] = stack(5, LOG);
+        inventory.crafting_grid.update_result(&recipes);
+
+        inventory.shift_click_slot(ScreenSlot::CraftingResult, &recipes);
+        assert_eq!(inventory.slots[0], stack(62, PLANKS));
+        assert_eq!(inventory.crafting_grid.slots[0], stack(4, LOG));
+        assert_eq!(inventory.crafting_grid.result, stack(4, PLANKS));
+    }
+
+    #[test]
+    fn clicking_the_result_crafts_onto_the_cursor() {
+        let recipes = recipes();
+        let mut inventory = empty_inventory();
+        inventory.crafting_grid.slots[2] = stack(2, LOG);
+        inventory.crafting_grid.update_result(&recipes);
+
+        inventory.left_click_slot(ScreenSlot::CraftingResult, &recipes);
+        assert_eq!(inventory.cursor_stack, stack(4, PLANKS));
+        inventory.right_click_slot(ScreenSlot::CraftingResult, &recipes);
+        assert_eq!(inventory.cursor_stack, stack(8, PLANKS));
+        assert_eq!(inventory.crafting_grid.slots[2], None);
+        assert_eq!(inventory.crafting_grid.result, None);
+
+        // Nothing is crafted if the cursor holds something else
+        inventory.crafting_grid.slots[2] = stack(1, LOG);
+        inventory.crafting_grid.update_result(&recipes);
+        inventory.cursor_stack = stack(1, Item::Apple);
+        inventory.left_click_slot(ScreenSlot::CraftingResult, &recipes);
+        assert_eq!(inventory.crafting_grid.slots[2], stack(1, LOG));
+        assert_eq!(inventory.cursor_stack, stack(1, Item::Apple));
+    }
+}
@@ -0,0 +1,168 @@
+use nalgebra::Matrix4;
+use nalgebra_glm::{Mat4, vec3};
+
+use crate::constants::{GUI_SCALING, WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::gui::draw_item_count;
+use crate::inventory::{HOTBAR_SIZE, INVENTORY_SIZE, Inventory};
+use crate::inventory::item::{ItemRenders, ItemStack};
+use crate::shader_compilation::ShaderProgram;
+use crate::types::{ItemIcons, TexturePack};
+
+// Layout of the inventory screen in unscaled GUI pixels, relative to the center of the window
+const SLOT_SPACING: f32 = 20.0;
+const SLOT_HALF_SIZE: f32 = 9.0;
+// Vertical position of the rows of the main inventory, from top to bottom
+const MAIN_INVENTORY_ROWS_Y: [f32; 3] = [34.0, 14.0, -6.0];
+const HOTBAR_ROW_Y: f32 = -32.0;
+// The crafting grid is above the main inventory, with its result on the right
+const CRAFTING_GRID_TOP_ROW_Y: f32 = 98.0;
+const CRAFTING_GRID_LEFT_COLUMN_X: f32 = -60.0;
+const CRAFTING_RESULT_X: f32 = 50.0;
+const SCREEN_HALF_WIDTH: f32 = 91.0;
+const SCREEN_TOP: f32 = 109.0;
+const SCREEN_BOTTOM: f32 = -43.0;
+
+/// A slot of the inventory screen
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ScreenSlot {
+    Inventory(usize),
+    CraftingGrid(usize),
+    CraftingResult,
+}
+
+/// Converts a cursor position in window coordinates to GUI coordinates (origin at the bottom left)
+pub fn cursor_to_gui_coords(x: f64, y: f64) -> (f32, f32) {
+    (x as f32, WINDOW_HEIGHT as f32 - y as f32)
+}
+
+fn row_y(index: usize) -> f32 {
+    if index < HOTBAR_SIZE {
+        HOTBAR_ROW_Y
+    } else {
+        MAIN_INVENTORY_ROWS_Y[(index - HOTBAR_SIZE) / HOTBAR_SIZE]
+    }
+}
+
+/// Center of a slot of the inventory screen in GUI coordinates
+/// `grid_size` is the number of slots per side of the crafting grid
+pub fn slot_position(slot: ScreenSlot, grid_size: usize) -> (f32, f32) {
+    let (x, y) = match slot {
+        ScreenSlot::Inventory(index) => (((index % HOTBAR_SIZE) as f32 - 4.0) * SLOT_SPACING, row_y(index)),
+        ScreenSlot::CraftingGrid(index) => (
+            CRAFTING_GRID_LEFT_COLUMN_X + (index % grid_size) as f32 * SLOT_SPACING,
+            CRAFTING_GRID_TOP_ROW_Y - (index / grid_size) as f32 * SLOT_SPACING,
+        ),
+        // Next to the middle of the grid
+        ScreenSlot::CraftingResult => (
+            CRAFTING_RESULT_X,
+            CRAFTING_GRID_TOP_ROW_Y - (grid_size - 1) as f32 * SLOT_SPACING / 2.0,
+        ),
+    };
+    (
+        WINDOW_WIDTH as f32 / 2.0 + x * GUI_SCALING,
+        WINDOW_HEIGHT as f32 / 2.0 + y * GUI_SCALING,
+    )
+}
+
+/// Every slot of the inventory screen
+fn screen_slots(grid_size: usize) -> impl Iterator<Item = ScreenSlot> {
+    (0..INVENTORY_SIZE).map(ScreenSlot::Inventory)
+        .chain((0..grid_size * grid_size).map(ScreenSlot::CraftingGrid))
+        .chain(std::iter::once(ScreenSlot::CraftingResult))
+}
+
+/// The slot under a position in GUI coordinates
+pub fn slot_at(x: f32, y: f32, grid_size: usize) -> Option<ScreenSlot> {
+    screen_slots(grid_size).find(|&slot| {
+        let (slot_x, slot_y) = slot_position(slot, grid_size);
+        (x - slot_x).abs() <= SLOT_HALF_SIZE * GUI_SCALING && (y - slot_y).abs() <= SLOT_HALF_SIZE * GUI_SCALING
+    })
+}
+
+/// Whether a position in GUI coordinates is over the inventory screen
+/// Clicking anywhere else drops the stack held by the cursor
+pub fn is_inside_screen(x: f32, y: f32) -> bool {
+    let (center_x, center_y) = (WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0);
+    (x - center_x).abs() <= SCREEN_HALF_WIDTH * GUI_SCALING
+        && y >= center_y + SCREEN_BOTTOM * GUI_SCALING
+        && y <= center_y + SCREEN_TOP * GUI_SCALING
+}
+
+impl Inventory {
+    /// Draws the slots of the inventory screen, each row of 9 slots looks like the hotbar
+    /// The slots of the crafting grid and its result are drawn one by one
+    pub fn draw_screen_slots(&self, hotbar_vao: u32, slot_vao: u32, shader: &mut ShaderProgram) {
+        let projection_matrix = nalgebra_glm::ortho(
+            0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+
+        shader.use_program();
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        shader.set_uniform1i("tex", 2);
+        gl_call!(gl::BindVertexArray(hotbar_vao));
+
+        for &y in MAIN_INVENTORY_ROWS_Y.iter().chain(&[HOTBAR_ROW_Y]) {
+            let model_matrix = {
+                let translate_matrix = Matrix4::new_translation(&vec3(
+                    WINDOW_WIDTH as f32 / 2.0, WINDOW_HEIGHT as f32 / 2.0 + y * GUI_SCALING, 0.0));
+                let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(182.0 * GUI_SCALING, 22.0 * GUI_SCALING, 1.0));
+                translate_matrix * scale_matrix
+            };
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        }
+
+        gl_call!(gl::BindVertexArray(slot_vao));
+        let grid_size = self.crafting_grid.size;
+        for slot in screen_slots(grid_size).skip(INVENTORY_SIZE) {
+            let (x, y) = slot_position(slot, grid_size);
+            let model_matrix = {
+                let translate_matrix = Matrix4::new_translation(&vec3(x, y, 0.0));
+                let scale_matrix: Mat4 = Matrix4::new_nonuniform_scaling(&vec3(22.0 * GUI_SCALING, 22.0 * GUI_SCALING, 1.0));
+                translate_matrix * scale_matrix
+            };
+            shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+            gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6));
+        }
+    }
+
+    /// The stack shown in a slot of the inventory screen
+    fn screen_slot_stack(&self, slot: ScreenSlot) -> Option<ItemStack> {
+        match slot {
+            ScreenSlot::Inventory(index) => self.slots[index],
+            ScreenSlot::CraftingGrid(index) => self.crafting_grid.slots[index],
+            ScreenSlot::CraftingResult => self.crafting_grid.result,
+        }
+    }
+
+    /// Draws the items of every slot of the inventory screen and the stack held by the cursor
+    /// `cursor` is in GUI coordinates
+    pub fn draw_screen_items(&self, cursor: (f32, f32), item_renders: &mut ItemRenders, texture_pack: &TexturePack, item_icons: &ItemIcons, shader: &mut ShaderProgram) {
+        let grid_size = self.crafting_grid.size;
+        for slot in screen_slots(grid_size) {
+            if let Some(stack) = self.screen_slot_stack(slot) {
+                let (x, y) = slot_position(slot, grid_size);
+                item_renders.get(stack.item, &texture_pack, &item_icons).draw(x, y, shader);
+            }
+        }
+        if let Some(stack) = self.cursor_stack {
+            item_renders.get(stack.item, &texture_pack, &item_icons).draw(cursor.0, cursor.1, shader);
+        }
+    }
+
+    /// Draws the number of items of the stacks of the inventory screen on top of the items
+    pub fn draw_screen_item_counts(&self, cursor: (f32, f32), digit_vaos: &[u32; 10], shader: &mut ShaderProgram) {
+        let draw_count = |stack: &ItemStack, (x, y): (f32, f32), shader: &mut ShaderProgram| {
+            draw_item_count(digit_vaos, stack.amount,
+                            x + SLOT_HALF_SIZE * GUI_SCALING, y - SLOT_HALF_SIZE * GUI_SCALING, shader);
+        };
+        let grid_size = self.crafting_grid.size;
+        for slot in screen_slots(grid_size) {
+            if let Some(stack) = self.screen_slot_stack(slot) {
+                draw_count(&stack, slot_position(slot, grid_size), shader);
+            }
+        }
+        if let Some(stack) = &self.cursor_stack {
+            draw_count(stack, cursor, shader);
+        }
+    }
+}
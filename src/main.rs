@@ -19,21 +19,32 @@ use ecs::components::*;
 use ecs::systems::*;
 use timer::Timer;
 
-use crate::chunk_manager::ChunkManager;
+use crate::block_tick::BlockBehaviours;
+use crate::chat::Chat;
+use crate::chunk_manager::{ChunkCullingStats, ChunkManager};
 use crate::constants::*;
+use crate::crafting::{RECIPES_PATH, RecipeRegistry};
+use crate::debug_overlay::DebugInfo;
 use crate::debugging::*;
+use crate::dropped_item::DroppedItem;
+use crate::falling_block::FallingBlock;
 use crate::fps_counter::FpsCounter;
-use crate::gui::{create_gui_icons_texture, create_widgets_texture};
+use crate::health::Health;
+use crate::gui::{create_digits_texture, create_gui_icons_texture, create_widgets_texture, GuiState};
 use crate::input::InputCache;
 use crate::inventory::Inventory;
 use crate::main_hand::MainHand;
+use crate::mob::{Mob, MobAI};
 use crate::particle_system::ParticleSystem;
-use crate::physics::Interpolator;
-use crate::player::{PlayerPhysicsState, PlayerState};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::player::{player_physics_body, PlayerState};
 use crate::shader_compilation::ShaderProgram;
-use crate::texture_pack::generate_array_texture;
+use crate::sky::{create_moon_texture, create_sun_texture};
+use crate::texture_pack::{create_destroy_stages_texture, generate_array_texture};
 use crate::types::Shaders;
 use crate::window::create_window;
+use crate::weather::Weather;
+use crate::world_time::WorldTime;
 use crate::ecs::systems::chunk_loading::ChunkLoading;
 use std::sync::Arc;
 use std::thread;
@@ -47,10 +58,12 @@ pub mod shapes;
 pub mod util;
 pub mod chunk_manager;
 pub mod chunk;
+pub mod chunk_arena;
 pub mod raycast;
 pub mod block_texture_faces;
 pub mod physics;
 pub mod aabb;
+pub mod frustum;
 pub mod constants;
 pub mod input;
 pub mod window;
@@ -64,6 +77,24 @@ pub mod timer;
 pub mod particle_system;
 pub mod ecs;
 pub mod main_hand;
+pub mod dropped_item;
+pub mod crafting;
+pub mod health;
+pub mod text;
+pub mod debug_overlay;
+pub mod command;
+pub mod chat;
+pub mod world_time;
+pub mod sky;
+pub mod weather;
+pub mod precipitation;
+pub mod fluid;
+pub mod block_tick;
+pub mod vegetation;
+pub mod falling_block;
+pub mod redstone;
+pub mod pathfinding;
+pub mod mob;
 use parking_lot::deadlock;
 
 fn main() {
@@ -90,10 +121,16 @@ fn main() {
 
     let mut world = World::new();
     world.register::<PlayerState>();
-    world.register::<Interpolator<PlayerPhysicsState>>();
+    world.register::<Interpolator<EntityPhysicsState>>();
+    world.register::<PhysicsBody>();
     world.register::<Inventory>();
     world.register::<MainHand>();
     world.register::<MainHandItemChanged>();
+    world.register::<DroppedItem>();
+    world.register::<FallingBlock>();
+    world.register::<Health>();
+    world.register::<Mob>();
+    world.register::<MobAI>();
 
     let mut dispatcher = DispatcherBuilder::new()
         .with_thread_local({
@@ -116,19 +153,35 @@ fn main() {
                 events,
             }
         })
+        .with_thread_local(HandleChatInput)
         .with_thread_local(InventoryHandleInput)
         .with_thread_local(HandlePlayerInput)
         .with_thread_local(UpdatePlayerPhysics)
+        .with_thread_local(EntityPhysics)
         .with_thread_local(UpdatePlayerState)
+        .with_thread_local(UpdatePlayerHealth)
         .with_thread_local(PlaceAndBreakBlocks)
         .with_thread_local(UpdateMainHand)
+        .with_thread_local(UpdateDroppedItems)
+        .with_thread_local(UpdateWeather::new())
+        .with_thread_local(UpdateBlockTicks::new())
+        .with_thread_local(UpdateFallingBlocks)
+        .with_thread_local(SpawnMobs::new())
+        .with_thread_local(UpdateMobs)
         .with_thread_local(ChunkLoading::new())
 
+        .with_thread_local(RenderSky::new())
         .with_thread_local(RenderChunks)
         .with_thread_local(RenderParticles)
+        .with_thread_local(RenderPrecipitation::new())
+        .with_thread_local(RenderDroppedItems::new())
+        .with_thread_local(RenderFallingBlocks::new())
+        .with_thread_local(RenderMobs::new())
         .with_thread_local(RenderBlockOutline::new())
         .with_thread_local(RenderMainHand::new())
         .with_thread_local(RenderGUI::new())
+        .with_thread_local(RenderChat::new())
+        .with_thread_local(RenderDebugOverlay::new())
 
         .with_thread_local(AdvanceGlobalTime)
         .with_thread_local(FpsCounter::new())
@@ -137,11 +190,13 @@ fn main() {
 
     world.insert(InputCache::default());
     world.insert(Timer::default());
-    world.insert({
-        let (item_array_texture, texture_pack) = generate_array_texture();
+    {
+        let (item_array_texture, texture_pack, item_icons, mob_textures) = generate_array_texture();
         gl_call!(gl::BindTextureUnit(0, item_array_texture));
-        texture_pack
-    });
+        world.insert(texture_pack);
+        world.insert(item_icons);
+        world.insert(mob_textures);
+    }
     world.insert({
         let mut particle_systems: HashMap<&str, ParticleSystem> = HashMap::new();
         particle_systems.insert("block_particles", ParticleSystem::new(500));
@@ -155,9 +210,22 @@ fn main() {
         shaders_resource.insert("item_shader", ShaderProgram::compile("src/shaders/item.vert", "src/shaders/item.frag"));
         shaders_resource.insert("particle_shader", ShaderProgram::compile("src/shaders/particle.vert", "src/shaders/particle.frag"));
         shaders_resource.insert("hand_shader", ShaderProgram::compile("src/shaders/hand.vert", "src/shaders/hand.frag"));
+        shaders_resource.insert("renderer2d_shader", ShaderProgram::compile("src/shaders/renderer2d.vert", "src/shaders/renderer2d.frag"));
+        shaders_resource.insert("color_shader", ShaderProgram::compile("src/shaders/color.vert", "src/shaders/color.frag"));
+        shaders_resource.insert("sky_shader", ShaderProgram::compile("src/shaders/sky.vert", "src/shaders/sky.frag"));
+        shaders_resource.insert("celestial_shader", ShaderProgram::compile("src/shaders/celestial.vert", "src/shaders/celestial.frag"));
+        shaders_resource.insert("precipitation_shader", ShaderProgram::compile("src/shaders/precipitation.vert", "src/shaders/precipitation.frag"));
         shaders_resource
     });
     world.insert(Arc::new(ChunkManager::new()));
+    world.insert(ChunkCullingStats::default());
+    world.insert(DebugInfo::default());
+    world.insert(GuiState::default());
+    world.insert(Chat::default());
+    world.insert(WorldTime::default());
+    world.insert(Weather::default());
+    world.insert(BlockBehaviours::default());
+    world.insert(RecipeRegistry::load(RECIPES_PATH));
 
     {
         let gui_icons_texture = create_gui_icons_texture();
@@ -167,14 +235,28 @@ fn main() {
         let gui_widgets_texture = create_widgets_texture();
         gl_call!(gl::ActiveTexture(gl::TEXTURE0 + 2));
         gl_call!(gl::BindTexture(gl::TEXTURE_2D, gui_widgets_texture));
+
+        let gui_digits_texture = create_digits_texture();
+        gl_call!(gl::ActiveTexture(gl::TEXTURE0 + 3));
+        gl_call!(gl::BindTexture(gl::TEXTURE_2D, gui_digits_texture));
+
+        let destroy_stages_texture = create_destroy_stages_texture();
+        gl_call!(gl::BindTextureUnit(4, destroy_stages_texture));
+
+        gl_call!(gl::BindTextureUnit(5, create_sun_texture()));
+        gl_call!(gl::BindTextureUnit(6, create_moon_texture()));
     }
 
+    let player_body = player_physics_body();
     let _player = world.create_entity()
         .with(PlayerState::new())
         .with(Interpolator::new(
             1.0 / PHYSICS_TICKRATE,
-            PlayerPhysicsState::new_at_position(vec3(8.0f32, 195.0, 8.0)),
+            // Moved to the ground of the spawn point once it is loaded
+            EntityPhysicsState::new_at_position(vec3(WORLD_SPAWN.0 as f32 + 0.5, 195.0, WORLD_SPAWN.1 as f32 + 0.5), &player_body),
         ))
+        .with(player_body)
+        .with(Health::new())
         .with(Inventory::new())
         .with(MainHand::new())
         .with(MainHandItemChanged)
@@ -182,5 +264,7 @@ fn main() {
 
     loop {
         dispatcher.dispatch(&world);
+        // Creates and deletes the entities queued by the systems (dropped items, mobs)
+        world.maintain();
     }
 }
@@ -3,16 +3,15 @@ use core::ffi::c_void;
 use specs::Component;
 use specs::DenseVecStorage;
 
-use crate::chunk::BlockID;
-use crate::shapes::centered_unit_cube;
-use crate::types::TexturePack;
+use crate::inventory::item::Item;
+use crate::types::{ItemIcons, TexturePack};
 
 #[derive(Component)]
 pub struct MainHand {
     pub begin_switch: bool,
-    pub showing_item: Option<BlockID>,
+    pub showing_item: Option<Item>,
     pub render: MainHandRender,
-    pub switching_to: Option<BlockID>,
+    pub switching_to: Option<Item>,
 }
 
 impl MainHand {
@@ -25,19 +24,19 @@ impl MainHand {
         }
     }
 
-    pub fn switch_item_to(&mut self, item: Option<BlockID>) {
+    pub fn switch_item_to(&mut self, item: Option<Item>) {
         self.switching_to = item;
         self.begin_switch = true;
     }
 
-    pub fn set_showing_item(&mut self, item: Option<BlockID>) {
+    pub fn set_showing_item(&mut self, item: Option<Item>) {
         self.showing_item = item;
         self.render.dirty = true;
     }
 
-    pub fn update_if_dirty(&mut self, texture_pack: &TexturePack) {
+    pub fn update_if_dirty(&mut self, texture_pack: &TexturePack, item_icons: &ItemIcons) {
         if let Some(item) = self.showing_item {
-            self.render.update_vbo_if_dirty(item, &texture_pack);
+            self.render.update_vbo_if_dirty(item, &texture_pack, &item_icons);
         }
     }
 }
@@ -45,6 +44,7 @@ impl MainHand {
 pub struct MainHandRender {
     pub vao: u32,
     pub vbo: u32,
+    pub vertices: i32,
     pub dirty: bool,
 }
 
@@ -75,21 +75,21 @@ impl MainHandRender {
         Self {
             vao,
             vbo,
+            vertices: 0,
             dirty: true,
         }
     }
 
-    pub fn update_vbo_if_dirty(&mut self, item: BlockID, texture_pack: &TexturePack) {
+    pub fn update_vbo_if_dirty(&mut self, item: Item, texture_pack: &TexturePack, item_icons: &ItemIcons) {
         if self.dirty {
-            self.update_vbo(item, &texture_pack);
+            self.update_vbo(item, &texture_pack, &item_icons);
             self.dirty = false;
         }
     }
 
-    pub fn update_vbo(&mut self, item: BlockID, texture_pack: &TexturePack) {
-        let vbo_data = centered_unit_cube(
-            -0.5, -0.5, -0.5,
-            texture_pack.get(&item).unwrap().get_uv_of_every_face());
+    pub fn update_vbo(&mut self, item: Item, texture_pack: &TexturePack, item_icons: &ItemIcons) {
+        let vbo_data = item.model_vertices(&texture_pack, &item_icons);
+        self.vertices = (vbo_data.len() / 9) as i32;
 
         gl_call!(gl::NamedBufferData(self.vbo,
                     (vbo_data.len() * std::mem::size_of::<f32>() as usize) as isize,
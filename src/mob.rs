@@ -0,0 +1,273 @@
+use std::collections::VecDeque;
+use std::os::raw::c_void;
+use std::time::{Duration, Instant};
+
+use nalgebra::Matrix4;
+use nalgebra_glm::{Mat4, Vec3, vec3};
+use rand::{Rng, thread_rng};
+use specs::{Builder, Entities, LazyUpdate};
+
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{MOB_STEP_HEIGHT, MOB_STUCK_TIMEOUT, MOB_WANDER_RADIUS, PHYSICS_TICKRATE};
+use crate::health::Health;
+use crate::pathfinding::{find_path, is_standable, PathfindingRules};
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
+use crate::shader_compilation::ShaderProgram;
+use crate::shapes::centered_unit_cube;
+use crate::types::MobTextures;
+
+/// The kinds of creatures living in the world
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum MobKind {
+    Pig,
+}
+
+impl MobKind {
+    pub const ALL: [MobKind; 1] = [MobKind::Pig];
+
+    /// Width and height of the AABB
+    pub fn size(&self) -> (f32, f32) {
+        match self {
+            MobKind::Pig => (0.9, 0.9),
+        }
+    }
+
+    /// In half hearts
+    pub fn max_health(&self) -> u32 {
+        match self {
+            MobKind::Pig => 10,
+        }
+    }
+
+    /// Blocks per second
+    pub fn walking_speed(&self) -> f32 {
+        match self {
+            MobKind::Pig => 1.5,
+        }
+    }
+
+    /// Paths of the textures of the skin and of the face
+    pub fn texture_paths(&self) -> (&'static str, &'static str) {
+        match self {
+            MobKind::Pig => ("textures/entities/pig.png", "textures/entities/pig_face.png"),
+        }
+    }
+
+    pub fn physics_body(&self) -> PhysicsBody {
+        let (width, height) = self.size();
+        PhysicsBody {
+            step_height: MOB_STEP_HEIGHT,
+            max_speed: self.walking_speed(),
+            ..PhysicsBody::new(width, height)
+        }
+    }
+
+    pub fn pathfinding_rules(&self) -> PathfindingRules {
+        PathfindingRules {
+            height: self.size().1.ceil() as i32,
+            step_height: 0,
+            jump_height: 1,
+            max_drop: 3,
+            max_nodes: 1000,
+        }
+    }
+
+    /// The boxes of the model as (center, size, is_head), the feet at the origin and the head towards +z
+    fn model_boxes(&self) -> Vec<(Vec3, Vec3, bool)> {
+        match self {
+            MobKind::Pig => {
+                let mut boxes = vec![
+                    (vec3(0.0, 0.625, 0.0), vec3(0.625, 0.5, 0.875), false),
+                    (vec3(0.0, 0.75, 0.5625), vec3(0.5, 0.5, 0.5), true),
+                ];
+                for &(x, z) in &[(-0.1875, -0.3125), (0.1875, -0.3125), (-0.1875, 0.3125), (0.1875, 0.3125)] {
+                    boxes.push((vec3(x, 0.1875, z), vec3(0.25, 0.375, 0.25), false));
+                }
+                boxes
+            }
+        }
+    }
+}
+
+/// A creature walking around on its own
+pub struct Mob {
+    pub kind: MobKind,
+    // Angle around the y axis of the direction the mob faces, 0 towards +z
+    pub yaw: f32,
+}
+
+/// What a mob is busy with
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Goal {
+    // Stands still until then
+    Idle(Instant),
+    // Walks along its path to the last block of it
+    Wander,
+}
+
+/// The goal of a mob and the path it follows, as the positions of its feet
+pub struct MobAI {
+    pub goal: Goal,
+    pub path: VecDeque<(i32, i32, i32)>,
+    // Last time the mob reached a block of its path
+    pub(crate) last_progress: Instant,
+}
+
+impl MobAI {
+    pub fn new() -> Self {
+        MobAI {
+            goal: Goal::Idle(Instant::now()),
+            path: VecDeque::new(),
+            last_progress: Instant::now(),
+        }
+    }
+
+    /// Picks the goals of the mob and steers it along its path
+    /// Returns the horizontal direction to walk in, and whether to jump
+    pub fn update(&mut self, kind: MobKind, state: &EntityPhysicsState, chunk_manager: &ChunkManager, now: Instant) -> (Vec3, bool) {
+        let standing_still = (vec3(0.0, 0.0, 0.0), false);
+        let mut rng = thread_rng();
+        let (x, y, z) = state.feet_block();
+        match self.goal {
+            Goal::Idle(until) => {
+                if now < until {
+                    return standing_still;
+                }
+                // Wander to a random spot nearby which can be walked to
+                let rules = kind.pathfinding_rules();
+                let (goal_x, goal_z) = (
+                    x + rng.gen_range(-MOB_WANDER_RADIUS, MOB_WANDER_RADIUS + 1),
+                    z + rng.gen_range(-MOB_WANDER_RADIUS, MOB_WANDER_RADIUS + 1));
+                let path = (y - 4..=y + 4).rev()
+                    .find(|&goal_y| is_standable(chunk_manager, &rules, goal_x, goal_y, goal_z))
+                    .and_then(|goal_y| find_path(chunk_manager, &rules, (x, y, z), (goal_x, goal_y, goal_z)));
+                match path {
+                    Some(path) if !path.is_empty() => {
+                        self.path = path.into();
+                        self.goal = Goal::Wander;
+                        self.last_progress = now;
+                    }
+                    _ => self.goal = Goal::Idle(now + Duration::from_secs(1)),
+                }
+                standing_still
+            }
+            Goal::Wander => {
+                let next = match self.path.front() {
+                    Some(&next) => next,
+                    None => {
+                        self.goal = Goal::Idle(now + Duration::from_secs(rng.gen_range(2, 8)));
+                        return standing_still;
+                    }
+                };
+                if now.saturating_duration_since(self.last_progress) > *MOB_STUCK_TIMEOUT {
+                    self.path.clear();
+                    self.goal = Goal::Idle(now + Duration::from_secs(1));
+                    return standing_still;
+                }
+
+                let to_next = vec3(next.0 as f32 + 0.5 - state.position.x, 0.0, next.2 as f32 + 0.5 - state.position.z);
+                if to_next.norm() < 0.3 && y == next.1 {
+                    self.path.pop_front();
+                    self.last_progress = now;
+                }
+                if to_next.norm() < 0.05 {
+                    return standing_still;
+                }
+                (to_next.normalize(), next.1 > y)
+            }
+        }
+    }
+}
+
+/// Mobs appear on the grass, where they have room to stand
+pub fn can_spawn_at(chunk_manager: &ChunkManager, kind: MobKind, x: i32, y: i32, z: i32) -> bool {
+    chunk_manager.get_block(x, y - 1, z) == Some(BlockID::GrassBlock)
+        && is_standable(chunk_manager, &kind.pathfinding_rules(), x, y, z)
+}
+
+/// Creates a mob standing at `position`, it is added to the world at the end of the frame
+pub fn spawn_mob(lazy_update: &LazyUpdate, entities: &Entities, kind: MobKind, position: Vec3) {
+    let body = kind.physics_body();
+    lazy_update.create_entity(entities)
+        .with(Mob { kind, yaw: thread_rng().gen_range(0.0, 2.0 * std::f32::consts::PI) })
+        .with(MobAI::new())
+        .with(Health::with_max_health(kind.max_health()))
+        .with(Interpolator::new(1.0 / PHYSICS_TICKRATE, EntityPhysicsState::new_at_position(position, &body)))
+        .with(body)
+        .build();
+    info!("Spawned a {:?} at ({} {} {})", kind, position.x, position.y, position.z);
+}
+
+/// GPU buffers of the boxes making up a kind of mob
+pub struct MobModel {
+    vao: u32,
+    vbo: u32,
+    vertices: i32,
+}
+
+impl MobModel {
+    pub fn new(kind: MobKind, mob_textures: &MobTextures) -> Self {
+        let (skin, face) = *mob_textures.get(&kind).unwrap();
+        let mut vbo_data = Vec::new();
+        for (center, size, is_head) in kind.model_boxes() {
+            let front = if is_head { face } else { skin };
+            let cube = centered_unit_cube(-0.5, -0.5, -0.5, (front, skin, skin, skin, skin, skin));
+            // Every vertex is made of its position, texture coordinates and normal
+            for vertex in cube.chunks(9) {
+                vbo_data.push(center.x + vertex[0] * size.x);
+                vbo_data.push(center.y + vertex[1] * size.y);
+                vbo_data.push(center.z + vertex[2] * size.z);
+                vbo_data.extend_from_slice(&vertex[3..]);
+            }
+        }
+
+        let mut vao = 0;
+        gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3 as i32, gl::FLOAT, gl::FALSE, 0));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+        // Texture coords
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 1, 3 as i32, gl::FLOAT, gl::FALSE, 3 * std::mem::size_of::<f32>() as u32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+        // Normals
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 2));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 2, 3 as i32, gl::FLOAT, gl::FALSE, 6 * std::mem::size_of::<f32>() as u32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 2, 0));
+
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+        gl_call!(gl::NamedBufferData(vbo,
+                    (vbo_data.len() * std::mem::size_of::<f32>() as usize) as isize,
+                    vbo_data.as_ptr() as *const c_void,
+                    gl::STATIC_DRAW));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, (9 * std::mem::size_of::<f32>()) as i32));
+
+        MobModel {
+            vao,
+            vbo,
+            vertices: (vbo_data.len() / 9) as i32,
+        }
+    }
+
+    /// `position` is the center of the feet of the mob
+    pub fn draw(&self, position: &Vec3, yaw: f32, shader: &mut ShaderProgram) {
+        let model_matrix: Mat4 = Matrix4::new_translation(position) * Matrix4::from_euler_angles(0.0, yaw, 0.0);
+        shader.set_uniform_matrix4fv("model", model_matrix.as_ptr());
+
+        gl_call!(gl::BindVertexArray(self.vao));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices));
+    }
+}
+
+impl Drop for MobModel {
+    fn drop(&mut self) {
+        gl_call!(gl::DeleteBuffers(1, &self.vbo));
+        gl_call!(gl::DeleteVertexArrays(1, &self.vao));
+    }
+}
@@ -3,13 +3,13 @@ use std::time::{Instant, Duration};
 use nalgebra_glm::{Mat4, Vec3, vec3, vec4};
 
 use crate::chunk_manager::ChunkManager;
-use crate::physics::{Interpolatable, Interpolator};
+use crate::physics::{get_block_aabbs_on_the_way, Interpolatable, Interpolator, sweep_axis};
 use crate::shader_compilation::ShaderProgram;
 use crate::shapes::quad_array_texture;
 use nalgebra::Matrix4;
 use std::ffi::c_void;
 use rand::random;
-use crate::aabb::get_block_aabb;
+use crate::aabb::AABB;
 use num_traits::Zero;
 use crate::chunk::BlockID;
 use crate::types::TexturePack;
@@ -210,71 +210,24 @@ impl Interpolatable for ParticlePhysicsProperties {
     }
 }
 
+/// Particles aren't `PhysicsBody`s: they are points bouncing off the blocks, stored outside of the ECS,
+/// and nothing needs to know whether they are on the ground or in a fluid
 impl Interpolator<ParticlePhysicsProperties> {
     fn update_particle(&mut self, time: Instant, chunk_manager: &ChunkManager) {
         self.step(time, &mut |state, _t, dt| {
             let mut state = state.clone();
             state.velocity += state.acceleration * dt;
 
-            let vectors: &[Vec3] = &[
-                vec3(state.velocity.x, 0., 0.),
-                vec3(0., state.velocity.y, 0.),
-                vec3(0., 0., state.velocity.z),
-            ];
-
-            for v in vectors {
-                state.position += v * dt;
-
-                let containing_block = vec3(
-                    (state.position.x).floor() as i32,
-                    (state.position.y).floor() as i32,
-                    (state.position.z).floor() as i32,
-                );
-
-                let mut colliding_block_aabb = None;
-                if let Some(block) = chunk_manager.get_block(containing_block.x, containing_block.y, containing_block.z) {
-                    if !block.is_air() {
-                        let block_aabb = get_block_aabb(&vec3(
-                            containing_block.x as f32,
-                            containing_block.y as f32,
-                            containing_block.z as f32,
-                        ));
-                        colliding_block_aabb = Some(block_aabb);
-                    }
-                }
-
-                if colliding_block_aabb.is_none() {
-                    continue;
-                }
-                let colliding_block_aabb = colliding_block_aabb.unwrap();
-
-                let padding = 0.001;
-
-                if !v.x.is_zero() {
-                    if v.x < 0.0 {
-                        state.position.x = colliding_block_aabb.maxs.x + padding;
-                    } else {
-                        state.position.x = colliding_block_aabb.mins.x - padding;
-                    }
-                    state.velocity.x *= -0.1;
-                }
-                if !v.y.is_zero() {
-                    if v.y < 0.0 {
-                        state.position.y = colliding_block_aabb.maxs.y + padding;
-                    } else {
-                        state.position.y = colliding_block_aabb.mins.y - padding;
-                    }
-                    state.velocity.y *= -0.1;
-                }
-                if !v.z.is_zero() {
-                    if v.z < 0.0 {
-                        state.position.z = colliding_block_aabb.maxs.z + padding;
-                    } else {
-                        state.position.z = colliding_block_aabb.mins.z - padding
-                    }
-                    state.velocity.z *= -0.1;
+            // A particle is a point, it bounces off the blocks
+            let translation = state.velocity * dt;
+            let mut aabb = AABB::new(state.position, state.position);
+            let obstacles = get_block_aabbs_on_the_way(&aabb, &translation, 0.0, chunk_manager);
+            for &axis in &[0, 1, 2] {
+                if sweep_axis(&mut aabb, &obstacles, axis, translation[axis]) {
+                    state.velocity[axis] *= -0.1;
                 }
             }
+            state.position = aabb.mins;
 
             state.velocity.x *= 0.8;
             state.velocity.z *= 0.8;
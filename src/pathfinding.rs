@@ -0,0 +1,206 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::chunk_manager::ChunkManager;
+
+// Costs are in tenths of a block walked
+const WALK_COST: u32 = 10;
+const JUMP_COST: u32 = 15;
+// Added per block dropped
+const DROP_COST: u32 = 5;
+
+/// What a walking entity can do to reach its destination
+#[derive(Debug, Copy, Clone)]
+pub struct PathfindingRules {
+    // Height of the entity, in blocks
+    pub height: i32,
+    // Highest ledge climbed without jumping, blocks above it up to `jump_height` have to be jumped on
+    pub step_height: i32,
+    pub jump_height: i32,
+    // Highest ledge walked off
+    pub max_drop: i32,
+    // The search gives up after visiting this many blocks
+    pub max_nodes: usize,
+}
+
+/// Whether the entity fits in the column at (x, y, z) without standing on anything
+fn is_free(chunk_manager: &ChunkManager, rules: &PathfindingRules, x: i32, y: i32, z: i32) -> bool {
+    (y..y + rules.height).all(|y| {
+        match chunk_manager.get_block(x, y, z) {
+            // Entities walking around avoid fluids
+            Some(block) => !block.has_collision() && !block.is_fluid(),
+            None => false,
+        }
+    })
+}
+
+/// Whether the entity can stand with its feet at (x, y, z)
+pub fn is_standable(chunk_manager: &ChunkManager, rules: &PathfindingRules, x: i32, y: i32, z: i32) -> bool {
+    chunk_manager.is_solid_block_at(x, y - 1, z) && is_free(chunk_manager, rules, x, y, z)
+}
+
+/// The blocks reachable in one move from (x, y, z), with the cost of the move
+fn moves(chunk_manager: &ChunkManager, rules: &PathfindingRules, (x, y, z): (i32, i32, i32)) -> Vec<((i32, i32, i32), u32)> {
+    let mut moves = Vec::new();
+    for &(dx, dz) in &[(1, 0), (-1, 0), (0, 1), (0, -1)] {
+        let (nx, nz) = (x + dx, z + dz);
+        if is_standable(chunk_manager, rules, nx, y, nz) {
+            moves.push(((nx, y, nz), WALK_COST));
+            continue;
+        }
+
+        // Climb on a ledge, the entity needs room above its head to jump
+        if chunk_manager.is_solid_block_at(nx, y, nz) {
+            let climbed = (1..=rules.jump_height.max(rules.step_height))
+                .take_while(|&dy| is_free(chunk_manager, rules, x, y + dy, z))
+                .find(|&dy| is_standable(chunk_manager, rules, nx, y + dy, nz));
+            if let Some(dy) = climbed {
+                let cost = if dy <= rules.step_height { WALK_COST } else { JUMP_COST };
+                moves.push(((nx, y + dy, nz), cost));
+            }
+            continue;
+        }
+
+        // Walk off a ledge
+        if is_free(chunk_manager, rules, nx, y, nz) {
+            let landing = (1..=rules.max_drop)
+                .take_while(|&dy| is_free(chunk_manager, rules, nx, y - dy, nz))
+                .find(|&dy| is_standable(chunk_manager, rules, nx, y - dy, nz));
+            if let Some(dy) = landing {
+                moves.push(((nx, y - dy, nz), WALK_COST + DROP_COST * dy as u32));
+            }
+        }
+    }
+    moves
+}
+
+/// Lowest cost of a walk from (x, z) to the goal, climbing costs nothing more than walking so the height is left out
+fn heuristic((x, _, z): (i32, i32, i32), (gx, _, gz): (i32, i32, i32)) -> u32 {
+    ((x - gx).abs() + (z - gz).abs()) as u32 * WALK_COST
+}
+
+/// A* search of a walk from the feet position `start` to `goal`, over the blocks with collision
+/// Returns the positions of the feet after every move, without `start`, or None if there is no path within the budget
+/// Nodes with the same estimate are expanded in the order they were reached, so the same world gives the same path
+pub fn find_path(chunk_manager: &ChunkManager, rules: &PathfindingRules, start: (i32, i32, i32), goal: (i32, i32, i32)) -> Option<Vec<(i32, i32, i32)>> {
+    if !is_standable(chunk_manager, rules, goal.0, goal.1, goal.2) {
+        return None;
+    }
+    if start == goal {
+        return Some(Vec::new());
+    }
+
+    // Lowest cost to reach each block, and where it was reached from
+    let mut costs = HashMap::new();
+    let mut came_from = HashMap::new();
+    let mut open = BinaryHeap::new();
+    let mut order = 0u64;
+    costs.insert(start, 0);
+    open.push(Reverse((heuristic(start, goal), order, start)));
+
+    while let Some(Reverse((_, _, node))) = open.pop() {
+        if node == goal {
+            let mut path = vec![node];
+            let mut node = node;
+            while let Some(&previous) = came_from.get(&node) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                node = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if costs.len() >= rules.max_nodes {
+            return None;
+        }
+
+        let cost = costs[&node];
+        for (next, move_cost) in moves(chunk_manager, rules, node) {
+            let next_cost = cost + move_cost;
+            if costs.get(&next).map_or(true, |&known_cost| next_cost < known_cost) {
+                costs.insert(next, next_cost);
+                came_from.insert(next, node);
+                order += 1;
+                open.push(Reverse((next_cost + heuristic(next, goal), order, next)));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::BlockID;
+    use crate::chunk_manager::test_util::{empty_world, fill};
+
+    use super::*;
+
+    const RULES: PathfindingRules = PathfindingRules {
+        height: 1,
+        step_height: 0,
+        jump_height: 1,
+        max_drop: 3,
+        max_nodes: 1000,
+    };
+
+    /// A column of chunks with a stone floor under y = 10
+    fn flat_world() -> ChunkManager {
+        let chunk_manager = empty_world(0..=0, 0..=0);
+        fill(&chunk_manager, BlockID::Stone, (0, 9, 0), (15, 9, 15));
+        chunk_manager
+    }
+
+    #[test]
+    fn walks_straight_to_the_goal() {
+        let chunk_manager = flat_world();
+        let path = find_path(&chunk_manager, &RULES, (1, 10, 1), (5, 10, 1));
+        assert_eq!(path, Some(vec![(2, 10, 1), (3, 10, 1), (4, 10, 1), (5, 10, 1)]));
+    }
+
+    #[test]
+    fn jumps_on_a_ledge() {
+        let chunk_manager = flat_world();
+        fill(&chunk_manager, BlockID::Stone, (3, 10, 0), (15, 10, 15));
+        let path = find_path(&chunk_manager, &RULES, (1, 10, 1), (5, 11, 1));
+        assert_eq!(path, Some(vec![(2, 10, 1), (3, 11, 1), (4, 11, 1), (5, 11, 1)]));
+    }
+
+    #[test]
+    fn drops_off_a_ledge() {
+        let chunk_manager = flat_world();
+        fill(&chunk_manager, BlockID::Stone, (0, 10, 0), (3, 12, 15));
+        let path = find_path(&chunk_manager, &RULES, (1, 13, 1), (6, 10, 1));
+        assert_eq!(path, Some(vec![(2, 13, 1), (3, 13, 1), (4, 10, 1), (5, 10, 1), (6, 10, 1)]));
+    }
+
+    #[test]
+    fn doesnt_drop_further_than_max_drop() {
+        let chunk_manager = flat_world();
+        fill(&chunk_manager, BlockID::Stone, (0, 10, 0), (3, 13, 15));
+        assert_eq!(find_path(&chunk_manager, &RULES, (1, 14, 1), (6, 10, 1)), None);
+    }
+
+    #[test]
+    fn gives_up_on_an_unreachable_goal() {
+        let chunk_manager = flat_world();
+        // A wall two blocks high can't be jumped over
+        fill(&chunk_manager, BlockID::Stone, (3, 10, 0), (3, 11, 15));
+        assert_eq!(find_path(&chunk_manager, &RULES, (1, 10, 1), (5, 10, 1)), None);
+        // Nor can the entity stand in a block
+        assert_eq!(find_path(&chunk_manager, &RULES, (1, 10, 1), (3, 10, 1)), None);
+    }
+
+    #[test]
+    fn heuristic_never_overestimates_a_move() {
+        let chunk_manager = flat_world();
+        fill(&chunk_manager, BlockID::Stone, (3, 10, 0), (15, 10, 15));
+        let rules = PathfindingRules { step_height: 1, ..RULES };
+        for &node in &[(2, 10, 1), (3, 11, 1)] {
+            for (next, cost) in moves(&chunk_manager, &rules, node) {
+                assert!(heuristic(node, next) <= cost, "{:?} -> {:?}", node, next);
+            }
+        }
+    }
+}
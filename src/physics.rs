@@ -2,6 +2,14 @@ use std::time;
 
 use std::time::Instant;
 
+use nalgebra_glm::{Vec3, vec3};
+use num_traits::Zero;
+
+use crate::aabb::{AABB, get_block_aabb};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::constants::{FLUID_GRAVITY_SCALE, GRAVITY, IN_AIR_FRICTION, MAX_SINKING_SPEED, MAX_VERTICAL_VELOCITY, ON_GROUND_FRICTION};
+
 /// Fixed timestep physics simulation using the following method:
 /// https://gafferongames.com/post/fix_your_timestep/
 /// With this method, the physics are always deterministic and work independently
@@ -58,6 +66,13 @@ impl<T: Clone + Interpolatable> Interpolator<T> {
         &self.interpolated_state
     }
 
+    /// Jumps to a state without interpolating from the previous one
+    pub fn reset(&mut self, state: T) {
+        self.previous_state = state.clone();
+        self.current_state = state.clone();
+        self.interpolated_state = state;
+    }
+
     /// Advances the physics for a given state.
     pub fn step(&mut self, time: Instant, integrate: &mut dyn FnMut(&T, f32, f32) -> T) {
         let now = time;
@@ -96,4 +111,497 @@ impl Interpolator<f32> {
             convergence * dt * target_camera_height + (1.0 - convergence * dt) * camera_height
         });
     }
-}
\ No newline at end of file
+}
+
+/// How an entity moves and collides with the blocks, every body is moved by the `EntityPhysics` system
+/// Whatever drives the entity (the keyboard, an AI) sets the acceleration of its `EntityPhysicsState`
+pub struct PhysicsBody {
+    // Size of the AABB
+    pub width: f32,
+    pub height: f32,
+    // 0 for bodies floating freely, like flying players, the fluids don't slow them down either
+    pub gravity_scale: f32,
+    // Friction in the air, the bodies on the ground or in a fluid slow down with their friction instead
+    pub drag: f32,
+    // Highest ledge walked onto without jumping
+    pub step_height: f32,
+    // Limits of the horizontal and vertical speeds
+    pub max_speed: f32,
+    pub max_vertical_speed: f32,
+    // Doesn't walk off the edges of the blocks, like sneaking players
+    pub stays_on_edges: bool,
+
+    pub is_on_ground: bool,
+    pub fluid: Option<BlockID>,
+    // Highest speed the body hit the ground at since it was last taken, for the fall damage
+    pub landing_speed: Option<f32>,
+    // Distance walked on the ground since it was last taken
+    pub walked_distance: f32,
+}
+
+impl PhysicsBody {
+    pub fn new(width: f32, height: f32) -> Self {
+        PhysicsBody {
+            width,
+            height,
+            gravity_scale: 1.0,
+            drag: IN_AIR_FRICTION,
+            step_height: 0.0,
+            max_speed: f32::INFINITY,
+            max_vertical_speed: MAX_VERTICAL_VELOCITY,
+            stays_on_edges: false,
+            is_on_ground: false,
+            fluid: None,
+            landing_speed: None,
+            walked_distance: 0.0,
+        }
+    }
+
+    /// The AABB of the body when its feet are at `position`
+    pub fn aabb_at(&self, position: &Vec3) -> AABB {
+        AABB::new(
+            vec3(position.x - self.width / 2.0, position.y, position.z - self.width / 2.0),
+            vec3(position.x + self.width / 2.0, position.y + self.height, position.z + self.width / 2.0))
+    }
+
+    fn is_floating(&self) -> bool {
+        self.gravity_scale.is_zero()
+    }
+}
+
+/// `position` is the center of the bottom of the AABB
+#[derive(Clone)]
+pub struct EntityPhysicsState {
+    pub position: Vec3,
+    pub aabb: AABB,
+    pub velocity: Vec3,
+    pub acceleration: Vec3,
+}
+
+impl EntityPhysicsState {
+    pub fn new_at_position(position: Vec3, body: &PhysicsBody) -> Self {
+        EntityPhysicsState {
+            position,
+            aabb: body.aabb_at(&position),
+            velocity: vec3(0.0, 0.0, 0.0),
+            acceleration: vec3(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// The same body standing still at another position
+    pub fn teleported_to(&self, position: Vec3) -> Self {
+        let mut aabb = self.aabb;
+        aabb.ip_translate(&(position - self.position));
+        EntityPhysicsState {
+            position,
+            aabb,
+            velocity: vec3(0.0, 0.0, 0.0),
+            acceleration: vec3(0.0, 0.0, 0.0),
+        }
+    }
+
+    /// Position of the block the feet are in
+    pub fn feet_block(&self) -> (i32, i32, i32) {
+        (self.position.x.floor() as i32, (self.position.y + 0.01).floor() as i32, self.position.z.floor() as i32)
+    }
+
+    /// We apply friction on the axes the body is either slowing down (a = 0) on or
+    /// accelerating in the opposite direction
+    fn apply_friction(&mut self, dt: f32, body: &PhysicsBody) {
+        let friction = if let Some(fluid) = body.fluid {
+            fluid.fluid_friction()
+        } else if body.is_on_ground {
+            ON_GROUND_FRICTION
+        } else {
+            body.drag
+        };
+
+        if self.acceleration.x.is_zero() || self.acceleration.x.signum() != self.velocity.x.signum() {
+            self.velocity.x -= friction * self.velocity.x * dt;
+        }
+        if self.acceleration.z.is_zero() || self.acceleration.z.signum() != self.velocity.z.signum() {
+            self.velocity.z -= friction * self.velocity.z * dt;
+        }
+        if body.is_floating() {
+            if self.acceleration.y.is_zero() || self.acceleration.y.signum() != self.velocity.y.signum() {
+                self.velocity.y -= ON_GROUND_FRICTION * self.velocity.y * dt;
+            }
+        }
+    }
+
+    fn limit_velocity(&mut self, body: &PhysicsBody) {
+        let mut max_speed = body.max_speed;
+        if let Some(fluid) = body.fluid.filter(|_| !body.is_floating()) {
+            self.velocity.y = self.velocity.y.max(-MAX_SINKING_SPEED);
+            max_speed *= fluid.fluid_speed_factor();
+        }
+
+        let speed = self.velocity.xz().norm();
+        if speed > max_speed {
+            self.velocity.x *= max_speed / speed;
+            self.velocity.z *= max_speed / speed;
+        }
+        // Limit the free falling speed
+        // https://www.planetminecraft.com/blog/the-acceleration-of-gravity-in-minecraft-and-terminal-velocity/
+        self.velocity.y = self.velocity.y.max(-body.max_vertical_speed).min(body.max_vertical_speed);
+    }
+
+    /// Moves the body by its velocity with the swept AABB collision against the blocks around it
+    fn move_and_collide(&mut self, dt: f32, body: &mut PhysicsBody, chunk_manager: &ChunkManager) {
+        let was_on_ground = body.is_on_ground;
+        let mut translation = self.velocity * dt;
+        let obstacles = get_block_aabbs_on_the_way(&self.aabb, &translation, body.step_height, chunk_manager);
+
+        // Don't let the body walk off the block it stands on
+        if body.stays_on_edges && was_on_ground && translation.y < 0.0 {
+            let mut aabb = self.aabb;
+            for &axis in &[0, 2] {
+                let mut moved = aabb;
+                moved.mins[axis] += translation[axis];
+                moved.maxs[axis] += translation[axis];
+                let mut below = moved;
+                if sweep_axis(&mut below, &obstacles, 1, translation.y) {
+                    aabb = moved;
+                } else {
+                    translation[axis] = 0.0;
+                    self.velocity[axis] = 0.0;
+                }
+            }
+        }
+
+        let before = self.aabb;
+        // The axes are moved along one after the other, x, z, then y
+        let mut stopped = [false; 3];
+        for &axis in &[0, 2, 1] {
+            stopped[axis] = sweep_axis(&mut self.aabb, &obstacles, axis, translation[axis]);
+        }
+
+        // Climb on the ledges lower than the step height, if it gets the body further
+        if was_on_ground && body.step_height > 0.0 && translation.y <= 0.0 && (stopped[0] || stopped[2]) {
+            let mut aabb = before;
+            let mut stepped_stopped = [false; 3];
+            sweep_axis(&mut aabb, &obstacles, 1, body.step_height);
+            for &axis in &[0, 2] {
+                stepped_stopped[axis] = sweep_axis(&mut aabb, &obstacles, axis, translation[axis]);
+            }
+            let climbed = aabb.mins.y - before.mins.y;
+            stepped_stopped[1] = sweep_axis(&mut aabb, &obstacles, 1, translation.y.min(0.0) - climbed);
+
+            let distance = |aabb: &AABB| (aabb.mins - before.mins).xz().norm_squared();
+            if stepped_stopped[1] && distance(&aabb) > distance(&self.aabb) {
+                self.aabb = aabb;
+                stopped = stepped_stopped;
+            }
+        }
+
+        let mut is_on_ground = false;
+        for axis in 0..3 {
+            if stopped[axis] {
+                self.velocity[axis] = 0.0;
+            }
+        }
+        if stopped[1] && translation.y < 0.0 {
+            is_on_ground = true;
+            let landing_speed = -translation.y / dt;
+            body.landing_speed = Some(body.landing_speed.map_or(landing_speed, |speed| speed.max(landing_speed)));
+        }
+        body.is_on_ground = is_on_ground;
+        if is_on_ground {
+            body.walked_distance += (self.aabb.mins - before.mins).xz().norm();
+        }
+
+        self.position = vec3(
+            self.aabb.mins.x + body.width / 2.0,
+            self.aabb.mins.y,
+            self.aabb.mins.z + body.width / 2.0);
+    }
+}
+
+impl Interpolatable for EntityPhysicsState {
+    fn interpolate(&self, alpha: f32, other: &Self) -> Self {
+        let interpolate_vec3 = |from: &Vec3, to: &Vec3| {
+            alpha * from + (1.0 - alpha) * to
+        };
+
+        Self {
+            position: interpolate_vec3(&self.position, &other.position),
+            aabb: AABB {
+                mins: interpolate_vec3(&self.aabb.mins, &other.aabb.mins),
+                maxs: interpolate_vec3(&self.aabb.maxs, &other.aabb.maxs),
+            },
+            velocity: interpolate_vec3(&self.velocity, &other.velocity),
+            acceleration: interpolate_vec3(&self.acceleration, &other.acceleration),
+        }
+    }
+}
+
+impl Interpolator<EntityPhysicsState> {
+    /// Integrates the acceleration of the body and the gravity, then moves it out of the blocks
+    /// The acceleration is kept for the next steps, until whatever drives the body changes it
+    pub fn update_body(&mut self, time: Instant, chunk_manager: &ChunkManager, body: &mut PhysicsBody) {
+        self.step(time, &mut |state, _t, dt| {
+            let mut state = state.clone();
+            body.fluid = get_fluid(&state.aabb, chunk_manager);
+            let gravity = match body.fluid {
+                Some(_) => GRAVITY * FLUID_GRAVITY_SCALE,
+                None => GRAVITY,
+            } * body.gravity_scale;
+
+            state.velocity += (state.acceleration + vec3(0.0, gravity, 0.0)) * dt;
+            state.apply_friction(dt, body);
+            state.limit_velocity(body);
+            state.move_and_collide(dt, body, chunk_manager);
+            state
+        });
+    }
+}
+
+// Distance under which two faces are touching, it makes up for the imprecision of floats
+const COLLISION_EPSILON: f32 = 1e-4;
+
+/// Broad phase of the collision detection
+/// The AABBs of the blocks with collision in the region swept by `aabb` moving by `translation`,
+/// and `step_height` above it
+pub fn get_block_aabbs_on_the_way(aabb: &AABB, translation: &Vec3, step_height: f32, chunk_manager: &ChunkManager) -> Vec<AABB> {
+    let moved_mins = aabb.mins + translation;
+    let moved_maxs = aabb.maxs + translation;
+    let mins = vec3(aabb.mins.x.min(moved_mins.x), aabb.mins.y.min(moved_mins.y), aabb.mins.z.min(moved_mins.z));
+    let maxs = vec3(aabb.maxs.x.max(moved_maxs.x), aabb.maxs.y.max(moved_maxs.y) + step_height, aabb.maxs.z.max(moved_maxs.z));
+
+    let mut block_aabbs = Vec::new();
+    for y in mins.y.floor() as i32..=maxs.y.floor() as i32 {
+        for z in mins.z.floor() as i32..=maxs.z.floor() as i32 {
+            for x in mins.x.floor() as i32..=maxs.x.floor() as i32 {
+                if chunk_manager.is_solid_block_at(x, y, z) {
+                    block_aabbs.push(get_block_aabb(&vec3(x as f32, y as f32, z as f32)));
+                }
+            }
+        }
+    }
+    block_aabbs
+}
+
+/// Narrow phase of the collision detection
+/// Moves `aabb` by `distance` along `axis` (0 for x, 1 for y, 2 for z), or until it touches the first of
+/// the `obstacles` on the way, however fast it goes. The obstacles it already intersects are ignored so it can get out of them
+/// Returns whether it was stopped by an obstacle
+pub fn sweep_axis(aabb: &mut AABB, obstacles: &[AABB], axis: usize, distance: f32) -> bool {
+    if distance.is_zero() {
+        return false;
+    }
+    let faces_each_other = |obstacle: &AABB| (0..3)
+        .filter(|&other_axis| other_axis != axis)
+        .all(|a| aabb.mins[a] < obstacle.maxs[a] - COLLISION_EPSILON && aabb.maxs[a] > obstacle.mins[a] + COLLISION_EPSILON);
+
+    // Face of the closest obstacle on the way
+    let mut stop: Option<f32> = None;
+    for obstacle in obstacles.iter().filter(|&obstacle| faces_each_other(obstacle)) {
+        if distance > 0.0 && obstacle.mins[axis] >= aabb.maxs[axis] - COLLISION_EPSILON {
+            if obstacle.mins[axis] < stop.unwrap_or(aabb.maxs[axis] + distance) {
+                stop = Some(obstacle.mins[axis]);
+            }
+        } else if distance < 0.0 && obstacle.maxs[axis] <= aabb.mins[axis] + COLLISION_EPSILON {
+            if obstacle.maxs[axis] > stop.unwrap_or(aabb.mins[axis] + distance) {
+                stop = Some(obstacle.maxs[axis]);
+            }
+        }
+    }
+
+    // The AABB is put against the face instead of translated, because of the imprecision of floats
+    let size = aabb.maxs[axis] - aabb.mins[axis];
+    match stop {
+        Some(face) if distance > 0.0 => {
+            aabb.maxs[axis] = face;
+            aabb.mins[axis] = face - size;
+        }
+        Some(face) => {
+            aabb.mins[axis] = face;
+            aabb.maxs[axis] = face + size;
+        }
+        None => {
+            aabb.mins[axis] += distance;
+            aabb.maxs[axis] += distance;
+        }
+    }
+    stop.is_some()
+}
+
+/// The fluid an entity is in, lava when it touches both
+pub fn get_fluid(aabb: &AABB, chunk_manager: &ChunkManager) -> Option<BlockID> {
+    let mut fluid = None;
+    for y in aabb.mins.y.floor() as i32..=aabb.maxs.y.floor() as i32 {
+        for z in aabb.mins.z.floor() as i32..=aabb.maxs.z.floor() as i32 {
+            for x in aabb.mins.x.floor() as i32..=aabb.maxs.x.floor() as i32 {
+                match chunk_manager.get_block(x, y, z) {
+                    Some(BlockID::Lava) => return Some(BlockID::Lava),
+                    Some(block) if block.is_fluid() => fluid = Some(block),
+                    _ => {}
+                }
+            }
+        }
+    }
+    fluid
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::chunk_manager::test_util::{empty_world, fill};
+
+    use super::*;
+
+    const PLAYER_WIDTH: f32 = 0.6;
+    const PLAYER_HEIGHT: f32 = 1.8;
+
+    /// A stone floor one block thick at y = 10, from (-10, -10) to (10, 10)
+    fn flat_world() -> ChunkManager {
+        let chunk_manager = empty_world(-1..=1, -1..=1);
+        fill(&chunk_manager, BlockID::Stone, (-10, 10, -10), (9, 10, 9));
+        chunk_manager
+    }
+
+    fn player_body() -> PhysicsBody {
+        PhysicsBody {
+            max_speed: 4.3,
+            ..PhysicsBody::new(PLAYER_WIDTH, PLAYER_HEIGHT)
+        }
+    }
+
+    /// Runs the physics at 60 updates per second for `seconds`, with a constant acceleration
+    fn simulate(chunk_manager: &ChunkManager, body: &mut PhysicsBody, state: EntityPhysicsState, acceleration: Vec3, seconds: f32) -> EntityPhysicsState {
+        let mut interpolator = Interpolator::new(1.0 / 60.0, state);
+        interpolator.get_latest_state_mut().acceleration = acceleration;
+        let start = interpolator.current_time;
+        for frame in 1..=(seconds * 60.0) as u32 {
+            interpolator.update_body(start + Duration::from_secs_f32(frame as f32 / 60.0), chunk_manager, body);
+        }
+        interpolator.get_latest_state().clone()
+    }
+
+    fn player_aabb_at(x: f32, y: f32, z: f32) -> AABB {
+        AABB::new(vec3(x, y, z), vec3(x + PLAYER_WIDTH, y + PLAYER_HEIGHT, z + PLAYER_WIDTH))
+    }
+
+    #[test]
+    fn sweep_doesnt_tunnel_through_a_thin_wall() {
+        let wall = get_block_aabb(&vec3(5.0, 0.0, 0.0));
+        let mut aabb = player_aabb_at(0.0, 0.0, 0.2);
+        assert!(sweep_axis(&mut aabb, &[wall], 0, 100.0));
+        assert_eq!(aabb.maxs.x, 5.0);
+        assert_eq!(aabb.mins.x, 5.0 - PLAYER_WIDTH);
+
+        // Touching the wall, it can only move away from it
+        assert!(sweep_axis(&mut aabb, &[wall], 0, 0.5));
+        assert_eq!(aabb.maxs.x, 5.0);
+        assert!(!sweep_axis(&mut aabb, &[wall], 0, -1.0));
+        assert_eq!(aabb.maxs.x, 4.0);
+    }
+
+    #[test]
+    fn sweep_stops_at_the_closest_obstacle() {
+        let near = get_block_aabb(&vec3(3.0, 0.0, 0.0));
+        let far = get_block_aabb(&vec3(5.0, 0.0, 0.0));
+        let mut aabb = player_aabb_at(0.0, 0.0, 0.2);
+        assert!(sweep_axis(&mut aabb, &[far, near], 0, 100.0));
+        assert_eq!(aabb.maxs.x, 3.0);
+
+        let mut aabb = player_aabb_at(8.0, 0.0, 0.2);
+        assert!(sweep_axis(&mut aabb, &[near, far], 0, -100.0));
+        assert_eq!(aabb.mins.x, 6.0);
+    }
+
+    #[test]
+    fn sweep_slides_along_a_face_and_ignores_overlapping_obstacles() {
+        let wall = get_block_aabb(&vec3(5.0, 0.0, 0.0));
+        let mut aabb = player_aabb_at(5.0 - PLAYER_WIDTH, 0.0, 0.2);
+        assert!(!sweep_axis(&mut aabb, &[wall], 2, 3.0));
+        assert_eq!(aabb.mins.z, 3.2);
+
+        // An entity stuck in a block can walk out of it
+        let mut aabb = AABB::new(vec3(5.2, 0.2, 0.2), vec3(5.8, 0.8, 0.8));
+        assert!(!sweep_axis(&mut aabb, &[wall], 0, 2.0));
+        assert_eq!(aabb.mins.x, 7.2);
+    }
+
+    #[test]
+    fn lands_at_terminal_velocity_on_a_thin_floor() {
+        let chunk_manager = flat_world();
+        let mut body = player_body();
+        let mut state = EntityPhysicsState::new_at_position(vec3(0.5, 40.0, 0.5), &body);
+        state.velocity.y = -90.0;
+        let state = simulate(&chunk_manager, &mut body, state, vec3(0.0, 0.0, 0.0), 2.0);
+        assert_eq!(state.position.y, 11.0);
+        assert!(body.is_on_ground);
+        assert!(body.landing_speed.unwrap() > 80.0);
+    }
+
+    #[test]
+    fn fast_body_stops_at_a_one_block_wall() {
+        let chunk_manager = flat_world();
+        chunk_manager.put_block(BlockID::Stone, 5, 11, 0);
+        chunk_manager.put_block(BlockID::Stone, 5, 12, 0);
+        let mut body = PhysicsBody {
+            max_speed: 500.0,
+            gravity_scale: 0.0,
+            ..PhysicsBody::new(PLAYER_WIDTH, PLAYER_HEIGHT)
+        };
+        let mut state = EntityPhysicsState::new_at_position(vec3(0.5, 11.0, 0.5), &body);
+        state.velocity.x = 400.0;
+        let state = simulate(&chunk_manager, &mut body, state, vec3(0.0, 0.0, 0.0), 0.5);
+        assert!((state.position.x - (5.0 - PLAYER_WIDTH / 2.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn walking_into_a_corner_stops_on_both_walls() {
+        let chunk_manager = flat_world();
+        for i in -3..3 {
+            for y in 11..=12 {
+                chunk_manager.put_block(BlockID::Stone, 3, y, i);
+                chunk_manager.put_block(BlockID::Stone, i, y, 2);
+            }
+        }
+        let mut body = player_body();
+        body.is_on_ground = true;
+        let state = EntityPhysicsState::new_at_position(vec3(0.5, 11.0, 0.5), &body);
+        let state = simulate(&chunk_manager, &mut body, state, vec3(21.0, 0.0, 21.0), 2.0);
+        assert!((state.position.x - (3.0 - PLAYER_WIDTH / 2.0)).abs() < 1e-5);
+        assert!((state.position.z - (2.0 - PLAYER_WIDTH / 2.0)).abs() < 1e-5);
+        assert_eq!(state.position.y, 11.0);
+    }
+
+    #[test]
+    fn climbs_a_ledge_only_as_high_as_its_step_height() {
+        let chunk_manager = flat_world();
+        for z in -9..-2 {
+            chunk_manager.put_block(BlockID::Stone, 0, 11, z);
+        }
+
+        let mut stepper = PhysicsBody { step_height: 1.0, ..player_body() };
+        stepper.is_on_ground = true;
+        let state = EntityPhysicsState::new_at_position(vec3(0.5, 11.0, -1.5), &stepper);
+        let stepped = simulate(&chunk_manager, &mut stepper, state.clone(), vec3(0.0, 0.0, -30.0), 1.0);
+        assert_eq!(stepped.position.y, 12.0);
+        // Stepping up isn't a fall
+        assert!(stepper.landing_speed.unwrap() < 1.0);
+
+        let mut walker = PhysicsBody { step_height: 0.6, ..player_body() };
+        walker.is_on_ground = true;
+        let blocked = simulate(&chunk_manager, &mut walker, state, vec3(0.0, 0.0, -30.0), 1.0);
+        assert_eq!(blocked.position.y, 11.0);
+        assert!((blocked.position.z - (-2.0 + PLAYER_WIDTH / 2.0)).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sneaking_body_stays_on_the_edge() {
+        let chunk_manager = flat_world();
+        let mut body = PhysicsBody { stays_on_edges: true, ..player_body() };
+        body.is_on_ground = true;
+        let state = EntityPhysicsState::new_at_position(vec3(-9.0, 11.0, -5.5), &body);
+        let state = simulate(&chunk_manager, &mut body, state, vec3(-30.0, 0.0, 0.0), 3.0);
+        assert_eq!(state.position.y, 11.0);
+        // The body hangs over the edge of the floor at x = -10, but stays on it
+        assert!(state.position.x < -9.9);
+        assert!(state.position.x + PLAYER_WIDTH / 2.0 > -10.0);
+    }
+}
@@ -1,16 +1,36 @@
 use std::time::Instant;
 
 use nalgebra::{clamp, Vector3};
-use nalgebra_glm::{IVec3, Mat4, pi, vec2, Vec3, vec3};
-use num_traits::Zero;
+use nalgebra_glm::{IVec3, Mat4, pi, Vec3, vec3};
 
-use crate::aabb::{AABB, get_block_aabb};
-use crate::chunk_manager::ChunkManager;
-use crate::constants::{FLYING_SPEED, FLYING_SPRINTING_SPEED, FOV, HORIZONTAL_ACCELERATION, IN_AIR_FRICTION, JUMP_IMPULSE, MAX_VERTICAL_VELOCITY, MOUSE_SENSITIVITY_X, MOUSE_SENSITIVITY_Y, ON_GROUND_FRICTION, PLAYER_EYES_HEIGHT, PLAYER_HALF_WIDTH, PLAYER_HEIGHT, PLAYER_WIDTH, SNEAKING_SPEED, SPRINTING_SPEED, WALKING_SPEED};
+use crate::chunk::BlockID;
+use crate::constants::{FLYING_SPEED, FLYING_SPRINTING_SPEED, FLYING_VERTICAL_SPEED, FOV, HORIZONTAL_ACCELERATION, JUMP_IMPULSE, MAX_VERTICAL_VELOCITY, MOUSE_SENSITIVITY_X, MOUSE_SENSITIVITY_Y, PLAYER_EYES_HEIGHT, PLAYER_HEIGHT, PLAYER_STEP_HEIGHT, PLAYER_WIDTH, SNEAKING_SPEED, SPRINTING_SPEED, WALKING_SPEED};
 use crate::input::InputCache;
-use crate::physics::{Interpolatable, Interpolator};
+use crate::inventory::item::Item;
+use crate::physics::{EntityPhysicsState, Interpolator, PhysicsBody};
 use crate::util::Forward;
 
+/// Creative: blocks break instantly, without dropping anything, and placing them is free
+/// Survival: blocks take time to break depending on their hardness and the held tool
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum GameMode {
+    Creative,
+    Survival,
+}
+
+/// Seconds needed to break a block in survival while holding an item, None for unbreakable blocks
+pub fn block_break_time(block: BlockID, held_item: Option<Item>) -> Option<f32> {
+    let hardness = block.hardness()?;
+    let is_right_tool = !block.requires_pickaxe() || held_item.map_or(false, |item| item.is_pickaxe());
+    let speed = held_item.map_or(1.0, |item| item.mining_speed(block));
+    Some(hardness * if is_right_tool { 1.5 } else { 5.0 } / speed)
+}
+
+/// Whether breaking a block in survival while holding an item drops it
+pub fn can_harvest(block: BlockID, held_item: Option<Item>) -> bool {
+    !block.requires_pickaxe() || held_item.map_or(false, |item| item.is_pickaxe())
+}
+
 pub struct PlayerState {
     pub rotation: Vec3,
     pub camera_height: Interpolator<f32>,
@@ -18,19 +38,24 @@ pub struct PlayerState {
     pub view_matrix: Mat4,
     pub projection_matrix: Mat4,
 
-    pub is_on_ground: bool,
     pub is_sneaking: bool,
     pub is_sprinting: bool,
     pub is_flying: bool,
 
     pub targeted_block: Option<((i32, i32, i32), IVec3)>,
 
+    pub game_mode: GameMode,
+    // Block being broken in survival, and how far along it is from 0 to 1
+    pub breaking_block: Option<(i32, i32, i32)>,
+    pub break_progress: f32,
+
     pub(crate) jump_last_executed: Instant,
     pub(crate) fly_throttle: bool,
     pub(crate) fly_last_toggled: Instant,
     pub(crate) sprint_throttle: bool,
     pub(crate) sprint_last_toggled: Instant,
     pub(crate) block_placing_last_executed: Instant,
+    pub(crate) break_progress_last_updated: Instant,
 }
 
 impl PlayerState {
@@ -42,19 +67,23 @@ impl PlayerState {
             view_matrix: Mat4::identity(),
             projection_matrix: Mat4::identity(),
 
-            is_on_ground: false,
             is_sneaking: false,
             is_sprinting: false,
             is_flying: false,
 
             targeted_block: None,
 
+            game_mode: GameMode::Survival,
+            breaking_block: None,
+            break_progress: 0.0,
+
             jump_last_executed: Instant::now(),
             fly_throttle: false,
             fly_last_toggled: Instant::now(),
             sprint_throttle: false,
             sprint_last_toggled: Instant::now(),
             block_placing_last_executed: Instant::now(),
+            break_progress_last_updated: Instant::now(),
         }
     }
 
@@ -67,51 +96,39 @@ impl PlayerState {
             -pi::<f32>() / 2.0 + 0.0001,
             pi::<f32>() / 2.0 - 0.0001);
     }
-}
-
-#[derive(Clone)]
-pub struct PlayerPhysicsState {
-    pub position: Vec3,
-    pub aabb: AABB,
-    pub velocity: Vec3,
-    pub acceleration: Vec3,
-}
 
-impl PlayerPhysicsState {
-    pub fn new_at_position(position: Vec3) -> Self {
-        PlayerPhysicsState {
-            position,
-            aabb: {
-                let mins = vec3(position.x - PLAYER_HALF_WIDTH, position.y, position.z - PLAYER_HALF_WIDTH);
-                let maxs = vec3(position.x + PLAYER_HALF_WIDTH, position.y + PLAYER_HEIGHT, position.z + PLAYER_HALF_WIDTH);
-                AABB::new(mins, maxs)
-            },
-            velocity: vec3(0.0, 0.0, 0.0),
-            acceleration: vec3(0.0, 0.0, 0.0),
-        }
+    /// Speed limits and gravity of the body of the player depending on how it moves
+    pub fn configure_body(&self, body: &mut PhysicsBody, input_cache: &InputCache) {
+        body.gravity_scale = if self.is_flying { 0.0 } else { 1.0 };
+        body.max_vertical_speed = if self.is_flying { FLYING_VERTICAL_SPEED } else { MAX_VERTICAL_VELOCITY };
+        body.max_speed = if self.is_flying {
+            if self.is_sprinting {
+                FLYING_SPRINTING_SPEED
+            } else {
+                FLYING_SPEED
+            }
+        } else {
+            if self.is_sprinting {
+                SPRINTING_SPEED
+            } else if self.is_sneaking {
+                SNEAKING_SPEED
+            } else {
+                WALKING_SPEED
+            }
+        };
+        body.stays_on_edges = input_cache.is_key_pressed(glfw::Key::LeftShift);
     }
 }
 
-impl Interpolatable for PlayerPhysicsState {
-    fn interpolate(&self, alpha: f32, other: &Self) -> Self {
-        let interpolate_vec3 = |from: &Vec3, to: &Vec3| {
-            alpha * from + (1.0 - alpha) * to
-        };
-
-        Self {
-            position: interpolate_vec3(&self.position, &other.position),
-            aabb: AABB {
-                mins: interpolate_vec3(&self.aabb.mins, &other.aabb.mins),
-                maxs: interpolate_vec3(&self.aabb.maxs, &other.aabb.maxs),
-            },
-            velocity: interpolate_vec3(&self.velocity, &other.velocity),
-            acceleration: interpolate_vec3(&self.acceleration, &other.acceleration),
-        }
+pub fn player_physics_body() -> PhysicsBody {
+    PhysicsBody {
+        step_height: PLAYER_STEP_HEIGHT,
+        ..PhysicsBody::new(PLAYER_WIDTH, PLAYER_HEIGHT)
     }
 }
 
-impl PlayerPhysicsState {
-    pub fn apply_keyboard_mouvement(&mut self, player_properties: &mut PlayerState, input_cache: &InputCache) {
+impl EntityPhysicsState {
+    pub fn apply_keyboard_mouvement(&mut self, player_properties: &mut PlayerState, body: &PhysicsBody, input_cache: &InputCache) {
         let rotation = &player_properties.rotation;
         if player_properties.is_flying {
             if input_cache.is_key_pressed(glfw::Key::Space) {
@@ -126,7 +143,7 @@ impl PlayerPhysicsState {
         if input_cache.is_key_pressed(glfw::Key::Space) {
             let now = Instant::now();
             if now.duration_since(player_properties.jump_last_executed).as_secs_f32() >= 0.475 {
-                if player_properties.is_on_ground {
+                if body.is_on_ground {
                     self.velocity.y = *JUMP_IMPULSE;
                     player_properties.jump_last_executed = now;
                 }
@@ -153,143 +170,4 @@ impl PlayerPhysicsState {
             self.acceleration += horizontal_acceleration;
         }
     }
-
-    pub fn get_colliding_block_coords(&self, chunk_manager: &ChunkManager) -> Option<Vec3> {
-        let player_mins = &self.aabb.mins;
-        let player_maxs = &self.aabb.maxs;
-
-        let block_mins = vec3(
-            player_mins.x.floor() as i32, player_mins.y.floor() as i32, player_mins.z.floor() as i32,
-        );
-        let block_maxs = vec3(
-            player_maxs.x.floor() as i32, player_maxs.y.floor() as i32, player_maxs.z.floor() as i32,
-        );
-
-        // We query all the blocks around the player to check whether it's colliding with one of them
-        let mut colliding_block = None;
-        for y in block_mins.y..=block_maxs.y {
-            for z in block_mins.z..=block_maxs.z {
-                for x in block_mins.x..=block_maxs.x {
-                    if let Some(block) = chunk_manager.get_block(x, y, z) {
-                        if !block.is_air() {
-                            let block_aabb = get_block_aabb(&vec3(x as f32, y as f32, z as f32));
-                            if self.aabb.intersects(&block_aabb) {
-                                colliding_block = Some(vec3(x as f32, y as f32, z as f32));
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        colliding_block
-    }
-
-    pub fn separate_from_block(&mut self, v: &Vec3, block_coords: &Vec3) -> bool {
-        let mut is_player_on_ground = false;
-        let block_aabb = get_block_aabb(&block_coords);
-
-        if !v.x.is_zero() {
-            if v.x < 0.0 {
-                // I've opted to create a new AABB instead of translating the old one
-                // because of the imprecision of floats.
-                self.aabb = AABB::new(
-                    vec3(block_aabb.maxs.x, self.aabb.mins.y, self.aabb.mins.z),
-                    vec3(block_aabb.maxs.x + PLAYER_WIDTH, self.aabb.maxs.y, self.aabb.maxs.z));
-            } else {
-                self.aabb = AABB::new(
-                    vec3(block_aabb.mins.x - PLAYER_WIDTH, self.aabb.mins.y, self.aabb.mins.z),
-                    vec3(block_aabb.mins.x, self.aabb.maxs.y, self.aabb.maxs.z));
-            }
-            self.velocity.x = 0.0
-        }
-
-        if !v.y.is_zero() {
-            if v.y < 0.0 {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, block_aabb.maxs.y, self.aabb.mins.z),
-                    vec3(self.aabb.maxs.x, block_aabb.maxs.y + PLAYER_HEIGHT, self.aabb.maxs.z));
-                is_player_on_ground = true;
-            } else {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, block_aabb.mins.y - PLAYER_HEIGHT, self.aabb.mins.z),
-                    vec3(self.aabb.maxs.x, block_aabb.mins.y, self.aabb.maxs.z));
-            }
-            self.velocity.y = 0.0;
-        }
-
-        if !v.z.is_zero() {
-            if v.z < 0.0 {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, self.aabb.mins.y, block_aabb.maxs.z),
-                    vec3(self.aabb.maxs.x, self.aabb.maxs.y, block_aabb.maxs.z + PLAYER_WIDTH));
-            } else {
-                self.aabb = AABB::new(
-                    vec3(self.aabb.mins.x, self.aabb.mins.y, block_aabb.mins.z - PLAYER_WIDTH),
-                    vec3(self.aabb.maxs.x, self.aabb.maxs.y, block_aabb.mins.z));
-            }
-            self.velocity.z = 0.0
-        }
-        is_player_on_ground
-    }
-
-    pub fn apply_friction(&mut self, dt: f32, player_state: &PlayerState) {
-        let friction = if player_state.is_on_ground {
-            ON_GROUND_FRICTION
-        } else {
-            IN_AIR_FRICTION
-        };
-
-        // We apply friction if the player is either slowing down (a = 0) or
-        // walks in the opposite direction
-        if self.acceleration.x.is_zero() || self.acceleration.x.signum() != self.velocity.x.signum() {
-            self.velocity.x -= friction * self.velocity.x * dt;
-        }
-        if self.acceleration.z.is_zero() || self.acceleration.z.signum() != self.velocity.z.signum() {
-            self.velocity.z -= friction * self.velocity.z * dt;
-        }
-        if player_state.is_flying {
-            if self.acceleration.y.is_zero() || self.acceleration.y.signum() != self.velocity.y.signum() {
-                self.velocity.y -= ON_GROUND_FRICTION * self.velocity.y * dt;
-            }
-        }
-    }
-
-    pub fn limit_velocity(&mut self, player_properties: &PlayerState) {
-        // Limit the horizontal speed
-        let mut horizontal_vel = vec2(self.velocity.x, self.velocity.z);
-        let speed = horizontal_vel.magnitude();
-
-        let max_speed = if player_properties.is_flying {
-            self.velocity.y = clamp(self.velocity.y, -8.0, 8.0);
-            if player_properties.is_sprinting {
-                FLYING_SPRINTING_SPEED
-            } else {
-                FLYING_SPEED
-            }
-        } else {
-            if player_properties.is_sprinting {
-                SPRINTING_SPEED
-            } else if player_properties.is_sneaking {
-                SNEAKING_SPEED
-            } else {
-                WALKING_SPEED
-            }
-        };
-
-        if speed > max_speed {
-            horizontal_vel = horizontal_vel.scale(max_speed / speed);
-        }
-
-        self.velocity.x = horizontal_vel.x;
-        self.velocity.z = horizontal_vel.y;
-
-        // Limit the free falling speed (vertical)
-        // https://www.planetminecraft.com/blog/the-acceleration-of-gravity-in-minecraft-and-terminal-velocity/
-        if self.velocity.y < -MAX_VERTICAL_VELOCITY {
-            self.velocity.y = -MAX_VERTICAL_VELOCITY;
-        }
-    }
 }
-
-
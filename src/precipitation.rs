@@ -0,0 +1,181 @@
+use std::ffi::c_void;
+use std::ptr::null;
+use std::time::Instant;
+
+use nalgebra_glm::{Mat4, Vec3, vec3};
+use rand::{Rng, thread_rng};
+
+use crate::chunk_manager::ChunkManager;
+use crate::shader_compilation::ShaderProgram;
+use crate::weather::{Precipitation, Weather};
+
+// Number of raindrops or snowflakes around the camera during a whole shower
+const MAX_DROPS: usize = 2000;
+// Horizontal distance from the camera to the farthest drops
+const DROPS_RADIUS: f32 = 16.0;
+// Drops fall from this height above the camera, and disappear as far below it
+const DROPS_HEIGHT: f32 = 12.0;
+
+const RAIN_SPEED: f32 = 14.0;
+const RAIN_STREAK_LENGTH: f32 = 0.6;
+const RAIN_STREAK_WIDTH: f32 = 0.03;
+const RAIN_COLOR: [f32; 4] = [0.55, 0.6, 0.75, 0.6];
+
+const SNOW_SPEED: f32 = 1.5;
+// Snowflakes drift from side to side while they fall
+const SNOW_SWAY: f32 = 0.4;
+const SNOWFLAKE_SIZE: f32 = 0.08;
+const SNOW_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.9];
+
+// Position (3 floats) and color (4 floats) of every vertex
+const VERTEX_SIZE: usize = 7;
+
+struct Drop {
+    position: Vec3,
+    kind: Precipitation,
+    sway_phase: f32,
+}
+
+/// Rain streaks and snowflakes falling around the camera, stopped by the highest block of their column
+pub struct PrecipitationRenderer {
+    drops: Vec<Drop>,
+    last_updated: Instant,
+    vao: u32,
+    vbo: u32,
+}
+
+impl PrecipitationRenderer {
+    pub fn new() -> Self {
+        let mut vao = 0;
+        gl_call!(gl::CreateVertexArrays(1, &mut vao));
+
+        // Position
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 0));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 0, 3, gl::FLOAT, gl::FALSE, 0));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 0, 0));
+
+        // Color
+        gl_call!(gl::EnableVertexArrayAttrib(vao, 1));
+        gl_call!(gl::VertexArrayAttribFormat(vao, 1, 4, gl::FLOAT, gl::FALSE, (3 * std::mem::size_of::<f32>()) as u32));
+        gl_call!(gl::VertexArrayAttribBinding(vao, 1, 0));
+
+        let mut vbo = 0;
+        gl_call!(gl::CreateBuffers(1, &mut vbo));
+        gl_call!(gl::NamedBufferData(vbo,
+                (MAX_DROPS * 6 * VERTEX_SIZE * std::mem::size_of::<f32>()) as isize,
+                null(),
+                gl::DYNAMIC_DRAW));
+        gl_call!(gl::VertexArrayVertexBuffer(vao, 0, vbo, 0, (VERTEX_SIZE * std::mem::size_of::<f32>()) as i32));
+
+        PrecipitationRenderer {
+            drops: Vec::with_capacity(MAX_DROPS),
+            last_updated: Instant::now(),
+            vao,
+            vbo,
+        }
+    }
+
+    /// Moves the drops down, replacing those which reached the ground or are too far from the camera
+    pub fn update(&mut self, time: Instant, camera_position: &Vec3, weather: &Weather, chunk_manager: &ChunkManager) {
+        let dt = time.saturating_duration_since(self.last_updated).as_secs_f32();
+        self.last_updated = time;
+
+        for drop in &mut self.drops {
+            match drop.kind {
+                Precipitation::Rain => drop.position.y -= RAIN_SPEED * dt,
+                Precipitation::Snow => {
+                    drop.sway_phase += dt;
+                    drop.position.x += drop.sway_phase.sin() * SNOW_SWAY * dt;
+                    drop.position.z += drop.sway_phase.cos() * SNOW_SWAY * dt;
+                    drop.position.y -= SNOW_SPEED * dt;
+                }
+            }
+        }
+
+        let ground_height = |position: &Vec3| {
+            chunk_manager.heighest_block_at(position.x.floor() as i32, position.z.floor() as i32)
+                .map(|y| (y + 1) as f32)
+        };
+        let is_around_camera = |position: &Vec3| {
+            let offset = position - camera_position;
+            offset.xz().norm() < DROPS_RADIUS && offset.y > -DROPS_HEIGHT
+        };
+        self.drops.retain(|drop| is_around_camera(&drop.position)
+            && ground_height(&drop.position).map_or(false, |ground| drop.position.y > ground));
+
+        let target_drops = (MAX_DROPS as f32 * weather.precipitation_strength()) as usize;
+        self.drops.truncate(target_drops);
+
+        // Drops which can't be placed because the ground is too high are tried again next frame
+        let mut rng = thread_rng();
+        for _ in self.drops.len()..target_drops {
+            let angle = rng.gen_range(0.0, 2.0 * std::f32::consts::PI);
+            let distance = DROPS_RADIUS * rng.gen::<f32>().sqrt();
+            let position = camera_position + vec3(
+                angle.cos() * distance,
+                rng.gen_range(-DROPS_HEIGHT, DROPS_HEIGHT),
+                angle.sin() * distance);
+            if ground_height(&position).map_or(true, |ground| position.y <= ground) {
+                continue;
+            }
+            let (x, y, z) = (position.x.floor() as i32, position.y.floor() as i32, position.z.floor() as i32);
+            if let Some(kind) = weather.precipitation_at(x, y, z) {
+                self.drops.push(Drop {
+                    position,
+                    kind,
+                    sway_phase: rng.gen_range(0.0, 2.0 * std::f32::consts::PI),
+                });
+            }
+        }
+    }
+
+    pub fn render(&self, shader: &mut ShaderProgram, view_matrix: &Mat4, projection_matrix: &Mat4) {
+        if self.drops.is_empty() {
+            return;
+        }
+
+        // Drops always face the camera, rain streaks stay vertical
+        let camera_right = vec3(view_matrix[(0, 0)], view_matrix[(0, 1)], view_matrix[(0, 2)]);
+        let camera_up = vec3(view_matrix[(1, 0)], view_matrix[(1, 1)], view_matrix[(1, 2)]);
+        let horizontal_right = vec3(camera_right.x, 0.0, camera_right.z)
+            .try_normalize(1.0e-6)
+            .unwrap_or_else(|| vec3(1.0, 0.0, 0.0));
+
+        let mut vbo_data: Vec<f32> = Vec::with_capacity(self.drops.len() * 6 * VERTEX_SIZE);
+        for drop in &self.drops {
+            let (right, up, color) = match drop.kind {
+                Precipitation::Rain => (
+                    horizontal_right * RAIN_STREAK_WIDTH / 2.0,
+                    vec3(0.0, RAIN_STREAK_LENGTH / 2.0, 0.0),
+                    &RAIN_COLOR),
+                Precipitation::Snow => (
+                    camera_right * SNOWFLAKE_SIZE / 2.0,
+                    camera_up * SNOWFLAKE_SIZE / 2.0,
+                    &SNOW_COLOR),
+            };
+            let corners = [
+                drop.position - right - up,
+                drop.position + right - up,
+                drop.position + right + up,
+                drop.position + right + up,
+                drop.position - right + up,
+                drop.position - right - up,
+            ];
+            for corner in &corners {
+                vbo_data.extend(corner.iter());
+                vbo_data.extend(color);
+            }
+        }
+
+        gl_call!(gl::NamedBufferSubData(self.vbo,
+                0,
+                (vbo_data.len() * std::mem::size_of::<f32>()) as isize,
+                vbo_data.as_ptr() as *mut c_void));
+
+        shader.use_program();
+        shader.set_uniform_matrix4fv("view", view_matrix.as_ptr());
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        gl_call!(gl::BindVertexArray(self.vao));
+        gl_call!(gl::DrawArrays(gl::TRIANGLES, 0, 6 * self.drops.len() as i32));
+    }
+}
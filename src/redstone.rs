@@ -0,0 +1,373 @@
+use std::collections::HashMap;
+
+use crate::block_tick::{BlockBehaviour, TickContext};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+
+/// Signal of the power sources, a wire carries one less than its strongest neighbour
+pub const MAX_POWER: u8 = 15;
+// State of the levers and buttons which are on
+const SWITCH_ON: u8 = 1;
+// A repeater stores the direction of its output in the first 2 bits of its state,
+// its delay minus one in the next 2 and whether it is powered in the 5th
+const REPEATER_FACING_MASK: u8 = 0b00011;
+const REPEATER_DELAY_MASK: u8 = 0b01100;
+const REPEATER_DELAY_SHIFT: u8 = 2;
+const REPEATER_POWERED: u8 = 0b10000;
+// Physics ticks in one step of the delay of a repeater, 0.1 s
+const REDSTONE_TICK: u64 = 6;
+// Physics ticks a button stays pressed, 1 s
+const BUTTON_PRESS_TICKS: u64 = 60;
+// Wires further than this many wires from a changed wire keep their signal
+const MAX_WIRE_NETWORK: usize = 4096;
+// The output directions of the repeaters, by the facing stored in their state
+const FACINGS: [(i32, i32); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+
+fn neighbours((x, y, z): (i32, i32, i32)) -> [(i32, i32, i32); 6] {
+    [
+        (x + 1, y, z), (x - 1, y, z),
+        (x, y + 1, z), (x, y - 1, z),
+        (x, y, z + 1), (x, y, z - 1),
+    ]
+}
+
+fn is_wire(chunk_manager: &ChunkManager, (x, y, z): (i32, i32, i32)) -> bool {
+    chunk_manager.get_block(x, y, z) == Some(BlockID::RedstoneWire)
+}
+
+fn repeater_delay(state: u8) -> u64 {
+    ((state & REPEATER_DELAY_MASK) >> REPEATER_DELAY_SHIFT) as u64 + 1
+}
+
+/// The block powered by a repeater
+fn repeater_output((x, y, z): (i32, i32, i32), state: u8) -> (i32, i32, i32) {
+    let (dx, dz) = FACINGS[(state & REPEATER_FACING_MASK) as usize];
+    (x + dx, y, z + dz)
+}
+
+/// The block powering a repeater
+fn repeater_input((x, y, z): (i32, i32, i32), state: u8) -> (i32, i32, i32) {
+    let (dx, dz) = FACINGS[(state & REPEATER_FACING_MASK) as usize];
+    (x - dx, y, z - dz)
+}
+
+/// Power sent by the block at `from` to its neighbour at `to`
+/// Levers and buttons power all their neighbours, wires too with their strength, repeaters only the block in front
+pub fn power_emitted(chunk_manager: &ChunkManager, from: (i32, i32, i32), to: (i32, i32, i32)) -> u8 {
+    let (x, y, z) = from;
+    let (block, state) = match (chunk_manager.get_block(x, y, z), chunk_manager.get_block_state(x, y, z)) {
+        (Some(block), Some(state)) => (block, state),
+        _ => return 0,
+    };
+    match block {
+        BlockID::Lever | BlockID::StoneButton if state & SWITCH_ON != 0 => MAX_POWER,
+        BlockID::RedstoneWire => state,
+        BlockID::Repeater if state & REPEATER_POWERED != 0 && repeater_output(from, state) == to => MAX_POWER,
+        _ => 0,
+    }
+}
+
+/// Strongest power sent to `position` by its neighbours, leaving out the wires if `ignore_wires` is set
+fn power_received(chunk_manager: &ChunkManager, position: (i32, i32, i32), ignore_wires: bool) -> u8 {
+    neighbours(position).iter()
+        .filter(|&&neighbour| !ignore_wires || !is_wire(chunk_manager, neighbour))
+        .map(|&neighbour| power_emitted(chunk_manager, neighbour, position))
+        .max()
+        .unwrap_or(0)
+}
+
+/// The new strength of the wires connected to the wire at (x, y, z) whose signal has to change
+/// Wires next to each other, above and below included, are connected and the signal decays by 1 per wire
+/// The world is only read, the result depends on nothing else than the blocks around the wires
+pub fn solve_wires(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> Vec<((i32, i32, i32), u8)> {
+    // The wires in the order they are reached from (x, y, z)
+    let mut wires = Vec::new();
+    let mut indices = HashMap::new();
+    if is_wire(chunk_manager, (x, y, z)) {
+        indices.insert((x, y, z), 0);
+        wires.push((x, y, z));
+    }
+    let mut i = 0;
+    while i < wires.len() {
+        for &neighbour in &neighbours(wires[i]) {
+            if wires.len() < MAX_WIRE_NETWORK && !indices.contains_key(&neighbour) && is_wire(chunk_manager, neighbour) {
+                indices.insert(neighbour, wires.len());
+                wires.push(neighbour);
+            }
+        }
+        i += 1;
+    }
+
+    // The signal spreads from the strongest wires, starting with the power from the other blocks
+    let mut strengths: Vec<u8> = wires.iter()
+        .map(|&wire| power_received(chunk_manager, wire, true))
+        .collect();
+    let mut by_strength = vec![Vec::new(); MAX_POWER as usize + 1];
+    for (i, &strength) in strengths.iter().enumerate() {
+        by_strength[strength as usize].push(i);
+    }
+    for strength in (2..=MAX_POWER).rev() {
+        for i in std::mem::take(&mut by_strength[strength as usize]) {
+            // The wire got a stronger signal since
+            if strengths[i] != strength {
+                continue;
+            }
+            for neighbour in &neighbours(wires[i]) {
+                if let Some(&j) = indices.get(neighbour) {
+                    if strengths[j] < strength - 1 {
+                        strengths[j] = strength - 1;
+                        by_strength[strength as usize - 1].push(j);
+                    }
+                }
+            }
+        }
+    }
+
+    wires.into_iter()
+        .zip(strengths)
+        .filter(|&((x, y, z), strength)| chunk_manager.get_block_state(x, y, z) != Some(strength))
+        .collect()
+}
+
+/// Levers are switched on and off by right clicking them
+pub struct Lever;
+
+impl BlockBehaviour for Lever {
+    fn used(&self, context: &TickContext, x: i32, y: i32, z: i32) -> bool {
+        let chunk_manager = context.chunk_manager;
+        if let Some(state) = chunk_manager.get_block_state(x, y, z) {
+            chunk_manager.put_block_with_state(BlockID::Lever, state ^ SWITCH_ON, x, y, z);
+        }
+        true
+    }
+}
+
+/// Buttons are on for a second after being pressed
+pub struct Button;
+
+impl BlockBehaviour for Button {
+    fn used(&self, context: &TickContext, x: i32, y: i32, z: i32) -> bool {
+        let chunk_manager = context.chunk_manager;
+        if chunk_manager.get_block_state(x, y, z) == Some(0) {
+            chunk_manager.put_block_with_state(BlockID::StoneButton, SWITCH_ON, x, y, z);
+            chunk_manager.schedule_tick(BlockID::StoneButton, x, y, z, BUTTON_PRESS_TICKS, 0);
+        }
+        true
+    }
+
+    fn scheduled_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        context.chunk_manager.put_block_with_state(BlockID::StoneButton, 0, x, y, z);
+    }
+}
+
+/// Wires carry the signal of the power sources, their state is their signal strength
+pub struct Wire;
+
+impl BlockBehaviour for Wire {
+    fn neighbour_changed(&self, context: &TickContext, x: i32, y: i32, z: i32, _from: (i32, i32, i32)) {
+        for ((x, y, z), strength) in solve_wires(context.chunk_manager, x, y, z) {
+            context.chunk_manager.put_block_with_state(BlockID::RedstoneWire, strength, x, y, z);
+        }
+    }
+}
+
+/// Repeaters power the block in front of them at full strength when the block behind them is powered,
+/// after a delay of 1 to 4 steps which is changed by right clicking them
+pub struct Repeater;
+
+impl BlockBehaviour for Repeater {
+    fn neighbour_changed(&self, context: &TickContext, x: i32, y: i32, z: i32, _from: (i32, i32, i32)) {
+        let chunk_manager = context.chunk_manager;
+        let state = match chunk_manager.get_block_state(x, y, z) {
+            Some(state) => state,
+            None => return,
+        };
+        let is_powered = power_emitted(chunk_manager, repeater_input((x, y, z), state), (x, y, z)) > 0;
+        if is_powered != (state & REPEATER_POWERED != 0) {
+            chunk_manager.schedule_tick(BlockID::Repeater, x, y, z, repeater_delay(state) * REDSTONE_TICK, 0);
+        }
+    }
+
+    fn scheduled_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        let chunk_manager = context.chunk_manager;
+        let state = match chunk_manager.get_block_state(x, y, z) {
+            Some(state) => state,
+            None => return,
+        };
+        let new_state = if power_emitted(chunk_manager, repeater_input((x, y, z), state), (x, y, z)) > 0 {
+            state | REPEATER_POWERED
+        } else {
+            state & !REPEATER_POWERED
+        };
+        if new_state != state {
+            chunk_manager.put_block_with_state(BlockID::Repeater, new_state, x, y, z);
+        }
+    }
+
+    fn used(&self, context: &TickContext, x: i32, y: i32, z: i32) -> bool {
+        let chunk_manager = context.chunk_manager;
+        if let Some(state) = chunk_manager.get_block_state(x, y, z) {
+            let delay = (state & REPEATER_DELAY_MASK).wrapping_add(1 << REPEATER_DELAY_SHIFT) & REPEATER_DELAY_MASK;
+            chunk_manager.put_block_with_state(BlockID::Repeater, state & !REPEATER_DELAY_MASK | delay, x, y, z);
+        }
+        true
+    }
+
+    /// The output faces away from the player
+    fn placed_state(&self, facing: (i32, i32)) -> u8 {
+        FACINGS.iter().position(|&direction| direction == facing).unwrap_or(0) as u8
+    }
+}
+
+/// Lamps are lit while a neighbour powers them
+pub struct Lamp;
+
+impl BlockBehaviour for Lamp {
+    fn neighbour_changed(&self, context: &TickContext, x: i32, y: i32, z: i32, _from: (i32, i32, i32)) {
+        let chunk_manager = context.chunk_manager;
+        let is_powered = power_received(chunk_manager, (x, y, z), false) > 0;
+        match chunk_manager.get_block(x, y, z) {
+            Some(BlockID::RedstoneLamp) if is_powered => {
+                chunk_manager.put_block(BlockID::LitRedstoneLamp, x, y, z);
+            }
+            Some(BlockID::LitRedstoneLamp) if !is_powered => {
+                chunk_manager.put_block(BlockID::RedstoneLamp, x, y, z);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk_manager::test_util::empty_world;
+
+    use super::*;
+
+    // Height of the wires, away from the bottom of the world
+    const Y: i32 = 20;
+
+    /// Wires along x from `from` to `to` included, without any signal
+    fn wire_line(chunk_manager: &ChunkManager, from: i32, to: i32) {
+        for x in from..=to {
+            chunk_manager.put_block_with_state(BlockID::RedstoneWire, 0, x, Y, 0);
+        }
+    }
+
+    fn switch_on_lever(chunk_manager: &ChunkManager, x: i32) {
+        chunk_manager.put_block_with_state(BlockID::Lever, SWITCH_ON, x, Y, 0);
+    }
+
+    /// Solves the network of the wire at x and stores the new strengths, like `Wire` does
+    fn solve_and_apply(chunk_manager: &ChunkManager, x: i32) {
+        for ((x, y, z), strength) in solve_wires(chunk_manager, x, Y, 0) {
+            chunk_manager.put_block_with_state(BlockID::RedstoneWire, strength, x, y, z);
+        }
+    }
+
+    fn strengths(chunk_manager: &ChunkManager, from: i32, to: i32) -> Vec<u8> {
+        (from..=to).map(|x| chunk_manager.get_block_state(x, Y, 0).unwrap()).collect()
+    }
+
+    #[test]
+    fn signal_decays_along_the_wire() {
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        switch_on_lever(&chunk_manager, 0);
+        wire_line(&chunk_manager, 1, 16);
+        solve_and_apply(&chunk_manager, 1);
+        let expected: Vec<u8> = (0..=MAX_POWER).rev().collect();
+        assert_eq!(strengths(&chunk_manager, 1, 16), expected);
+    }
+
+    #[test]
+    fn strongest_source_wins_where_two_signals_meet() {
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        switch_on_lever(&chunk_manager, 0);
+        switch_on_lever(&chunk_manager, 10);
+        wire_line(&chunk_manager, 1, 9);
+        solve_and_apply(&chunk_manager, 9);
+        assert_eq!(strengths(&chunk_manager, 1, 9), vec![15, 14, 13, 12, 11, 12, 13, 14, 15]);
+    }
+
+    #[test]
+    fn breaking_a_wire_cuts_the_signal_after_it() {
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        switch_on_lever(&chunk_manager, 0);
+        wire_line(&chunk_manager, 1, 10);
+        solve_and_apply(&chunk_manager, 1);
+
+        chunk_manager.put_block(BlockID::Air, 5, Y, 0);
+        // The wires still connected to the lever keep their signal
+        assert!(solve_wires(&chunk_manager, 4, Y, 0).is_empty());
+        solve_and_apply(&chunk_manager, 6);
+        assert_eq!(strengths(&chunk_manager, 1, 4), vec![15, 14, 13, 12]);
+        assert_eq!(strengths(&chunk_manager, 6, 10), vec![0; 5]);
+    }
+
+    #[test]
+    fn large_networks_are_cut_off() {
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        // A cube of 16 * 16 * 17 wires, all with a signal left over from a source which is gone
+        let mut wire_count = 0;
+        for x in 0..16 {
+            for y in Y..Y + 17 {
+                for z in 0..16 {
+                    chunk_manager.put_block_with_state(BlockID::RedstoneWire, 1, x, y, z);
+                    wire_count += 1;
+                }
+            }
+        }
+        assert!(wire_count > MAX_WIRE_NETWORK);
+
+        let changes = solve_wires(&chunk_manager, 0, Y, 0);
+        assert_eq!(changes.len(), MAX_WIRE_NETWORK);
+        assert!(changes.iter().all(|&(_, strength)| strength == 0));
+    }
+
+    #[test]
+    fn solving_again_gives_the_same_strengths() {
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        switch_on_lever(&chunk_manager, 0);
+        switch_on_lever(&chunk_manager, 12);
+        wire_line(&chunk_manager, 1, 11);
+        for z in 1..4 {
+            chunk_manager.put_block_with_state(BlockID::RedstoneWire, 0, 6, Y, z);
+        }
+
+        let first = solve_wires(&chunk_manager, 1, Y, 0);
+        assert_eq!(solve_wires(&chunk_manager, 1, Y, 0), first);
+        // The same network solved from another wire ends up with the same signal
+        let mut from_branch = solve_wires(&chunk_manager, 6, Y, 3);
+        let mut first = first;
+        first.sort();
+        from_branch.sort();
+        assert_eq!(from_branch, first);
+
+        solve_and_apply(&chunk_manager, 1);
+        assert!(solve_wires(&chunk_manager, 6, Y, 3).is_empty());
+    }
+
+    #[test]
+    fn repeaters_power_the_block_in_front_of_them() {
+        let position = (3, Y, 3);
+        for (facing, &(dx, dz)) in FACINGS.iter().enumerate() {
+            assert_eq!(Repeater.placed_state((dx, dz)), facing as u8);
+            for delay in 0..4 {
+                let state = facing as u8 | delay << REPEATER_DELAY_SHIFT | REPEATER_POWERED;
+                assert_eq!(repeater_output(position, state), (3 + dx, Y, 3 + dz));
+                assert_eq!(repeater_input(position, state), (3 - dx, Y, 3 - dz));
+                assert_eq!(repeater_delay(state), delay as u64 + 1);
+            }
+        }
+
+        let chunk_manager = empty_world(0..=1, 0..=0);
+        for (facing, &(dx, dz)) in FACINGS.iter().enumerate() {
+            let state = facing as u8 | REPEATER_POWERED;
+            chunk_manager.put_block_with_state(BlockID::Repeater, state, 3, Y, 3);
+            for &neighbour in &neighbours(position) {
+                let expected = if neighbour == (3 + dx, Y, 3 + dz) { MAX_POWER } else { 0 };
+                assert_eq!(power_emitted(&chunk_manager, position, neighbour), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,13 @@
+#version 450 core
+
+out vec4 Color;
+
+uniform sampler2D tex;
+uniform float brightness;
+
+in vec2 texture_coords;
+
+void main() {
+    Color = texture(tex, texture_coords);
+    Color.a *= brightness;
+}
@@ -0,0 +1,15 @@
+#version 450 core
+
+uniform mat4 model;
+uniform mat4 view;
+uniform mat4 projection;
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec2 tex_coords;
+
+out vec2 texture_coords;
+
+void main() {
+    texture_coords = tex_coords;
+    gl_Position = projection * view * model * vec4(pos, 1.0);
+}
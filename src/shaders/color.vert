@@ -0,0 +1,10 @@
+#version 450 core
+
+uniform mat4 model;
+uniform mat4 projection;
+
+layout (location = 0) in vec3 pos;
+
+void main() {
+    gl_Position = projection * model * vec4(pos, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450 core
+
+out vec4 Color;
+
+in VertexAttributes {
+    vec4 color;
+} attrs;
+
+void main() {
+    Color = attrs.color;
+}
@@ -0,0 +1,16 @@
+#version 450 core
+
+uniform mat4 view;
+uniform mat4 projection;
+
+layout (location = 0) in vec3 pos;
+layout (location = 1) in vec4 color;
+
+out VertexAttributes {
+    vec4 color;
+} attrs;
+
+void main() {
+    attrs.color = color;
+    gl_Position = projection * view * vec4(pos, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 450 core
+
+out vec4 Color;
+
+uniform sampler2D textures[8];
+
+in VertexAttributes {
+    flat int texture_index;
+    vec2 texture_coords;
+    vec4 color;
+} attrs;
+
+void main() {
+    vec4 diffuse_frag = texture(textures[attrs.texture_index], attrs.texture_coords);
+    if (diffuse_frag.a == 0.0) {
+        discard;
+    }
+    Color = diffuse_frag * attrs.color;
+}
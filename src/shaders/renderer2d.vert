@@ -0,0 +1,21 @@
+#version 450 core
+
+uniform mat4 projection;
+
+layout (location = 0) in vec3 pos;
+// Index of the texture in the batch, then the UV coords
+layout (location = 1) in vec3 texture_coords;
+layout (location = 2) in vec4 color;
+
+out VertexAttributes {
+    flat int texture_index;
+    vec2 texture_coords;
+    vec4 color;
+} attrs;
+
+void main() {
+    attrs.texture_index = int(texture_coords.x);
+    attrs.texture_coords = texture_coords.yz;
+    attrs.color = color;
+    gl_Position = projection * vec4(pos, 1.0);
+}
@@ -0,0 +1,44 @@
+#version 450 core
+
+out vec4 Color;
+
+// Of the view matrix without the position of the camera
+uniform mat4 inverse_view_projection;
+uniform vec3 zenith_color;
+uniform vec3 horizon_color;
+uniform vec3 sunset_color;
+uniform float sunset_strength;
+uniform float star_brightness;
+uniform float celestial_angle;
+
+in vec2 ndc;
+
+float hash(vec3 p) {
+    return fract(sin(dot(p, vec3(12.9898, 78.233, 37.719))) * 43758.5453);
+}
+
+void main() {
+    vec4 far = inverse_view_projection * vec4(ndc, 1.0, 1.0);
+    vec3 direction = normalize(far.xyz / far.w);
+    float elevation = direction.y;
+
+    vec3 color = mix(horizon_color, zenith_color, smoothstep(0.0, 0.5, elevation));
+    // Darker below the horizon
+    color *= 1.0 - 0.4 * smoothstep(0.0, 0.5, -elevation);
+
+    // Glow around the horizon on the side of the sun while it rises or sets
+    vec3 sun_side = vec3(cos(celestial_angle) >= 0.0 ? 1.0 : -1.0, 0.0, 0.0);
+    float glow = sunset_strength
+        * pow(max(dot(direction, sun_side), 0.0), 3.0)
+        * (1.0 - smoothstep(0.0, 0.4, abs(elevation)));
+    color = mix(color, sunset_color, glow);
+
+    // The stars turn with the sun and the moon
+    float c = cos(-celestial_angle);
+    float s = sin(-celestial_angle);
+    vec3 star_direction = vec3(c * direction.x - s * direction.y, s * direction.x + c * direction.y, direction.z);
+    float star = step(0.9985, hash(floor(star_direction * 200.0)));
+    color += vec3(star * star_brightness * smoothstep(-0.1, 0.2, elevation));
+
+    Color = vec4(color, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 450 core
+
+// The quad of the overlay, covering the window once doubled
+layout (location = 0) in vec3 pos;
+
+out vec2 ndc;
+
+void main() {
+    ndc = pos.xy * 2.0;
+    gl_Position = vec4(ndc, 0.0, 1.0);
+}
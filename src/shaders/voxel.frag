@@ -5,11 +5,14 @@ out vec4 Color;
 uniform sampler2DArray array_texture;
 uniform bool enable_fog;
 uniform vec3 sky_color;
+// Brightness of the daylight, lower at night
+uniform float sky_light;
 
 in VertexAttributes {
     vec3 texture_coords;
     vec3 normal;
     float ao;
+    float light;
     float visibility;
 } attrs;
 
@@ -27,6 +30,7 @@ void main() {
 //        Color.rgb *= 0.9;
     }
     Color.rgb *= (1.0 - attrs.ao * 0.15);
+    Color.rgb *= attrs.light * sky_light;
 
     if (enable_fog) {
         Color = mix(vec4(sky_color, 1.0), Color, attrs.visibility);
@@ -1,34 +1,64 @@
 #version 450 core
+#extension GL_ARB_shader_draw_parameters : require
 
 const float fog_gradient = 20.0;
 
-uniform mat4 model;
+// Same order as the active faces of a block: right, left, top, bottom, front, back
+const vec3 normals[6] = vec3[6](
+    vec3(1.0, 0.0, 0.0),
+    vec3(-1.0, 0.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, -1.0, 0.0),
+    vec3(0.0, 0.0, 1.0),
+    vec3(0.0, 0.0, -1.0)
+);
+
 uniform mat4 view;
 uniform mat4 projection;
 uniform float render_distance;
 
-layout (location = 0) in vec3 pos;
-layout (location = 1) in vec3 texture_coords;
-layout (location = 2) in vec3 normal;
-layout (location = 3) in float ao;
+// Origin of the chunk of each draw of the multi-draw, indexed with the draw ID
+layout (std430, binding = 0) readonly buffer ChunkOffsets {
+    vec4 chunk_offsets[];
+};
+
+// x: position (3 * 8 bits), normal index (3 bits), ambient occlusion (2 bits)
+// y: uv (2 * 4 bits), texture layer (12 bits), light (4 bits)
+// Positions and uvs are expressed in eighths of a block
+layout (location = 0) in uvec2 packed_vertex;
 
 out VertexAttributes {
     vec3 texture_coords;
     vec3 normal;
     float ao;
+    float light;
     float visibility;
 } attrs;
 
 void main() {
-    attrs.texture_coords = texture_coords;
-    attrs.normal = normal;
+    vec3 pos = vec3(
+        packed_vertex.x & 0xFFu,
+        (packed_vertex.x >> 8) & 0xFFu,
+        (packed_vertex.x >> 16) & 0xFFu) / 8.0;
+    uint normal_index = (packed_vertex.x >> 24) & 0x7u;
+    float ao = float((packed_vertex.x >> 27) & 0x3u);
+
+    vec2 uv = vec2(
+        packed_vertex.y & 0xFu,
+        (packed_vertex.y >> 4) & 0xFu) / 8.0;
+    float layer = float((packed_vertex.y >> 8) & 0xFFFu);
+    float light = float((packed_vertex.y >> 20) & 0xFu) / 15.0;
+
+    attrs.texture_coords = vec3(uv, layer);
+    attrs.normal = normals[normal_index];
     attrs.ao = ao;
+    attrs.light = light;
     attrs.visibility = 1.0;
-    vec4 frag_pos = view * model * vec4(pos, 1.0f);
+    vec4 frag_pos = view * vec4(pos + chunk_offsets[gl_DrawIDARB].xyz, 1.0f);
     gl_Position = projection * frag_pos;
 
     // Fog
     float fog_density = 0.066 / render_distance;
     float distance = length(frag_pos.xyz);
     attrs.visibility = exp(-pow(distance * fog_density, fog_gradient));
-}
\ No newline at end of file
+}
@@ -1,3 +1,5 @@
+use nalgebra_glm::{Vec3, vec3};
+
 use crate::types::{UVFaces, TextureLayer};
 
 
@@ -23,98 +25,167 @@ pub fn quad_array_texture() -> Vec<f32> {
     ]).to_vec()
 }
 
-// bl = bottom left
-// tr = top right
-// Creates and write the vertices of a cube directly into "ptr" (usually a VBO mapped to virtual memory)
-pub unsafe fn write_unit_cube_to_ptr(ptr: *mut f32, x: f32, y: f32, z: f32,
+// Voxel vertices are packed into two u32s:
+// - word 0: x (8 bits), y (8 bits), z (8 bits), normal index (3 bits), ambient occlusion (2 bits)
+// - word 1: u (4 bits), v (4 bits), texture layer (12 bits), light (4 bits)
+// Positions and UV coordinates are stored in eighths of a block so that faces which
+// don't span a whole block can still be represented.
+pub const PACKED_VERTEX_SIZE: usize = 2;
+pub const VERTICES_PER_QUAD: usize = 4;
+pub const INDICES_PER_QUAD: usize = 6;
+pub const MAX_LIGHT_LEVEL: u32 = 15;
+
+// Normal indices follow the order of the active faces: [right, left, top, bottom, front, back]
+pub const NORMAL_RIGHT: u32 = 0;
+pub const NORMAL_LEFT: u32 = 1;
+pub const NORMAL_TOP: u32 = 2;
+pub const NORMAL_BOTTOM: u32 = 3;
+pub const NORMAL_FRONT: u32 = 4;
+pub const NORMAL_BACK: u32 = 5;
+
+#[inline]
+pub fn pack_voxel_vertex(x: u32, y: u32, z: u32, u: u32, v: u32, normal: u32, ao: u8, layer: TextureLayer, light: u32) -> [u32; PACKED_VERTEX_SIZE] {
+    let ao = std::cmp::min(ao as u32, 3);
+    [
+        (x & 0xFF) | (y & 0xFF) << 8 | (z & 0xFF) << 16 | (normal & 0x7) << 24 | ao << 27,
+        (u & 0xF) | (v & 0xF) << 4 | (layer & 0xFFF) << 8 | (light & 0xF) << 20,
+    ]
+}
+
+// Position of a packed vertex relative to its chunk, in blocks
+#[inline]
+pub fn unpack_voxel_position(vertex: &[u32]) -> Vec3 {
+    vec3(
+        (vertex[0] & 0xFF) as f32,
+        (vertex[0] >> 8 & 0xFF) as f32,
+        (vertex[0] >> 16 & 0xFF) as f32) / 8.0
+}
+
+// Center of a quad made of 4 packed vertices
+pub fn quad_center(quad: &[u32]) -> Vec3 {
+    quad.chunks(PACKED_VERTEX_SIZE)
+        .map(unpack_voxel_position)
+        .fold(vec3(0.0, 0.0, 0.0), |acc, pos| acc + pos) / VERTICES_PER_QUAD as f32
+}
+
+// Indices of the two triangles of every quad, the vertices being in counter-clockwise order
+pub fn quad_indices(n_quads: u32) -> Vec<u32> {
+    let mut indices = Vec::with_capacity(n_quads as usize * INDICES_PER_QUAD);
+    for i in 0..n_quads {
+        let first = i * VERTICES_PER_QUAD as u32;
+        indices.extend_from_slice(&[first, first + 1, first + 2, first + 2, first + 3, first]);
+    }
+    indices
+}
+
+// Creates and write the packed vertices of a cube directly into "ptr" (usually a VBO mapped to virtual memory)
+// Every visible face is made of 4 vertices, the triangles are assembled by the shared index buffer
+// `height` is in eighths of a block, the sides of lower blocks show the bottom of their texture
+// Returns the number of faces written
+pub unsafe fn write_unit_cube_to_ptr(ptr: *mut u32, x: u32, y: u32, z: u32, height: u32,
                                      (front_layer, back_layer, top_layer, bottom_layer, left_layer, right_layer): (TextureLayer, TextureLayer, TextureLayer, TextureLayer, TextureLayer, TextureLayer),
                                      [right, left, top, bottom, front, back]: [bool; 6],
                                      ao: [[u8; 4]; 6]) -> u32 {
-    let vertex_size = 10;
-    let vertices_per_face = 6;
-    let face_size = vertex_size * vertices_per_face;
+    let face_size = PACKED_VERTEX_SIZE * VERTICES_PER_QUAD;
+    let light = MAX_LIGHT_LEVEL;
+
+    // Block corners, in eighths of a block
+    let (x0, y0, z0) = (8 * x, 8 * y, 8 * z);
+    let (x1, y1, z1) = (x0 + 8, y0 + height, z0 + 8);
 
     let mut i = 0;
-    let mut copied_vertices = 0;
+    let mut copied_faces = 0;
 
-    let uv = (0.0, 0.0, 1.0, 1.0);
+    let mut write_face = |vertices: [[u32; PACKED_VERTEX_SIZE]; VERTICES_PER_QUAD]| {
+        for (j, vertex) in vertices.iter().enumerate() {
+            ptr.offset(i + (j * PACKED_VERTEX_SIZE) as isize).copy_from_nonoverlapping(vertex.as_ptr(), PACKED_VERTEX_SIZE);
+        }
+        i += face_size as isize;
+        copied_faces += 1;
+    };
 
-    // First 3 floats contain the position, last 2 are the UV coordinates
     if front {
-        ptr.offset(i).copy_from_nonoverlapping([
-            0.0f32 + x,  0.0 + y, 1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32,
-            1.0 + x,  0.0 + y,  1.0 + z, uv.2, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][1] as f32,
-            1.0 + x,  1.0 + y,  1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32,
-            1.0 + x,  1.0 + y,  1.0 + z, uv.2, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][2] as f32,
-            0.0 + x,  1.0 + y,  1.0 + z, uv.0, uv.3, front_layer as f32, 0.0, 0.0, 1.0, ao[4][3] as f32,
-            0.0 + x,  0.0 + y,  1.0 + z, uv.0, uv.1, front_layer as f32, 0.0, 0.0, 1.0, ao[4][0] as f32,
-        ].as_ptr(), face_size);
-        i += face_size as isize;
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x0, y0, z1, 0, 0, NORMAL_FRONT, ao[4][0], front_layer, light),
+            pack_voxel_vertex(x1, y0, z1, 8, 0, NORMAL_FRONT, ao[4][1], front_layer, light),
+            pack_voxel_vertex(x1, y1, z1, 8, height, NORMAL_FRONT, ao[4][2], front_layer, light),
+            pack_voxel_vertex(x0, y1, z1, 0, height, NORMAL_FRONT, ao[4][3], front_layer, light),
+        ]);
     }
     if back {
-        ptr.offset(i).copy_from_nonoverlapping([
-            1.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32,
-            0.0 + x,  0.0 + y,  0.0 + z, uv.2, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][1] as f32,
-            0.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32,
-            0.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][2] as f32,
-            1.0 + x,  1.0 + y,  0.0 + z, uv.0, uv.3, back_layer as f32, 0.0, 0.0, -1.0, ao[5][3] as f32,
-            1.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, back_layer as f32, 0.0, 0.0, -1.0, ao[5][0] as f32,
-        ].as_ptr(), face_size);
-        i += face_size as isize;
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x1, y0, z0, 0, 0, NORMAL_BACK, ao[5][0], back_layer, light),
+            pack_voxel_vertex(x0, y0, z0, 8, 0, NORMAL_BACK, ao[5][1], back_layer, light),
+            pack_voxel_vertex(x0, y1, z0, 8, height, NORMAL_BACK, ao[5][2], back_layer, light),
+            pack_voxel_vertex(x1, y1, z0, 0, height, NORMAL_BACK, ao[5][3], back_layer, light),
+        ]);
     }
     if left {
-        ptr.offset(i).copy_from_nonoverlapping([
-            0.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32,
-            0.0 + x,  0.0 + y,  1.0 + z, uv.2, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][1] as f32,
-            0.0 + x,  1.0 + y,  1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32,
-            0.0 + x,  1.0 + y,  1.0 + z, uv.2, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][2] as f32,
-            0.0 + x,  1.0 + y,  0.0 + z, uv.0, uv.3, left_layer as f32, -1.0, 0.0, 0.0, ao[1][3] as f32,
-            0.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, left_layer as f32, -1.0, 0.0, 0.0, ao[1][0] as f32,
-        ].as_ptr(), face_size);
-        i += face_size as isize;
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x0, y0, z0, 0, 0, NORMAL_LEFT, ao[1][0], left_layer, light),
+            pack_voxel_vertex(x0, y0, z1, 8, 0, NORMAL_LEFT, ao[1][1], left_layer, light),
+            pack_voxel_vertex(x0, y1, z1, 8, height, NORMAL_LEFT, ao[1][2], left_layer, light),
+            pack_voxel_vertex(x0, y1, z0, 0, height, NORMAL_LEFT, ao[1][3], left_layer, light),
+        ]);
     }
     if right {
-        ptr.offset(i).copy_from_nonoverlapping([
-            1.0 + x,  0.0 + y,  1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32,
-            1.0 + x,  0.0 + y,  0.0 + z, uv.2, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][1] as f32,
-            1.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32,
-            1.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][2] as f32,
-            1.0 + x,  1.0 + y,  1.0 + z, uv.0, uv.3, right_layer as f32, 1.0, 0.0, 0.0, ao[0][3] as f32,
-            1.0 + x,  0.0 + y,  1.0 + z, uv.0, uv.1, right_layer as f32, 1.0, 0.0, 0.0, ao[0][0] as f32,
-        ].as_ptr(), face_size);
-        i += face_size as isize;
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x1, y0, z1, 0, 0, NORMAL_RIGHT, ao[0][0], right_layer, light),
+            pack_voxel_vertex(x1, y0, z0, 8, 0, NORMAL_RIGHT, ao[0][1], right_layer, light),
+            pack_voxel_vertex(x1, y1, z0, 8, height, NORMAL_RIGHT, ao[0][2], right_layer, light),
+            pack_voxel_vertex(x1, y1, z1, 0, height, NORMAL_RIGHT, ao[0][3], right_layer, light),
+        ]);
     }
     if top {
-        ptr.offset(i).copy_from_nonoverlapping([
-            0.0 + x,  1.0 + y,  1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32,
-            1.0 + x,  1.0 + y,  1.0 + z, uv.2, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][1] as f32,
-            1.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32,
-            1.0 + x,  1.0 + y,  0.0 + z, uv.2, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][2] as f32,
-            0.0 + x,  1.0 + y,  0.0 + z, uv.0, uv.3, top_layer as f32, 0.0, 1.0, 0.0, ao[2][3] as f32,
-            0.0 + x,  1.0 + y,  1.0 + z, uv.0, uv.1, top_layer as f32, 0.0, 1.0, 0.0, ao[2][0] as f32,
-        ].as_ptr(), face_size);
-        i += face_size as isize;
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x0, y1, z1, 0, 0, NORMAL_TOP, ao[2][0], top_layer, light),
+            pack_voxel_vertex(x1, y1, z1, 8, 0, NORMAL_TOP, ao[2][1], top_layer, light),
+            pack_voxel_vertex(x1, y1, z0, 8, 8, NORMAL_TOP, ao[2][2], top_layer, light),
+            pack_voxel_vertex(x0, y1, z0, 0, 8, NORMAL_TOP, ao[2][3], top_layer, light),
+        ]);
     }
     if bottom {
-        ptr.offset(i).copy_from_nonoverlapping([
-            0.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32,
-            1.0 + x,  0.0 + y,  0.0 + z, uv.2, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][1] as f32,
-            1.0 + x,  0.0 + y,  1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32,
-            1.0 + x,  0.0 + y,  1.0 + z, uv.2, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][2] as f32,
-            0.0 + x,  0.0 + y,  1.0 + z, uv.0, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][3] as f32,
-            0.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0, ao[3][0] as f32,
-        ].as_ptr(), face_size);
-        copied_vertices += vertices_per_face;
+        write_face([
+            pack_voxel_vertex(x0, y0, z0, 0, 0, NORMAL_BOTTOM, ao[3][0], bottom_layer, light),
+            pack_voxel_vertex(x1, y0, z0, 8, 0, NORMAL_BOTTOM, ao[3][1], bottom_layer, light),
+            pack_voxel_vertex(x1, y0, z1, 8, 8, NORMAL_BOTTOM, ao[3][2], bottom_layer, light),
+            pack_voxel_vertex(x0, y0, z1, 0, 8, NORMAL_BOTTOM, ao[3][3], bottom_layer, light),
+        ]);
     }
-    copied_vertices as u32
+    copied_faces
 }
 
-pub fn block_outline() -> &'static [f32; 72] {
+// A cross is made of two diagonal quads, drawn from both sides
+pub const CROSS_FACES: u32 = 4;
+
+// Writes the packed vertices of a plant into "ptr", like `write_unit_cube_to_ptr`
+// Returns the number of faces written
+pub unsafe fn write_cross_to_ptr(ptr: *mut u32, x: u32, y: u32, z: u32, layer: TextureLayer) -> u32 {
+    let light = MAX_LIGHT_LEVEL;
+    let (x0, y0, z0) = (8 * x, 8 * y, 8 * z);
+    let (x1, y1, z1) = (x0 + 8, y0 + 8, z0 + 8);
+
+    // Each diagonal goes from the first corner to the second one, on the ground
+    let diagonals = [((x0, z0), (x1, z1)), ((x0, z1), (x1, z0))];
+    let mut i = 0;
+    for &((xa, za), (xb, zb)) in &diagonals {
+        for &((xa, za), (xb, zb), normal) in &[((xa, za), (xb, zb), NORMAL_FRONT), ((xb, zb), (xa, za), NORMAL_BACK)] {
+            let vertices = [
+                pack_voxel_vertex(xa, y0, za, 0, 0, normal, 0, layer, light),
+                pack_voxel_vertex(xb, y0, zb, 8, 0, normal, 0, layer, light),
+                pack_voxel_vertex(xb, y1, zb, 8, 8, normal, 0, layer, light),
+                pack_voxel_vertex(xa, y1, za, 0, 8, normal, 0, layer, light),
+            ];
+            for vertex in &vertices {
+                ptr.offset(i).copy_from_nonoverlapping(vertex.as_ptr(), PACKED_VERTEX_SIZE);
+                i += PACKED_VERTEX_SIZE as isize;
+            }
+        }
+    }
+    CROSS_FACES
+}
+
+pub fn block_outline()-> &'static [f32; 72] {
     // Groups of parallel lines for each dimension
     &[
         0.0f32, 0.0, 0.0, 1.0, 0.0, 0.0,
@@ -183,4 +254,79 @@ pub fn centered_unit_cube(x: f32, y: f32, z: f32, (front_layer, back_layer, top_
         0.0 + x,  0.0 + y,  1.0 + z, uv.0, uv.3, bottom_layer as f32, 0.0, -1.0, 0.0,
         0.0 + x,  0.0 + y,  0.0 + z, uv.0, uv.1, bottom_layer as f32, 0.0, -1.0, 0.0,
     ].to_vec()
-}
\ No newline at end of file
+}
+
+// Size of the icons of the items, in pixels
+pub const ITEM_ICON_SIZE: u32 = 16;
+
+/// Flat square showing the icon of an item, centered on the origin, facing +z
+/// Same vertex layout as `centered_unit_cube`
+pub fn item_sprite_quad(layer: TextureLayer) -> Vec<f32> {
+    let layer = layer as f32;
+    // The normal of the top face, which isn't shaded by the item shader
+    [
+        -0.5f32, -0.5, 0.0, 0.0, 0.0, layer, 0.0, 1.0, 0.0,
+        0.5, -0.5, 0.0, 1.0, 0.0, layer, 0.0, 1.0, 0.0,
+        0.5, 0.5, 0.0, 1.0, 1.0, layer, 0.0, 1.0, 0.0,
+        0.5, 0.5, 0.0, 1.0, 1.0, layer, 0.0, 1.0, 0.0,
+        -0.5, 0.5, 0.0, 0.0, 1.0, layer, 0.0, 1.0, 0.0,
+        -0.5, -0.5, 0.0, 0.0, 0.0, layer, 0.0, 1.0, 0.0,
+    ].to_vec()
+}
+
+/// Icon of an item extruded to a thickness of one pixel, centered on the origin
+/// The front and back are the whole icon, and every edge of an opaque pixel touching
+/// a transparent one gets a side face textured with the color of that pixel
+/// `is_opaque(x, y)` tells if a pixel of the icon is opaque, with (0, 0) at the bottom left
+/// Same vertex layout as `centered_unit_cube`
+pub fn extruded_item_sprite(layer: TextureLayer, is_opaque: &dyn Fn(u32, u32) -> bool) -> Vec<f32> {
+    let layer = layer as f32;
+    let pixel = 1.0 / ITEM_ICON_SIZE as f32;
+    let t = pixel / 2.0;
+    let mut vertices = Vec::new();
+
+    // Corners in counter-clockwise order seen from outside
+    let mut push_quad = |corners: [(f32, f32, f32); 4], uvs: [(f32, f32); 4], normal: (f32, f32, f32)| {
+        for &i in &[0, 1, 2, 2, 3, 0] {
+            let (x, y, z) = corners[i];
+            let (u, v) = uvs[i];
+            vertices.extend_from_slice(&[x, y, z, u, v, layer, normal.0, normal.1, normal.2]);
+        }
+    };
+
+    let full_uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    push_quad([(-0.5, -0.5, t), (0.5, -0.5, t), (0.5, 0.5, t), (-0.5, 0.5, t)],
+              full_uvs, (0.0, 0.0, 1.0));
+    push_quad([(0.5, -0.5, -t), (-0.5, -0.5, -t), (-0.5, 0.5, -t), (0.5, 0.5, -t)],
+              [(1.0, 0.0), (0.0, 0.0), (0.0, 1.0), (1.0, 1.0)], (0.0, 0.0, -1.0));
+
+    let is_transparent = |x: i32, y: i32| {
+        x < 0 || y < 0 || x >= ITEM_ICON_SIZE as i32 || y >= ITEM_ICON_SIZE as i32 || !is_opaque(x as u32, y as u32)
+    };
+
+    for y in 0..ITEM_ICON_SIZE as i32 {
+        for x in 0..ITEM_ICON_SIZE as i32 {
+            if is_transparent(x, y) {
+                continue;
+            }
+            let (x0, y0) = (x as f32 * pixel - 0.5, y as f32 * pixel - 0.5);
+            let (x1, y1) = (x0 + pixel, y0 + pixel);
+            let uv = ((x as f32 + 0.5) * pixel, (y as f32 + 0.5) * pixel);
+            let uvs = [uv; 4];
+
+            if is_transparent(x + 1, y) {
+                push_quad([(x1, y0, t), (x1, y0, -t), (x1, y1, -t), (x1, y1, t)], uvs, (1.0, 0.0, 0.0));
+            }
+            if is_transparent(x - 1, y) {
+                push_quad([(x0, y0, -t), (x0, y0, t), (x0, y1, t), (x0, y1, -t)], uvs, (-1.0, 0.0, 0.0));
+            }
+            if is_transparent(x, y + 1) {
+                push_quad([(x0, y1, t), (x1, y1, t), (x1, y1, -t), (x0, y1, -t)], uvs, (0.0, 1.0, 0.0));
+            }
+            if is_transparent(x, y - 1) {
+                push_quad([(x0, y0, -t), (x1, y0, -t), (x1, y0, t), (x0, y0, t)], uvs, (0.0, -1.0, 0.0));
+            }
+        }
+    }
+    vertices
+}
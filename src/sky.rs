@@ -0,0 +1,114 @@
+use nalgebra::Matrix4;
+use nalgebra_glm::{Mat4, pi, Vec3, vec3};
+
+use crate::constants::BACKGROUND_COLOR;
+use crate::gui::{read_gui_image, upload_gui_texture};
+
+const DAY_ZENITH_COLOR: (f32, f32, f32) = (0.47, 0.65, 1.0);
+const NIGHT_ZENITH_COLOR: (f32, f32, f32) = (0.0, 0.0, 0.02);
+const NIGHT_HORIZON_COLOR: (f32, f32, f32) = (0.02, 0.03, 0.07);
+// Tint of the horizon on the side of the sun while it rises or sets
+pub const SUNSET_COLOR: [f32; 3] = [1.0, 0.45, 0.15];
+// The blocks are never completely dark at night
+const MIN_SKY_LIGHT: f32 = 0.2;
+
+// Distance of the sun and the moon from the camera, and their size
+const CELESTIAL_BODY_DISTANCE: f32 = 100.0;
+pub const SUN_SIZE: f32 = 30.0;
+pub const MOON_SIZE: f32 = 20.0;
+
+fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
+    let t = ((x - edge0) / (edge1 - edge0)).max(0.0).min(1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+fn mix((r0, g0, b0): (f32, f32, f32), (r1, g1, b1): (f32, f32, f32), t: f32) -> Vec3 {
+    vec3(r0 + (r1 - r0) * t, g0 + (g1 - g0) * t, b0 + (b1 - b0) * t)
+}
+
+/// Colors of the sky and light of the world at a time of the day
+pub struct Sky {
+    // Angle of the sun above the eastern horizon (+x), in radians, the moon is on the opposite side
+    pub celestial_angle: f32,
+    pub zenith_color: Vec3,
+    // Also the color of the fog
+    pub horizon_color: Vec3,
+    // How much the horizon is tinted with the sunset color
+    pub sunset_strength: f32,
+    pub star_brightness: f32,
+    // Opacity of the sun and the moon, hidden behind the clouds
+    pub celestial_brightness: f32,
+    // Multiplies the light of the blocks
+    pub sky_light: f32,
+}
+
+impl Sky {
+    /// `day_fraction` goes from 0 at sunrise to 1 at the next sunrise
+    pub fn at(day_fraction: f32) -> Self {
+        let celestial_angle = day_fraction * 2.0 * pi::<f32>();
+        let sun_height = celestial_angle.sin();
+        // 0 at night and 1 during the day, changing while the sun crosses the horizon
+        let daylight = smoothstep(-0.25, 0.25, sun_height);
+        let (r, g, b, _) = BACKGROUND_COLOR;
+
+        Sky {
+            celestial_angle,
+            zenith_color: mix(NIGHT_ZENITH_COLOR, DAY_ZENITH_COLOR, daylight),
+            horizon_color: mix(NIGHT_HORIZON_COLOR, (r, g, b), daylight),
+            sunset_strength: (1.0 - sun_height.abs() * 4.0).max(0.0) * 0.8,
+            star_brightness: (1.0 - daylight * 2.0).max(0.0),
+            celestial_brightness: 1.0,
+            sky_light: MIN_SKY_LIGHT + (1.0 - MIN_SKY_LIGHT) * daylight,
+        }
+    }
+
+    /// Covers the sky with clouds, `darkness` going from 0 for a clear sky to 1,
+    /// and lights everything up while `lightning_flash` fades out
+    pub fn with_weather(self, darkness: f32, lightning_flash: f32) -> Self {
+        let overcast = |color: Vec3| {
+            let gray = color.dot(&vec3(0.3, 0.59, 0.11));
+            let color = color + (vec3(gray, gray, gray) - color) * darkness;
+            let color = color * (1.0 - 0.5 * darkness);
+            color + (vec3(1.0, 1.0, 1.0) - color) * lightning_flash * 0.8
+        };
+        let sky_light = self.sky_light * (1.0 - 0.5 * darkness);
+
+        Sky {
+            celestial_angle: self.celestial_angle,
+            zenith_color: overcast(self.zenith_color),
+            horizon_color: overcast(self.horizon_color),
+            sunset_strength: self.sunset_strength * (1.0 - darkness),
+            star_brightness: self.star_brightness * (1.0 - darkness),
+            celestial_brightness: 1.0 - darkness,
+            sky_light: sky_light + (1.0 - sky_light) * lightning_flash,
+        }
+    }
+}
+
+/// Places the quad of the sun (at the celestial angle) or of the moon (half a turn further) in the sky, facing the camera
+/// The quad starts in the XY plane, centered on the origin
+pub fn celestial_body_model_matrix(celestial_angle: f32, size: f32) -> Mat4 {
+    let path_rotation = Matrix4::new_rotation(vec3(0.0, 0.0, celestial_angle - pi::<f32>() / 2.0));
+    let translation = Matrix4::new_translation(&vec3(0.0, CELESTIAL_BODY_DISTANCE, 0.0));
+    // Lay the quad flat, above the camera
+    let facing_rotation = Matrix4::new_rotation(vec3(-pi::<f32>() / 2.0, 0.0, 0.0));
+    let scale = Matrix4::new_scaling(size);
+    path_rotation * translation * facing_rotation * scale
+}
+
+/// The view matrix without the position of the camera, the sky always surrounds it
+pub fn sky_view_matrix(view_matrix: &Mat4) -> Mat4 {
+    let mut sky_view_matrix = *view_matrix;
+    sky_view_matrix[(0, 3)] = 0.0;
+    sky_view_matrix[(1, 3)] = 0.0;
+    sky_view_matrix[(2, 3)] = 0.0;
+    sky_view_matrix
+}
+
+pub fn create_sun_texture() -> u32 {
+    upload_gui_texture(&read_gui_image("textures/environment/sun.png"))
+}
+
+pub fn create_moon_texture() -> u32 {
+    upload_gui_texture(&read_gui_image("textures/environment/moon.png"))
+}
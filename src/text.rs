@@ -0,0 +1,155 @@
+use image::{DynamicImage, GenericImageView};
+
+use crate::constants::{WINDOW_HEIGHT, WINDOW_WIDTH};
+use crate::draw_commands::{QuadProps, Renderer2D};
+use crate::gui::{read_gui_image, upload_gui_texture};
+use crate::shader_compilation::ShaderProgram;
+
+pub const FONT_PATH: &str = "textures/gui/ascii.png";
+
+// The font texture is a grid of 16x16 glyphs of 8x8 pixels, in the order of the ASCII table
+const GLYPHS_PER_ROW: u32 = 16;
+const GLYPH_SIZE: u32 = 8;
+// Pixels between two lines of text
+pub const LINE_HEIGHT: f32 = 9.0;
+const SPACE_WIDTH: u8 = 3;
+
+pub type Color = (f32, f32, f32, f32);
+pub const WHITE: Color = (1.0, 1.0, 1.0, 1.0);
+
+/// Texture of the font, and the width in pixels of every glyph
+pub struct GlyphAtlas {
+    texture: u32,
+    widths: [u8; 256],
+}
+
+impl GlyphAtlas {
+    pub fn load(path: &str) -> Self {
+        let image = read_gui_image(path);
+        GlyphAtlas {
+            texture: upload_gui_texture(&image),
+            widths: Self::measure_glyphs(&image),
+        }
+    }
+
+    /// A glyph is as wide as its rightmost column with a visible pixel
+    fn measure_glyphs(image: &DynamicImage) -> [u8; 256] {
+        let mut widths = [0; 256];
+        for (code, width) in widths.iter_mut().enumerate() {
+            let (glyph_x, glyph_y) = Self::glyph_origin(code as u8);
+            *width = (0..GLYPH_SIZE)
+                .rev()
+                .find(|&x| (0..GLYPH_SIZE).any(|y| image.get_pixel(glyph_x + x, glyph_y + y)[3] > 0))
+                .map_or(0, |x| x as u8 + 1);
+        }
+        widths[b' ' as usize] = SPACE_WIDTH;
+        widths
+    }
+
+    /// Top left corner of a glyph in the texture, in pixels
+    fn glyph_origin(code: u8) -> (u32, u32) {
+        let code = code as u32;
+        ((code % GLYPHS_PER_ROW) * GLYPH_SIZE, (code / GLYPHS_PER_ROW) * GLYPH_SIZE)
+    }
+
+    /// Characters outside of the ASCII table are drawn as '?'
+    fn glyph_code(c: char) -> u8 {
+        if c.is_ascii() { c as u8 } else { b'?' }
+    }
+
+    /// Horizontal distance to the next glyph, in pixels
+    fn advance(&self, c: char) -> f32 {
+        self.widths[Self::glyph_code(c) as usize] as f32 + 1.0
+    }
+
+    /// UV coords of a glyph as (left, bottom, right, top)
+    fn glyph_uv(&self, c: char) -> (f32, f32, f32, f32) {
+        let (x, y) = Self::glyph_origin(Self::glyph_code(c));
+        let size = (GLYPHS_PER_ROW * GLYPH_SIZE) as f32;
+        let width = self.widths[Self::glyph_code(c) as usize] as f32;
+        (x as f32 / size, (y + GLYPH_SIZE) as f32 / size, (x as f32 + width) / size, y as f32 / size)
+    }
+}
+
+/// Draws text in GUI coordinates with a bitmap font, the glyphs of a frame are drawn in a single batch
+/// Every pixel of the font is `scale` pixels on the screen
+pub struct TextRenderer {
+    renderer: Renderer2D,
+    atlas: GlyphAtlas,
+}
+
+impl TextRenderer {
+    pub fn new() -> Self {
+        TextRenderer {
+            renderer: Renderer2D::new(100_000),
+            atlas: GlyphAtlas::load(FONT_PATH),
+        }
+    }
+
+    /// Width of the longest line of the text, in pixels
+    pub fn text_width(&self, text: &str, scale: f32) -> f32 {
+        text.lines()
+            .map(|line| line.chars().map(|c| self.atlas.advance(c)).sum::<f32>() - 1.0)
+            .fold(0.0, f32::max)
+            .max(0.0) * scale
+    }
+
+    /// Height of the text, in pixels
+    pub fn text_height(&self, text: &str, scale: f32) -> f32 {
+        let lines = text.lines().count().max(1) as f32;
+        ((lines - 1.0) * LINE_HEIGHT + GLYPH_SIZE as f32) * scale
+    }
+
+    pub fn begin(&mut self) {
+        self.renderer.begin_batch();
+    }
+
+    /// `(x, y)` is the top left corner of the text, every line goes below the previous one
+    pub fn draw_text(&mut self, (x, y): (f32, f32), scale: f32, color: Color, text: &str) {
+        self.draw_glyphs((x, y), 1.0, scale, color, text);
+    }
+
+    /// Draws the text on top of a darker copy of it, moved by a pixel of the font to the bottom right
+    pub fn draw_text_with_shadow(&mut self, (x, y): (f32, f32), scale: f32, color: Color, text: &str) {
+        let (r, g, b, a) = color;
+        let shadow_color = (r * 0.25, g * 0.25, b * 0.25, a);
+        self.draw_glyphs((x + scale, y - scale), 0.0, scale, shadow_color, text);
+        self.draw_glyphs((x, y), 1.0, scale, color, text);
+    }
+
+    /// Text centered horizontally on `x`
+    pub fn draw_centered_text_with_shadow(&mut self, (x, y): (f32, f32), scale: f32, color: Color, text: &str) {
+        let width = self.text_width(text, scale);
+        self.draw_text_with_shadow((x - width / 2.0, y), scale, color, text);
+    }
+
+    // The shadows are drawn under the text thanks to their lower z
+    fn draw_glyphs(&mut self, (x, y): (f32, f32), z: f32, scale: f32, color: Color, text: &str) {
+        for (i, line) in text.lines().enumerate() {
+            let mut glyph_x = x;
+            let glyph_y = y - (i as f32 * LINE_HEIGHT + GLYPH_SIZE as f32) * scale;
+            for c in line.chars() {
+                let advance = self.atlas.advance(c);
+                if c != ' ' {
+                    self.renderer.submit_quad(QuadProps {
+                        position: (glyph_x, glyph_y, z),
+                        size: ((advance - 1.0) * scale, GLYPH_SIZE as f32 * scale),
+                        texture_id: self.atlas.texture,
+                        texture_coords: self.atlas.glyph_uv(c),
+                        color,
+                    });
+                }
+                glyph_x += advance * scale;
+            }
+        }
+    }
+
+    /// Draws everything submitted since `begin`
+    pub fn end(&mut self, shader: &mut ShaderProgram) {
+        let projection_matrix = nalgebra_glm::ortho(
+            0.0, WINDOW_WIDTH as f32, 0.0, WINDOW_HEIGHT as f32, -5.0, 5.0);
+        shader.use_program();
+        shader.set_uniform_matrix4fv("projection", projection_matrix.as_ptr());
+        self.renderer.end_batch(shader);
+    }
+}
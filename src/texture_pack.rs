@@ -5,14 +5,18 @@ use image::{DynamicImage, GenericImageView};
 
 use crate::block_texture_faces::BlockFaces;
 use crate::chunk::BlockID;
-use crate::constants::ITEM_ARRAY_TEXTURE_LAYERS;
-use crate::types::{TextureLayer, TexturePack};
+use crate::constants::{DESTROY_STAGES, ITEM_ARRAY_TEXTURE_LAYERS};
+use crate::inventory::item::Item;
+use crate::mob::MobKind;
+use crate::types::{ItemIcons, MobTextures, TextureLayer, TexturePack};
 
-pub fn generate_array_texture() -> (u32, TexturePack) {
+pub fn generate_array_texture() -> (u32, TexturePack, ItemIcons, MobTextures) {
     let face_images = create_face_images_map();
     let array_texture = create_array_texture(ITEM_ARRAY_TEXTURE_LAYERS as i32);
-    let face_uvs = create_face_uvs_map(array_texture, face_images);
-    (array_texture, face_uvs)
+    let (face_uvs, first_free_layer) = create_face_uvs_map(array_texture, face_images);
+    let item_icons = create_item_icons_map(array_texture, first_free_layer);
+    let mob_textures = create_mob_textures_map(array_texture, first_free_layer + item_icons.len() as TextureLayer);
+    (array_texture, face_uvs, item_icons, mob_textures)
 }
 
 fn create_face_images_map() -> HashMap<BlockID, BlockFaces<&'static str>> {
@@ -37,11 +41,45 @@ fn create_face_images_map() -> HashMap<BlockID, BlockFaces<&'static str>> {
     face_images.insert(BlockID::Glass, BlockFaces::All("textures/blocks/glass.png"));
     face_images.insert(BlockID::Urss, BlockFaces::All("textures/blocks/urss.png"));
     face_images.insert(BlockID::Hitler, BlockFaces::All("textures/blocks/hitler.png"));
+    face_images.insert(BlockID::CraftingTable, BlockFaces::Each {
+        top: "textures/blocks/crafting_table_top.png",
+        bottom: "textures/blocks/oak_planks.png",
+        front: "textures/blocks/crafting_table_front.png",
+        back: "textures/blocks/crafting_table_side.png",
+        left: "textures/blocks/crafting_table_side.png",
+        right: "textures/blocks/crafting_table_front.png",
+    });
+    face_images.insert(BlockID::Snow, BlockFaces::All("textures/blocks/snow.png"));
+    face_images.insert(BlockID::Water, BlockFaces::All("textures/blocks/water.png"));
+    face_images.insert(BlockID::Lava, BlockFaces::All("textures/blocks/lava.png"));
+    face_images.insert(BlockID::OakSapling, BlockFaces::All("textures/blocks/oak_sapling.png"));
+    face_images.insert(BlockID::Sand, BlockFaces::All("textures/blocks/sand.png"));
+    face_images.insert(BlockID::Gravel, BlockFaces::All("textures/blocks/gravel.png"));
+    face_images.insert(BlockID::Lever, BlockFaces::All("textures/blocks/lever.png"));
+    face_images.insert(BlockID::StoneButton, BlockFaces::All("textures/blocks/stone_button.png"));
+    face_images.insert(BlockID::RedstoneWire, BlockFaces::All("textures/blocks/redstone_wire.png"));
+    face_images.insert(BlockID::Repeater, BlockFaces::Sides {
+        sides: "textures/blocks/repeater_side.png",
+        top: "textures/blocks/repeater_top.png",
+        bottom: "textures/blocks/repeater_side.png",
+    });
+    face_images.insert(BlockID::RedstoneLamp, BlockFaces::All("textures/blocks/redstone_lamp.png"));
+    face_images.insert(BlockID::LitRedstoneLamp, BlockFaces::All("textures/blocks/redstone_lamp_on.png"));
     face_images.insert(BlockID::Debug, BlockFaces::All("textures/blocks/debug.png"));
     face_images.insert(BlockID::Debug2, BlockFaces::All("textures/blocks/debug2.png"));
     face_images
 }
 
+/// One layer per stage of the cracks drawn on a block being broken
+pub fn create_destroy_stages_texture() -> u32 {
+    let array_texture = create_array_texture(DESTROY_STAGES as i32);
+    for stage in 0..DESTROY_STAGES {
+        let image = read_image(&format!("textures/blocks/destroy_stage_{}.png", stage));
+        blit_image_to_texture(&image, array_texture, stage as i32);
+    }
+    array_texture
+}
+
 fn create_array_texture(layers: i32) -> u32 {
     let mut item_array_texture: u32 = 0;
     gl_call!(gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut item_array_texture));
@@ -51,7 +89,8 @@ fn create_array_texture(layers: i32) -> u32 {
     item_array_texture
 }
 
-fn create_face_uvs_map(array_texture: u32, face_images: HashMap<BlockID, BlockFaces<&str>>) -> HashMap<BlockID, BlockFaces<TextureLayer>> {
+/// Returns the layers of the faces of every block and the first layer left unused
+fn create_face_uvs_map(array_texture: u32, face_images: HashMap<BlockID, BlockFaces<&str>>) -> (HashMap<BlockID, BlockFaces<TextureLayer>>, TextureLayer) {
     // Load all the images and fill the UV map for all the blocks
     // TODO don't load the same texture multiple times if reused for another block
 
@@ -92,10 +131,35 @@ fn create_face_uvs_map(array_texture: u32, face_images: HashMap<BlockID, BlockFa
             }
         }
     }
-    face_uvs
+    (face_uvs, layer)
+}
+
+/// Puts the icons of the items which are not blocks into the array texture, starting at `first_layer`
+fn create_item_icons_map(array_texture: u32, first_layer: TextureLayer) -> ItemIcons {
+    let mut item_icons = ItemIcons::new();
+    for (i, item) in Item::NON_BLOCK_ITEMS.iter().enumerate() {
+        let layer = first_layer + i as TextureLayer;
+        blit_image_to_texture(&read_image(item.icon_path().unwrap()), array_texture, layer as i32);
+        item_icons.insert(*item, layer);
+    }
+    item_icons
+}
+
+/// Puts the skin and the face of every kind of mob into the array texture, starting at `first_layer`
+fn create_mob_textures_map(array_texture: u32, first_layer: TextureLayer) -> MobTextures {
+    let mut mob_textures = MobTextures::new();
+    for (i, kind) in MobKind::ALL.iter().enumerate() {
+        let (skin_path, face_path) = kind.texture_paths();
+        let skin_layer = first_layer + 2 * i as TextureLayer;
+        blit_image_to_texture(&read_image(skin_path), array_texture, skin_layer as i32);
+        blit_image_to_texture(&read_image(face_path), array_texture, skin_layer as i32 + 1);
+        mob_textures.insert(*kind, (skin_layer, skin_layer + 1));
+    }
+    mob_textures
 }
 
-fn read_image(image_path: &str) -> DynamicImage {
+/// Reads an image with the y axis flipped for OpenGL
+pub fn read_image(image_path: &str) -> DynamicImage {
     let img = match image::open(image_path) {
         Ok(img) => img.flipv(), // We flip the y axis for OpenGL
         Err(err) => panic!("Filename: {}, error: {}", image_path, err.to_string())
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 use crate::chunk::BlockID;
+use crate::inventory::item::Item;
+use crate::mob::MobKind;
 use crate::block_texture_faces::BlockFaces;
 use crate::particle_system::ParticleSystem;
 use crate::shader_compilation::ShaderProgram;
@@ -7,5 +9,9 @@ use crate::shader_compilation::ShaderProgram;
 pub type TextureLayer = u32;
 pub type UVFaces = (TextureLayer, TextureLayer, TextureLayer, TextureLayer, TextureLayer, TextureLayer);
 pub type TexturePack = HashMap<BlockID, BlockFaces<TextureLayer>>;
+// Layer of the array texture holding the icon of each item which isn't a block
+pub type ItemIcons = HashMap<Item, TextureLayer>;
+// Layers of the skin and of the face of each kind of mob
+pub type MobTextures = HashMap<MobKind, (TextureLayer, TextureLayer)>;
 pub type ParticleSystems = HashMap<&'static str, ParticleSystem>;
 pub type Shaders = HashMap<&'static str, ShaderProgram>;
\ No newline at end of file
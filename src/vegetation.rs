@@ -0,0 +1,164 @@
+use std::collections::{HashSet, VecDeque};
+
+use rand::{Rng, thread_rng};
+
+use crate::block_tick::{BlockBehaviour, TickContext};
+use crate::chunk::BlockID;
+use crate::chunk_manager::ChunkManager;
+use crate::dropped_item::spawn_block_drop;
+use crate::inventory::item::{Item, ItemStack};
+
+// Leaves placed by the player never decay
+const PERSISTENT_LEAVES: u8 = 1;
+// Leaves further than this from a log, walking through the leaves, decay
+const LEAF_DECAY_DISTANCE: u32 = 6;
+// One decaying leaf in 20 drops a sapling, one in 200 an apple
+const SAPLING_DROP_CHANCE: f64 = 0.05;
+const APPLE_DROP_CHANCE: f64 = 0.005;
+// A sapling grows in two stages, each random tick has this chance to make it grow by one
+const SAPLING_GROWTH_CHANCE: f64 = 1.0 / 7.0;
+const OAK_TREE_HEIGHT: i32 = 5;
+
+/// The blocks of an oak growing on the ground block at (x, y, z)
+pub fn oak_tree(x: i32, y: i32, z: i32) -> Vec<(BlockID, (i32, i32, i32))> {
+    let h = OAK_TREE_HEIGHT;
+    let mut blocks = Vec::new();
+    for i in y + 1..y + 1 + h {
+        blocks.push((BlockID::OakLog, (x, i, z)));
+    }
+
+    for yy in y + h - 2..=y + h - 1 {
+        for xx in x - 2..=x + 2 {
+            for zz in z - 2..=z + 2 {
+                if xx != x || zz != z {
+                    blocks.push((BlockID::OakLeaves, (xx, yy, zz)));
+                }
+            }
+        }
+    }
+
+    for xx in x - 1..=x + 1 {
+        for zz in z - 1..=z + 1 {
+            if xx != x || zz != z {
+                blocks.push((BlockID::OakLeaves, (xx, y + h, zz)));
+            }
+        }
+    }
+
+    for &(dx, dz) in &[(0, 0), (1, 0), (-1, 0), (0, 1), (0, -1)] {
+        blocks.push((BlockID::OakLeaves, (x + dx, y + h + 1, z + dz)));
+    }
+    blocks
+}
+
+/// Grass spreads to the dirt nearby, and dies under opaque blocks and fluids
+pub struct GrassSpread;
+
+fn is_covered(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> bool {
+    chunk_manager.get_block(x, y + 1, z)
+        .filter(|block| block.is_opaque() || block.is_fluid())
+        .is_some()
+}
+
+impl BlockBehaviour for GrassSpread {
+    fn random_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        let chunk_manager = context.chunk_manager;
+        if is_covered(chunk_manager, x, y, z) {
+            chunk_manager.put_block(BlockID::Dirt, x, y, z);
+            return;
+        }
+
+        let mut rng = thread_rng();
+        for _ in 0..4 {
+            let (x, y, z) = (
+                x + rng.gen_range(-1, 2),
+                y + rng.gen_range(-3, 2),
+                z + rng.gen_range(-1, 2));
+            if chunk_manager.get_block(x, y, z) == Some(BlockID::Dirt) && !is_covered(chunk_manager, x, y, z) {
+                chunk_manager.put_block(BlockID::GrassBlock, x, y, z);
+            }
+        }
+    }
+}
+
+/// Leaves which aren't held by a log anymore decay, sometimes dropping a sapling or an apple
+pub struct LeafDecay;
+
+/// Whether a log can be reached from the leaves at (x, y, z) through other leaves
+fn is_near_log(chunk_manager: &ChunkManager, x: i32, y: i32, z: i32) -> bool {
+    let mut visited = HashSet::new();
+    let mut queue = VecDeque::new();
+    visited.insert((x, y, z));
+    queue.push_back(((x, y, z), 0));
+    while let Some(((x, y, z), distance)) = queue.pop_front() {
+        let neighbours = [
+            (x + 1, y, z), (x - 1, y, z),
+            (x, y + 1, z), (x, y - 1, z),
+            (x, y, z + 1), (x, y, z - 1),
+        ];
+        for &position in &neighbours {
+            match chunk_manager.get_block(position.0, position.1, position.2) {
+                Some(BlockID::OakLog) => return true,
+                // Unloaded chunks may hold the log
+                None => return true,
+                Some(BlockID::OakLeaves) if distance + 1 < LEAF_DECAY_DISTANCE && visited.insert(position) => {
+                    queue.push_back((position, distance + 1));
+                }
+                _ => {}
+            }
+        }
+    }
+    false
+}
+
+impl BlockBehaviour for LeafDecay {
+    fn random_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        let chunk_manager = context.chunk_manager;
+        if chunk_manager.get_block_state(x, y, z) == Some(PERSISTENT_LEAVES) || is_near_log(chunk_manager, x, y, z) {
+            return;
+        }
+
+        chunk_manager.put_block(BlockID::Air, x, y, z);
+        let mut rng = thread_rng();
+        if rng.gen_bool(SAPLING_DROP_CHANCE) {
+            spawn_block_drop(context.lazy_update, context.entities, ItemStack::new(1, Item::Block(BlockID::OakSapling)), (x, y, z));
+        }
+        if rng.gen_bool(APPLE_DROP_CHANCE) {
+            spawn_block_drop(context.lazy_update, context.entities, ItemStack::new(1, Item::Apple), (x, y, z));
+        }
+    }
+
+    fn placed_state(&self, _facing: (i32, i32)) -> u8 {
+        PERSISTENT_LEAVES
+    }
+}
+
+/// Saplings grow into an oak when there is enough room above them
+pub struct SaplingGrowth;
+
+impl BlockBehaviour for SaplingGrowth {
+    fn random_tick(&self, context: &TickContext, x: i32, y: i32, z: i32) {
+        let chunk_manager = context.chunk_manager;
+        if !thread_rng().gen_bool(SAPLING_GROWTH_CHANCE) {
+            return;
+        }
+        // The state is the growth stage
+        if chunk_manager.get_block_state(x, y, z) == Some(0) {
+            chunk_manager.put_block_with_state(BlockID::OakSapling, 1, x, y, z);
+            return;
+        }
+
+        let tree = oak_tree(x, y - 1, z);
+        let has_room = tree.iter().all(|&(_, (x, y, z))| {
+            match chunk_manager.get_block(x, y, z) {
+                Some(block) => block.is_air() || block == BlockID::OakLeaves || block == BlockID::OakSapling || block == BlockID::Snow,
+                None => false,
+            }
+        });
+        if has_room {
+            for (block, (x, y, z)) in tree {
+                chunk_manager.put_block(block, x, y, z);
+            }
+        }
+    }
+}
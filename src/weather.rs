@@ -0,0 +1,199 @@
+use std::time::{Duration, Instant};
+
+use noise::{NoiseFn, Point2, Seedable, SuperSimplex};
+use rand::{Rng, thread_rng};
+
+use crate::constants::WORLD_SEED;
+
+// Time taken by the previous weather to fade out when the weather changes
+const TRANSITION_DURATION: f32 = 10.0;
+// Below this temperature, the rain falls as snow and covers the ground
+pub const FREEZING_TEMPERATURE: f32 = 0.15;
+// Altitude above which it gets colder, and how much colder every block
+const TEMPERATURE_ALTITUDE: i32 = 120;
+const TEMPERATURE_DROP_PER_BLOCK: f32 = 0.0125;
+// Width of the warm and cold regions of the world, in blocks
+const TEMPERATURE_SCALE: f64 = 512.0;
+// Chance of a lightning strike every second of a thunderstorm, and how long its flash lasts
+const LIGHTNING_STRIKES_PER_SECOND: f32 = 0.1;
+const LIGHTNING_FLASH_DURATION: f32 = 0.3;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WeatherKind {
+    Clear,
+    Rain,
+    Snow,
+    Thunder,
+}
+
+impl WeatherKind {
+    pub fn has_precipitation(&self) -> bool {
+        *self != WeatherKind::Clear
+    }
+
+    /// Shortest and longest time the weather lasts before changing on its own, in seconds
+    fn duration_range(&self) -> (u64, u64) {
+        match self {
+            WeatherKind::Clear => (5 * 60, 15 * 60),
+            WeatherKind::Rain |
+            WeatherKind::Snow => (2 * 60, 6 * 60),
+            WeatherKind::Thunder => (60, 4 * 60),
+        }
+    }
+
+    /// The sky clears up between two showers
+    fn next<R: Rng>(&self, rng: &mut R) -> WeatherKind {
+        match self {
+            WeatherKind::Clear => match rng.gen_range(0, 4) {
+                0 | 1 => WeatherKind::Rain,
+                2 => WeatherKind::Snow,
+                _ => WeatherKind::Thunder,
+            },
+            _ => WeatherKind::Clear,
+        }
+    }
+
+    /// How much the clouds darken the sky and the world
+    fn sky_darkness(&self) -> f32 {
+        match self {
+            WeatherKind::Clear => 0.0,
+            WeatherKind::Rain => 0.4,
+            WeatherKind::Snow => 0.3,
+            WeatherKind::Thunder => 0.65,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Precipitation {
+    Rain,
+    Snow,
+}
+
+/// The weather of the whole world, changing on its own after a random time
+/// Follows the global timer like `WorldTime`, so the weather doesn't change while the game is paused
+pub struct Weather {
+    kind: WeatherKind,
+    // Faded out during the transition to the current weather
+    previous_kind: WeatherKind,
+    // From 0 when the weather changes to 1 once the previous weather has faded out
+    transition: f32,
+    next_change: Instant,
+    lightning_flash: f32,
+    // There are no biomes, the temperature varies smoothly across the world
+    temperature_noise: SuperSimplex,
+    last_updated: Instant,
+}
+
+impl Default for Weather {
+    fn default() -> Self {
+        let now = Instant::now();
+        let mut weather = Weather {
+            kind: WeatherKind::Clear,
+            previous_kind: WeatherKind::Clear,
+            transition: 1.0,
+            next_change: now,
+            lightning_flash: 0.0,
+            temperature_noise: SuperSimplex::new().set_seed(*WORLD_SEED),
+            last_updated: now,
+        };
+        weather.next_change = now + weather.random_duration();
+        weather
+    }
+}
+
+impl Weather {
+    /// `now` is the time of the global timer
+    pub fn advance(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_updated).as_secs_f32();
+        self.last_updated = now;
+
+        self.transition = (self.transition + elapsed / TRANSITION_DURATION).min(1.0);
+        if now >= self.next_change {
+            let kind = self.kind.next(&mut thread_rng());
+            self.set(kind, None);
+        }
+
+        self.lightning_flash = (self.lightning_flash - elapsed / LIGHTNING_FLASH_DURATION).max(0.0);
+        if self.kind == WeatherKind::Thunder
+            && thread_rng().gen::<f32>() < LIGHTNING_STRIKES_PER_SECOND * elapsed * self.transition {
+            self.lightning_flash = 1.0;
+        }
+    }
+
+    /// Changes the weather for `duration`, or for a random time
+    pub fn set(&mut self, kind: WeatherKind, duration: Option<Duration>) {
+        if kind != self.kind {
+            self.previous_kind = self.kind;
+            self.transition = 0.0;
+            self.kind = kind;
+        }
+        self.next_change = self.last_updated + duration.unwrap_or_else(|| self.random_duration());
+    }
+
+    fn random_duration(&self) -> Duration {
+        let (min, max) = self.kind.duration_range();
+        Duration::from_secs(thread_rng().gen_range(min, max + 1))
+    }
+
+    pub fn kind(&self) -> WeatherKind {
+        self.kind
+    }
+
+    /// Time left before the weather changes
+    pub fn remaining_duration(&self) -> Duration {
+        self.next_change.saturating_duration_since(self.last_updated)
+    }
+
+    /// The weather whose rain or snow is falling, the previous one while it fades out
+    fn falling_kind(&self) -> Option<WeatherKind> {
+        if self.kind.has_precipitation() {
+            Some(self.kind)
+        } else if self.previous_kind.has_precipitation() && self.transition < 1.0 {
+            Some(self.previous_kind)
+        } else {
+            None
+        }
+    }
+
+    /// From 0 when nothing falls to 1 during a whole shower
+    pub fn precipitation_strength(&self) -> f32 {
+        match (self.previous_kind.has_precipitation(), self.kind.has_precipitation()) {
+            (false, false) => 0.0,
+            (false, true) => self.transition,
+            (true, false) => 1.0 - self.transition,
+            (true, true) => 1.0,
+        }
+    }
+
+    /// What falls at a position, snow where it is cold enough
+    pub fn precipitation_at(&self, x: i32, y: i32, z: i32) -> Option<Precipitation> {
+        self.falling_kind().map(|kind| {
+            if kind == WeatherKind::Snow || self.temperature(x, y, z) < FREEZING_TEMPERATURE {
+                Precipitation::Snow
+            } else {
+                Precipitation::Rain
+            }
+        })
+    }
+
+    pub fn sky_darkness(&self) -> f32 {
+        let previous = self.previous_kind.sky_darkness();
+        previous + (self.kind.sky_darkness() - previous) * self.transition
+    }
+
+    /// From 1 when lightning strikes, quickly fading out to 0
+    pub fn lightning_flash(&self) -> f32 {
+        self.lightning_flash
+    }
+
+    /// Around 0 in cold regions and 1 in warm ones, getting colder higher up
+    pub fn temperature(&self, x: i32, y: i32, z: i32) -> f32 {
+        let noise = self.temperature_noise.get(Point2::from([
+            x as f64 / TEMPERATURE_SCALE,
+            z as f64 / TEMPERATURE_SCALE,
+        ])) as f32;
+        let altitude_drop = (y - TEMPERATURE_ALTITUDE).max(0) as f32 * TEMPERATURE_DROP_PER_BLOCK;
+        0.5 + 0.5 * noise - altitude_drop
+    }
+}
@@ -25,6 +25,7 @@ pub fn create_window(width: u32, height: u32, title: &str) -> (Glfw, Window, Rec
     // Make the window's context current
     window.make_current();
     window.set_key_polling(true);
+    window.set_char_polling(true);
     window.set_cursor_pos_polling(true);
     window.set_raw_mouse_motion(true);
     window.set_mouse_button_polling(true);
@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+use crate::constants::DAY_LENGTH;
+
+pub const TICKS_PER_DAY: u64 = 24000;
+
+/// Time of the world in ticks, following the global timer so it stops while the game is paused
+/// A day starts at sunrise: noon is at 6000 ticks and midnight at 18000
+/// The world is not saved yet, so every game starts on the first morning
+pub struct WorldTime {
+    ticks: f64,
+    // Real time taken by a day
+    day_length: Duration,
+    last_updated: Instant,
+}
+
+impl Default for WorldTime {
+    fn default() -> Self {
+        Self::new(*DAY_LENGTH)
+    }
+}
+
+impl WorldTime {
+    pub fn new(day_length: Duration) -> Self {
+        WorldTime {
+            ticks: 0.0,
+            day_length,
+            last_updated: Instant::now(),
+        }
+    }
+
+    /// `now` is the time of the global timer
+    pub fn advance(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_updated).as_secs_f64();
+        self.ticks += elapsed / self.day_length.as_secs_f64() * TICKS_PER_DAY as f64;
+        self.last_updated = now;
+    }
+
+    /// Ticks since the creation of the world
+    pub fn ticks(&self) -> u64 {
+        self.ticks as u64
+    }
+
+    pub fn day(&self) -> u64 {
+        self.ticks() / TICKS_PER_DAY
+    }
+
+    /// Ticks since the start of the current day
+    pub fn time_of_day(&self) -> u64 {
+        self.ticks() % TICKS_PER_DAY
+    }
+
+    /// How far along the current day is, from 0 to 1, between two ticks too
+    pub fn day_fraction(&self) -> f32 {
+        ((self.ticks % TICKS_PER_DAY as f64) / TICKS_PER_DAY as f64) as f32
+    }
+
+    /// Goes to a time of the current day
+    pub fn set_time_of_day(&mut self, time_of_day: u64) {
+        self.ticks = (self.day() * TICKS_PER_DAY + time_of_day) as f64;
+    }
+
+    pub fn add_ticks(&mut self, ticks: u64) {
+        self.ticks += ticks as f64;
+    }
+}